@@ -0,0 +1,1621 @@
+//! Vehicle profile generation
+//!
+//! Turns the `src/vehicles/*.toml` profile files into the constants
+//! included by the `src/vehicles/*.rs` modules.
+//!
+//! The step ranges of the spoof signals are derived from their voltages,
+//! rounded inwards so that every value of a range stays within the
+//! voltages. They are runtime data of the `VehicleProfile`.
+//!
+//! The build fails when a profile is inconsistent:
+//! - a spoof signal voltage range that is empty or outside of the DAC
+//! - a sensor plausibility model that is empty or reaches the stuck high
+//!   threshold
+//! - an override or interlock threshold that a plausible sensor reading
+//!   can't reach, or a brake light threshold outside of its spoof range
+//! - a throttle interlock brake threshold at or above the brake override
+//!   threshold, the brake would be overridden before the throttle is
+//!   interlocked
+//! - a parameter default outside of its runtime tuning limits, or limits
+//!   that let a threshold leave the range a plausible reading reaches
+//! - a torque speed schedule that isn't sorted by speed or exceeds the
+//!   torque command range
+//! - speed control pedal thresholds that aren't ordered brake engage,
+//!   brake release, throttle release, throttle engage
+
+extern crate toml;
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use toml::Value;
+
+/// Vehicle profile files in `src/vehicles`, generated into
+/// `$OUT_DIR/<name>.rs`
+const PROFILES: [&str; 4] = ["kial_soul_ev", "kial_niro", "kial_soul_petrol", "bench"];
+
+/// Must match `STEPS_PER_VOLT` of the vehicle modules. \[steps/volt\]
+const STEPS_PER_VOLT: f64 = 819.2;
+
+/// It's a 12 bit DAC. \[steps\]
+const DAC_MAX: f64 = 4095.0;
+
+/// Largest standard CAN ID
+const CAN_ID_MAX: i64 = 0x7FF;
+
+/// Range of the OBD data staleness timeouts, the OBD frames are sent
+/// every 10 to 100 ms \[ms\]
+const OBD_TIMEOUT_MIN: i64 = 20;
+const OBD_TIMEOUT_MAX: i64 = 1000;
+
+/// Must match `steering_module::FILTER_ALPHA`, the default of
+/// `Param::SteeringFilterAlpha`
+const STEERING_FILTER_ALPHA: f64 = 0.01;
+
+/// Range of the brake and throttle commands, see `MINIMUM_BRAKE_COMMAND`
+/// and `MAXIMUM_BRAKE_COMMAND`
+const PEDAL_COMMAND_MIN: f64 = 0.0;
+const PEDAL_COMMAND_MAX: f64 = 1.0;
+
+/// Output range of the speed control PID controller, see
+/// `SpeedController::new`
+const SPEED_CONTROL_OUTPUT_MIN: f64 = -1.0;
+const SPEED_CONTROL_OUTPUT_MAX: f64 = 1.0;
+
+/// Rounding slack of the derived ranges, a voltage that converts to an
+/// exact number of steps keeps it
+const STEPS_EPSILON: f64 = 1e-6;
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is not set");
+    let mut failures = String::new();
+
+    for name in PROFILES.iter() {
+        let path = format!("src/vehicles/{}.toml", name);
+        println!("cargo:rerun-if-changed={}", path);
+
+        let source =
+            fs::read_to_string(&path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e));
+        let profile: Value = source
+            .parse()
+            .unwrap_or_else(|e| panic!("Failed to parse {}: {}", path, e));
+
+        let mut generator = Generator::new(&path);
+        generator.profile(&profile);
+
+        if generator.errors.is_empty() {
+            fs::write(
+                Path::new(&out_dir).join(format!("{}.rs", name)),
+                generator.code,
+            )
+            .unwrap_or_else(|e| panic!("Failed to write the {} profile: {}", name, e));
+        } else {
+            for error in generator.errors.iter() {
+                writeln!(failures, "{}: {}", path, error).unwrap();
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        panic!("Inconsistent vehicle profile\n{}", failures);
+    }
+}
+
+/// Range a plausible reading reaches, what the override and interlock
+/// thresholds and their runtime tuning limits are compared to
+struct PlausibleRange {
+    name: &'static str,
+    min: f64,
+    max: f64,
+}
+
+/// Plausible ranges of the two channels of a sensor \[steps\]
+struct ChannelRanges {
+    high_min: i64,
+    high_max: i64,
+    low_min: i64,
+    low_max: i64,
+}
+
+impl ChannelRanges {
+    /// Range of the channel average
+    fn average(&self) -> PlausibleRange {
+        PlausibleRange {
+            name: "plausible sensor average",
+            min: (self.high_min + self.low_min) as f64 / 2.0,
+            max: (self.high_max + self.low_max) as f64 / 2.0,
+        }
+    }
+
+    /// Range of the absolute channel difference
+    fn difference(&self) -> PlausibleRange {
+        PlausibleRange {
+            name: "plausible sensor difference",
+            min: (self.high_min - self.low_max)
+                .max(self.low_min - self.high_max)
+                .max(0) as f64,
+            max: (self.high_max - self.low_min).max(self.low_max - self.high_min) as f64,
+        }
+    }
+}
+
+/// Derived range of a spoof signal \[steps\]
+struct StepRange {
+    min: u32,
+    max: u32,
+}
+
+struct Generator {
+    code: String,
+    errors: Vec<String>,
+}
+
+impl Generator {
+    fn new(path: &str) -> Self {
+        let mut code = String::new();
+        writeln!(code, "// Generated by build.rs from {}, do not edit", path).unwrap();
+
+        Generator {
+            code,
+            errors: Vec::new(),
+        }
+    }
+
+    fn error(&mut self, message: String) {
+        self.errors.push(message);
+    }
+
+    fn check(&mut self, condition: bool, message: String) {
+        if !condition {
+            self.error(message);
+        }
+    }
+
+    fn value<'a>(&mut self, table: &'a Value, section: &str, key: &str) -> Option<&'a Value> {
+        let value = table.get(key);
+
+        if value.is_none() {
+            self.error(format!("missing `{}{}`", section, key));
+        }
+
+        value
+    }
+
+    fn table<'a>(&mut self, table: &'a Value, section: &str, key: &str) -> &'a Value {
+        static EMPTY: Value = Value::Boolean(false);
+
+        match self.value(table, section, key) {
+            Some(value) if value.is_table() => value,
+            Some(_) => {
+                self.error(format!("`{}{}` is not a table", section, key));
+                &EMPTY
+            }
+            None => &EMPTY,
+        }
+    }
+
+    fn float(&mut self, table: &Value, section: &str, key: &str) -> f64 {
+        match self.value(table, section, key).map(number) {
+            Some(Some(value)) => value,
+            Some(None) => {
+                self.error(format!("`{}{}` is not a number", section, key));
+                0.0
+            }
+            None => 0.0,
+        }
+    }
+
+    fn positive(&mut self, table: &Value, section: &str, key: &str) -> f64 {
+        let value = self.float(table, section, key);
+        self.check(
+            value > 0.0,
+            format!("`{}{}` must be positive", section, key),
+        );
+        value
+    }
+
+    fn integer(&mut self, table: &Value, section: &str, key: &str, max: i64) -> i64 {
+        self.integer_within(table, section, key, 0, max)
+    }
+
+    fn integer_within(
+        &mut self,
+        table: &Value,
+        section: &str,
+        key: &str,
+        min: i64,
+        max: i64,
+    ) -> i64 {
+        match self.value(table, section, key) {
+            Some(Value::Integer(value)) if (*value >= min) && (*value <= max) => *value,
+            Some(Value::Integer(value)) => {
+                self.error(format!(
+                    "`{}{}` = {} is outside of [{}, {}]",
+                    section, key, value, min, max
+                ));
+                0
+            }
+            Some(_) => {
+                self.error(format!("`{}{}` is not an integer", section, key));
+                0
+            }
+            None => 0,
+        }
+    }
+
+    fn string(&mut self, table: &Value, section: &str, key: &str) -> String {
+        match self.value(table, section, key) {
+            Some(Value::String(value)) => value.clone(),
+            Some(_) => {
+                self.error(format!("`{}{}` is not a string", section, key));
+                String::new()
+            }
+            None => String::new(),
+        }
+    }
+
+    fn emit(&mut self, doc: &str, name: &str, ty: &str, value: &str) {
+        writeln!(self.code).unwrap();
+        for line in doc.lines() {
+            writeln!(self.code, "/// {}", line).unwrap();
+        }
+        writeln!(self.code, "pub const {}: {} = {};", name, ty, value).unwrap();
+    }
+
+    fn profile(&mut self, profile: &Value) {
+        let id = self.integer(profile, "", "id", i64::from(u8::max_value()));
+        self.emit(
+            "ID of the vehicle profile.",
+            "PROFILE_ID",
+            "u8",
+            &id.to_string(),
+        );
+
+        let name = self.string(profile, "", "name");
+        self.emit(
+            "Name of the vehicle profile.",
+            "PROFILE_NAME",
+            "&str",
+            &format!("{:?}", name),
+        );
+
+        let fault_hysteresis =
+            self.integer(profile, "", "fault_hysteresis", i64::from(u32::max_value()));
+        self.check(
+            fault_hysteresis > 0,
+            "`fault_hysteresis` must be positive".to_string(),
+        );
+        self.emit(
+            "Length of time in ms for delay of signal reads to ensure fault is\n\
+             outside the range of noise in the signal.",
+            "FAULT_HYSTERESIS",
+            "u32",
+            &fault_hysteresis.to_string(),
+        );
+
+        let stuck_high_threshold = self.integer(
+            profile,
+            "",
+            "sensor_stuck_high_threshold",
+            i64::from(u16::max_value()),
+        );
+        self.emit(
+            "ADC reading at or above which a sensor channel is considered\n\
+             stuck high (shorted to the 5 volt sensor supply). \\[steps\\]",
+            "SENSOR_STUCK_HIGH_THRESHOLD",
+            "u16",
+            &stuck_high_threshold.to_string(),
+        );
+
+        let fault_hysteresis_limits = self.limits(
+            profile,
+            "",
+            "fault_hysteresis",
+            fault_hysteresis as f64,
+            "Runtime tuning limits of `FAULT_HYSTERESIS`. \\[ms\\]",
+            "FAULT_HYSTERESIS_LIMITS",
+        );
+        self.check(
+            fault_hysteresis_limits.0 > 0.0,
+            "`fault_hysteresis_limits.min` must be positive".to_string(),
+        );
+
+        let obd = self.table(profile, "", "obd");
+        self.obd(obd);
+
+        let brake = self.table(profile, "", "brake");
+        let brake_pedal = if brake.get("pedal_position_sensor").is_some() {
+            Some(self.spoofed_brake(brake, stuck_high_threshold))
+        } else {
+            self.pressure_brake(brake, stuck_high_threshold);
+            None
+        };
+        self.slew_rate_limit(brake, "brake.", "BRAKE", "brake", "position");
+        self.rearm_policy(brake, "brake.", "BRAKE", "brake");
+
+        let throttle = self.table(profile, "", "throttle");
+        self.throttle(throttle, stuck_high_threshold);
+
+        let steering = self.table(profile, "", "steering");
+        self.steering(steering, stuck_high_threshold);
+
+        let speed_control = self.table(profile, "", "speed_control");
+        self.speed_control(speed_control);
+
+        if let Some(brake_pedal) = brake_pedal {
+            self.check(
+                brake_pedal.0 < brake_pedal.1,
+                format!(
+                    "`brake.throttle_interlock_threshold` = {} must be below \
+                     `brake.pedal_override_threshold` = {}",
+                    brake_pedal.0, brake_pedal.1
+                ),
+            );
+        }
+    }
+
+    fn obd(&mut self, obd: &Value) {
+        let wheel_speed_can_id = self.integer(obd, "obd.", "wheel_speed_can_id", CAN_ID_MAX);
+        self.emit(
+            "ID of the OBD wheel speed CAN frame.",
+            "OBD_WHEEL_SPEED_CAN_ID",
+            "u16",
+            &format!("0x{:X}", wheel_speed_can_id),
+        );
+
+        let wheel_speed_scalar = self.positive(obd, "obd.", "wheel_speed_scalar");
+        self.emit(
+            "Factor to scale OBD wheel speed to kilometers per hour",
+            "OBD_WHEEL_SPEED_SCALAR",
+            "f32",
+            &format!("{:?}", wheel_speed_scalar),
+        );
+
+        let wheel_speed_timeout = self.obd_timeout(obd, "wheel_speed_timeout");
+        self.emit(
+            "Length of time after which the OBD wheel speed data is\n\
+             considered stale. \\[ms\\]",
+            "OBD_WHEEL_SPEED_TIMEOUT",
+            "u32",
+            &wheel_speed_timeout.to_string(),
+        );
+
+        let steering_wheel_angle_can_id =
+            self.integer(obd, "obd.", "steering_wheel_angle_can_id", CAN_ID_MAX);
+        self.emit(
+            "ID of the OBD steering wheel angle CAN frame.",
+            "OBD_STEERING_WHEEL_ANGLE_CAN_ID",
+            "u16",
+            &format!("0x{:X}", steering_wheel_angle_can_id),
+        );
+
+        let steering_wheel_angle_scalar = self.positive(obd, "obd.", "steering_wheel_angle_scalar");
+        self.emit(
+            "Factor to scale OBD steering wheel angle to degrees.",
+            "OBD_STEERING_WHEEL_ANGLE_SCALAR",
+            "f32",
+            &format!("{:?}", steering_wheel_angle_scalar),
+        );
+
+        let steering_wheel_angle_timeout = self.obd_timeout(obd, "steering_wheel_angle_timeout");
+        self.emit(
+            "Length of time after which the OBD steering wheel angle data\n\
+             is considered stale. \\[ms\\]",
+            "OBD_STEERING_WHEEL_ANGLE_TIMEOUT",
+            "u32",
+            &steering_wheel_angle_timeout.to_string(),
+        );
+
+        let brake_pressure_can_id = self.integer(obd, "obd.", "brake_pressure_can_id", CAN_ID_MAX);
+        self.emit(
+            "ID of the OBD brake pressure CAN frame.",
+            "OBD_BRAKE_PRESSURE_CAN_ID",
+            "u16",
+            &format!("0x{:X}", brake_pressure_can_id),
+        );
+
+        let throttle_pressure_can_id = if obd.get("throttle_pressure_can_id").is_some() {
+            let id = self.integer(obd, "obd.", "throttle_pressure_can_id", CAN_ID_MAX);
+            format!("Some(0x{:X})", id)
+        } else {
+            "None".to_string()
+        };
+        self.emit(
+            "ID of the OBD throttle pressure CAN frame, if the vehicle has one.",
+            "OBD_THROTTLE_PRESSURE_CAN_ID",
+            "Option<u16>",
+            &throttle_pressure_can_id,
+        );
+    }
+
+    /// OBD data staleness timeout \[ms\]
+    fn obd_timeout(&mut self, obd: &Value, key: &str) -> i64 {
+        let timeout = self.integer(obd, "obd.", key, OBD_TIMEOUT_MAX);
+        self.check(
+            timeout >= OBD_TIMEOUT_MIN,
+            format!(
+                "`obd.{}` = {} is below the minimum of {} ms",
+                key, timeout, OBD_TIMEOUT_MIN
+            ),
+        );
+        timeout
+    }
+
+    /// Returns the throttle interlock and override thresholds
+    fn spoofed_brake(&mut self, brake: &Value, stuck_high_threshold: i64) -> (i64, i64) {
+        let low = self.spoof_signal(brake, "brake", "spoof_low_signal", "BRAKE", "low");
+        let high = self.spoof_signal(brake, "brake", "spoof_high_signal", "BRAKE", "high");
+
+        let sensor = self
+            .plausibility(
+                brake,
+                "brake",
+                "pedal_position_sensor",
+                "Plausibility model of the brake pedal position sensor channels.",
+                "BRAKE_PEDAL_POSITION_SENSOR_PLAUSIBILITY",
+                stuck_high_threshold,
+            )
+            .average();
+
+        let override_threshold = self.threshold(
+            brake,
+            "brake.",
+            "pedal_override_threshold",
+            &sensor,
+            i64::from(u16::max_value()),
+            "Runtime tuning limits of `BRAKE_PEDAL_OVERRIDE_THRESHOLD`. \\[steps\\]",
+            "BRAKE_PEDAL_OVERRIDE_THRESHOLD_LIMITS",
+        );
+        self.emit(
+            "Value of the brake position that indicates operator\noverride. \\[steps\\]",
+            "BRAKE_PEDAL_OVERRIDE_THRESHOLD",
+            "u16",
+            &override_threshold.to_string(),
+        );
+
+        let interlock_threshold = self.threshold(
+            brake,
+            "brake.",
+            "throttle_interlock_threshold",
+            &sensor,
+            i64::from(u32::max_value()),
+            "Runtime tuning limits of `THROTTLE_INTERLOCK_BRAKE_PEDAL_THRESHOLD`.\n\\[steps\\]",
+            "THROTTLE_INTERLOCK_BRAKE_PEDAL_THRESHOLD_LIMITS",
+        );
+        self.emit(
+            "Measured brake pedal position above which the throttle is\n\
+             interlocked. \\[steps\\]",
+            "THROTTLE_INTERLOCK_BRAKE_PEDAL_THRESHOLD",
+            "u32",
+            &interlock_threshold.to_string(),
+        );
+
+        for (key, signal, range) in [
+            ("light_spoof_low_threshold", "LOW", &low),
+            ("light_spoof_high_threshold", "HIGH", &high),
+        ]
+        .iter()
+        {
+            let threshold = self.integer(brake, "brake.", key, i64::from(u16::max_value()));
+            self.check(
+                (threshold >= i64::from(range.min)) && (threshold <= i64::from(range.max)),
+                format!(
+                    "`brake.{}` = {} is outside of the spoof signal range [{}, {}]",
+                    key, threshold, range.min, range.max
+                ),
+            );
+            self.emit(
+                &format!(
+                    "Minimum value of the {} spoof signal that activates the brake\n\
+                     lights. \\[steps\\]",
+                    signal.to_lowercase()
+                ),
+                &format!("BRAKE_LIGHT_SPOOF_{}_THRESHOLD", signal),
+                "u16",
+                &threshold.to_string(),
+            );
+        }
+
+        (interlock_threshold, override_threshold)
+    }
+
+    fn pressure_brake(&mut self, brake: &Value, stuck_high_threshold: i64) {
+        let pressure_min = self.float(brake, "brake.", "pressure_min");
+        let pressure_max = self.float(brake, "brake.", "pressure_max");
+        self.check(
+            (pressure_min >= 0.0) && (pressure_min < pressure_max),
+            format!(
+                "`brake.pressure_min` = {} and `brake.pressure_max` = {} are not an \
+                 increasing positive range",
+                pressure_min, pressure_max
+            ),
+        );
+        self.emit(
+            "Minimum possible pressure of brake system. \\[decibars\\]",
+            "BRAKE_PRESSURE_MIN_IN_DECIBARS",
+            "f32",
+            &format!("{:?}", pressure_min),
+        );
+        self.emit(
+            "Maximum possible pressure of brake system. \\[decibars\\]",
+            "BRAKE_PRESSURE_MAX_IN_DECIBARS",
+            "f32",
+            &format!("{:?}", pressure_max),
+        );
+
+        let pressure = PlausibleRange {
+            name: "brake pressure range",
+            min: pressure_min,
+            max: pressure_max,
+        };
+
+        let override_threshold = self.float_threshold(
+            brake,
+            "brake.",
+            "pressure_override_threshold",
+            &pressure,
+            "Runtime tuning limits of `BRAKE_OVERRIDE_PEDAL_THRESHOLD_IN_DECIBARS`.\n\
+             \\[decibars\\]",
+            "BRAKE_OVERRIDE_PEDAL_THRESHOLD_IN_DECIBARS_LIMITS",
+        );
+        let interlock_threshold = self.float_threshold(
+            brake,
+            "brake.",
+            "throttle_interlock_pressure_threshold",
+            &pressure,
+            "Runtime tuning limits of\n\
+             `THROTTLE_INTERLOCK_BRAKE_PRESSURE_THRESHOLD_IN_DECIBARS`. \\[decibars\\]",
+            "THROTTLE_INTERLOCK_BRAKE_PRESSURE_THRESHOLD_IN_DECIBARS_LIMITS",
+        );
+
+        self.emit(
+            "Value of brake pressure that indicates operator override.\n\\[decibars\\]",
+            "BRAKE_OVERRIDE_PEDAL_THRESHOLD_IN_DECIBARS",
+            "f32",
+            &format!("{:?}", override_threshold),
+        );
+        self.emit(
+            "Measured brake pedal pressure above which the throttle is\n\
+             interlocked. \\[decibars\\]",
+            "THROTTLE_INTERLOCK_BRAKE_PRESSURE_THRESHOLD_IN_DECIBARS",
+            "f32",
+            &format!("{:?}", interlock_threshold),
+        );
+        self.check(
+            interlock_threshold < override_threshold,
+            format!(
+                "`brake.throttle_interlock_pressure_threshold` = {} must be below \
+                 `brake.pressure_override_threshold` = {}",
+                interlock_threshold, override_threshold
+            ),
+        );
+
+        let light_threshold = self.float(brake, "brake.", "light_pressure_threshold");
+        self.within(
+            "brake.",
+            "light_pressure_threshold",
+            light_threshold,
+            &pressure,
+        );
+        self.emit(
+            "Brake pressure threshold for when to enable the brake light.\n\\[decibars\\]",
+            "BRAKE_LIGHT_PRESSURE_THRESHOLD_IN_DECIBARS",
+            "f32",
+            &format!("{:?}", light_threshold),
+        );
+
+        self.accumulator(brake, &pressure);
+        self.pressure_sensor(brake, stuck_high_threshold);
+        self.pressure_pid(brake);
+    }
+
+    fn accumulator(&mut self, brake: &Value, pressure: &PlausibleRange) {
+        let accumulator = self.table(brake, "brake.", "accumulator");
+        let section = "brake.accumulator.";
+
+        let pressure_min = self.float(accumulator, section, "pressure_min");
+        let pressure_max = self.float(accumulator, section, "pressure_max");
+        self.check(
+            (pressure_min > pressure.min)
+                && (pressure_min < pressure_max)
+                && (pressure_max <= pressure.max),
+            format!(
+                "`{}pressure_min` = {} and `{}pressure_max` = {} are not an increasing \
+                 range within the brake pressure range ({}, {}]",
+                section, pressure_min, section, pressure_max, pressure.min, pressure.max
+            ),
+        );
+        self.emit(
+            "Minimum accumulator pressure. \\[decibars\\]",
+            "BRAKE_ACCUMULATOR_PRESSURE_MIN_IN_DECIBARS",
+            "f32",
+            &format!("{:?}", pressure_min),
+        );
+        self.emit(
+            "Maximum accumulator pressure. \\[decibars\\]",
+            "BRAKE_ACCUMULATOR_PRESSURE_MAX_IN_DECIBARS",
+            "f32",
+            &format!("{:?}", pressure_max),
+        );
+
+        let pump_on_time_max = self.integer(
+            accumulator,
+            section,
+            "pump_on_time_max",
+            i64::from(u32::max_value()),
+        );
+        self.check(
+            pump_on_time_max > 0,
+            format!("`{}pump_on_time_max` must be positive", section),
+        );
+        self.emit(
+            "Maximum time the accumulator pump may run without reaching the\n\
+             maximum accumulator pressure. \\[milliseconds\\]",
+            "BRAKE_ACCUMULATOR_PUMP_ON_TIME_MAX_IN_MILLISECONDS",
+            "u32",
+            &pump_on_time_max.to_string(),
+        );
+    }
+
+    fn pressure_sensor(&mut self, brake: &Value, stuck_high_threshold: i64) {
+        self.plausibility(
+            brake,
+            "brake",
+            "pressure_sensor",
+            "Plausibility model of the two master cylinder pressure sensors.",
+            "BRAKE_PRESSURE_SENSOR_PLAUSIBILITY",
+            stuck_high_threshold,
+        );
+
+        let scale = self.positive(brake, "brake.", "pressure_sensor_scale");
+        let offset = self.float(brake, "brake.", "pressure_sensor_offset");
+        self.emit(
+            "Scalar value converting a brake pressure sensor reading to a\n\
+             pressure. \\[decibars/step\\]",
+            "BRAKE_PRESSURE_SENSOR_SCALE",
+            "f32",
+            &format!("{:?}", scale),
+        );
+        self.emit(
+            "Offset value converting a brake pressure sensor reading to a\n\
+             pressure. \\[decibars\\]",
+            "BRAKE_PRESSURE_SENSOR_OFFSET",
+            "f32",
+            &format!("{:?}", offset),
+        );
+
+        let check = self.table(brake, "brake.", "pressure_sensor_check");
+        let section = "brake.pressure_sensor_check.";
+        let steps = i64::from(u16::max_value());
+
+        let value_min = self.integer(check, section, "value_min", steps);
+        let value_max = self.integer(check, section, "value_max", steps);
+        self.check(
+            (value_min <= value_max) && (value_max < stuck_high_threshold),
+            format!(
+                "`{}value_min` = {} and `{}value_max` = {} are not a range below the \
+                 stuck high threshold {}",
+                section, value_min, section, value_max, stuck_high_threshold
+            ),
+        );
+        self.emit(
+            "Minimum possible value expected to be read from the brake pressure\n\
+             sensors when the pressure check pins (PCK1/PCK2) are asserted.",
+            "BRAKE_PRESSURE_SENSOR_CHECK_VALUE_MIN",
+            "u16",
+            &value_min.to_string(),
+        );
+        self.emit(
+            "Maximum possible value expected to be read from the brake pressure\n\
+             sensors when the pressure check pins (PCK1/PCK2) are asserted.",
+            "BRAKE_PRESSURE_SENSOR_CHECK_VALUE_MAX",
+            "u16",
+            &value_max.to_string(),
+        );
+
+        let interval = self.integer(check, section, "interval", i64::from(u32::max_value()));
+        let settle_time = self.integer(check, section, "settle_time", i64::from(u32::max_value()));
+        self.check(
+            (settle_time > 0) && (settle_time < interval),
+            format!(
+                "`{}settle_time` = {} must be positive and below `{}interval` = {}",
+                section, settle_time, section, interval
+            ),
+        );
+        self.emit(
+            "Time between two pressure sensor checks, only performed while\n\
+             control is disabled. \\[milliseconds\\]",
+            "BRAKE_PRESSURE_SENSOR_CHECK_INTERVAL_IN_MILLISECONDS",
+            "u32",
+            &interval.to_string(),
+        );
+        self.emit(
+            "Time the pressure check pins are asserted before the sensors\n\
+             are read. \\[milliseconds\\]",
+            "BRAKE_PRESSURE_SENSOR_CHECK_SETTLE_TIME_IN_MILLISECONDS",
+            "u32",
+            &settle_time.to_string(),
+        );
+
+        let failure_limit =
+            self.integer(check, section, "failure_limit", i64::from(u8::max_value()));
+        self.check(
+            failure_limit > 0,
+            format!("`{}failure_limit` must be positive", section),
+        );
+        self.emit(
+            "Number of consecutive failed pressure sensor checks that\n\
+             indicate a fault.",
+            "BRAKE_PRESSURE_SENSOR_CHECK_FAILURE_LIMIT",
+            "u8",
+            &failure_limit.to_string(),
+        );
+    }
+
+    /// The brake pressure PID controller and the solenoids it drives
+    fn pressure_pid(&mut self, brake: &Value) {
+        self.pid(
+            brake,
+            "brake.",
+            "pid",
+            "brake pressure",
+            "BRAKE",
+            "duty cycle/decibar",
+        );
+
+        let pid = brake.get("pid").unwrap_or(brake);
+        let output_min = self.float(pid, "brake.pid.", "output_min");
+        let output_max = self.float(pid, "brake.pid.", "output_max");
+        self.emit(
+            "Minimum output value of PID to be within a valid pressure range.",
+            "BRAKE_PID_OUTPUT_MIN",
+            "f32",
+            &format!("{:?}", output_min),
+        );
+        self.emit(
+            "Maximum output value of PID to be within a valid pressure range.",
+            "BRAKE_PID_OUTPUT_MAX",
+            "f32",
+            &format!("{:?}", output_max),
+        );
+
+        let accumulator = self.solenoid(brake, "accumulator_solenoid", "ACCUMULATOR", "actuation");
+        let release = self.solenoid(brake, "release_solenoid", "RELEASE", "release");
+
+        // the PID output is bounded by the clamped ranges, the release
+        // solenoid takes the negative outputs
+        self.check(
+            (-release.1 < output_min)
+                && (output_min < 0.0)
+                && (output_max > 0.0)
+                && (output_max < accumulator.1),
+            format!(
+                "`brake.pid.output_min` = {} and `brake.pid.output_max` = {} must hold \
+                 zero within (-{}, {}), the release and accumulator solenoid \
+                 `clamped_max`",
+                output_min, output_max, release.1, accumulator.1
+            ),
+        );
+    }
+
+    /// Returns the clamped PID range of the solenoid
+    fn solenoid(&mut self, brake: &Value, key: &str, prefix: &str, name: &str) -> (f64, f64) {
+        let section = format!("brake.{}.", key);
+        let solenoid = self.table(brake, "brake.", key);
+
+        let clamped_min = self.float(solenoid, &section, "clamped_min");
+        let clamped_max = self.float(solenoid, &section, "clamped_max");
+        self.check(
+            (clamped_min >= 0.0) && (clamped_min < clamped_max),
+            format!(
+                "`{}clamped_min` = {} and `{}clamped_max` = {} are not an increasing \
+                 positive range",
+                section, clamped_min, section, clamped_max
+            ),
+        );
+        self.emit(
+            &format!("Minimum clamped PID value of the {} solenoid.", name),
+            &format!("BRAKE_PID_{}_SOLENOID_CLAMPED_MIN", prefix),
+            "f32",
+            &format!("{:?}", clamped_min),
+        );
+        self.emit(
+            &format!("Maximum clamped PID value of the {} solenoid.", name),
+            &format!("BRAKE_PID_{}_SOLENOID_CLAMPED_MAX", prefix),
+            "f32",
+            &format!("{:?}", clamped_max),
+        );
+
+        let duty_cycle_min = self.float(solenoid, &section, "duty_cycle_min");
+        let duty_cycle_max = self.float(solenoid, &section, "duty_cycle_max");
+        self.check(
+            (duty_cycle_min >= 0.0)
+                && (duty_cycle_min < duty_cycle_max)
+                && (duty_cycle_max <= f64::from(u8::max_value())),
+            format!(
+                "`{}duty_cycle_min` = {} and `{}duty_cycle_max` = {} are not an \
+                 increasing range of 8 bit duty cycles",
+                section, duty_cycle_min, section, duty_cycle_max
+            ),
+        );
+        self.emit(
+            &format!(
+                "Minimum duty cycle that begins to actuate the {} solenoid.\n\
+                 3.921 KHz PWM frequency",
+                name
+            ),
+            &format!("BRAKE_{}_SOLENOID_DUTY_CYCLE_MIN", prefix),
+            "f32",
+            &format!("{:?}", duty_cycle_min),
+        );
+        self.emit(
+            &format!(
+                "Maximum duty cycle where {} solenoid has reached its stop.\n\
+                 3.921 KHz PWM frequency",
+                name
+            ),
+            &format!("BRAKE_{}_SOLENOID_DUTY_CYCLE_MAX", prefix),
+            "f32",
+            &format!("{:?}", duty_cycle_max),
+        );
+
+        (clamped_min, clamped_max)
+    }
+
+    fn throttle(&mut self, throttle: &Value, stuck_high_threshold: i64) {
+        self.spoof_signal(throttle, "throttle", "spoof_low_signal", "THROTTLE", "low");
+        self.spoof_signal(
+            throttle,
+            "throttle",
+            "spoof_high_signal",
+            "THROTTLE",
+            "high",
+        );
+
+        let sensor = self
+            .plausibility(
+                throttle,
+                "throttle",
+                "accelerator_position_sensor",
+                "Plausibility model of the accelerator position sensor channels.",
+                "ACCELERATOR_POSITION_SENSOR_PLAUSIBILITY",
+                stuck_high_threshold,
+            )
+            .average();
+
+        let override_threshold = self.threshold(
+            throttle,
+            "throttle.",
+            "accelerator_override_threshold",
+            &sensor,
+            i64::from(u32::max_value()),
+            "Runtime tuning limits of `ACCELERATOR_OVERRIDE_THRESHOLD`. \\[steps\\]",
+            "ACCELERATOR_OVERRIDE_THRESHOLD_LIMITS",
+        );
+        self.emit(
+            "Value of the accelerator position that indicates operator\n\
+             override. \\[steps\\]",
+            "ACCELERATOR_OVERRIDE_THRESHOLD",
+            "u32",
+            &override_threshold.to_string(),
+        );
+
+        self.slew_rate_limit(throttle, "throttle.", "THROTTLE", "throttle", "position");
+        self.rearm_policy(throttle, "throttle.", "THROTTLE", "throttle");
+
+        let interlock_threshold = self.float_threshold(
+            throttle,
+            "throttle.",
+            "interlock_brake_command_threshold",
+            &PlausibleRange {
+                name: "brake command range",
+                min: PEDAL_COMMAND_MIN,
+                max: PEDAL_COMMAND_MAX,
+            },
+            "Runtime tuning limits of `THROTTLE_INTERLOCK_BRAKE_COMMAND_THRESHOLD`.",
+            "THROTTLE_INTERLOCK_BRAKE_COMMAND_THRESHOLD_LIMITS",
+        );
+        self.emit(
+            "Brake command above which the throttle is interlocked.",
+            "THROTTLE_INTERLOCK_BRAKE_COMMAND_THRESHOLD",
+            "f32",
+            &format!("{:?}", interlock_threshold),
+        );
+
+        let action = match self
+            .string(throttle, "throttle.", "interlock_action")
+            .as_str()
+        {
+            "zero_throttle" => "ZeroThrottle",
+            "disable_throttle" => "DisableThrottle",
+            action => {
+                self.error(format!(
+                    "`throttle.interlock_action` = {:?} is not one of \"zero_throttle\" or \
+                     \"disable_throttle\"",
+                    action
+                ));
+                return;
+            }
+        };
+        self.emit(
+            "Action taken on the throttle while interlocked.",
+            "THROTTLE_INTERLOCK_ACTION",
+            "::interlock::InterlockAction",
+            &format!("::interlock::InterlockAction::{}", action),
+        );
+    }
+
+    fn steering(&mut self, steering: &Value, stuck_high_threshold: i64) {
+        for (key, signal) in [("spoof_low_signal", "low"), ("spoof_high_signal", "high")].iter() {
+            self.spoof_signal(steering, "steering", key, "STEERING", signal);
+
+            // the missing table is reported by `spoof_signal`
+            let table = match steering.get(*key) {
+                Some(table) => table,
+                None => continue,
+            };
+            let section = format!("steering.{}.", key);
+            let upper = signal.to_uppercase();
+
+            let scale = self.float(table, &section, "calibration_curve_scale");
+            let offset = self.float(table, &section, "calibration_curve_offset");
+            self.emit(
+                &format!(
+                    "Scalar value for the {} spoof signal taken from a calibration\n\
+                     curve. \\[volts/torque\\]",
+                    signal
+                ),
+                &format!("TORQUE_SPOOF_{}_SIGNAL_CALIBRATION_CURVE_SCALE", upper),
+                "f32",
+                &format!("{:?}", scale),
+            );
+            self.emit(
+                &format!(
+                    "Offset value for the {} spoof signal taken from a calibration\n\
+                     curve. \\[volts\\]",
+                    signal
+                ),
+                &format!("TORQUE_SPOOF_{}_SIGNAL_CALIBRATION_CURVE_OFFSET", upper),
+                "f32",
+                &format!("{:?}", offset),
+            );
+        }
+
+        let minimum_torque = self.float(steering, "steering.", "minimum_torque_command");
+        let maximum_torque = self.float(steering, "steering.", "maximum_torque_command");
+        self.check(
+            (minimum_torque < 0.0) && (maximum_torque > 0.0),
+            format!(
+                "`steering.minimum_torque_command` = {} and \
+                 `steering.maximum_torque_command` = {} must hold zero",
+                minimum_torque, maximum_torque
+            ),
+        );
+        self.emit(
+            "Minimum allowable torque value.",
+            "MINIMUM_TORQUE_COMMAND",
+            "f32",
+            &format!("{:?}", minimum_torque),
+        );
+        self.emit(
+            "Maximum allowable torque value.",
+            "MAXIMUM_TORQUE_COMMAND",
+            "f32",
+            &format!("{:?}", maximum_torque),
+        );
+
+        self.slew_rate_limit(steering, "steering.", "STEERING", "torque", "torque");
+        self.torque_speed_schedule(steering, (-minimum_torque).max(maximum_torque));
+
+        let sensor = self
+            .plausibility(
+                steering,
+                "steering",
+                "torque_sensor",
+                "Plausibility model of the torque sensor channels.\n\n\
+                 The channels mirror each other around the sensor midpoint, so their\n\
+                 sum stays roughly constant.",
+                "TORQUE_SENSOR_PLAUSIBILITY",
+                stuck_high_threshold,
+            )
+            .difference();
+
+        let override_threshold = self.threshold(
+            steering,
+            "steering.",
+            "torque_difference_override_threshold",
+            &sensor,
+            i64::from(u16::max_value()),
+            "Runtime tuning limits of `TORQUE_DIFFERENCE_OVERRIDE_THRESHOLD`.\n\\[steps\\]",
+            "TORQUE_DIFFERENCE_OVERRIDE_THRESHOLD_LIMITS",
+        );
+        self.emit(
+            "Value of torque sensor difference that indicates likely operator\n\
+             override. \\[steps\\]",
+            "TORQUE_DIFFERENCE_OVERRIDE_THRESHOLD",
+            "u16",
+            &override_threshold.to_string(),
+        );
+
+        let filter_alpha_limits = self.limits(
+            steering,
+            "steering.",
+            "filter_alpha",
+            STEERING_FILTER_ALPHA,
+            "Runtime tuning limits of the steering torque difference filter alpha.",
+            "STEERING_FILTER_ALPHA_LIMITS",
+        );
+        self.check(
+            (filter_alpha_limits.0 > 0.0) && (filter_alpha_limits.1 <= 1.0),
+            format!(
+                "`steering.filter_alpha_limits` [{}, {}] are outside of (0, 1]",
+                filter_alpha_limits.0, filter_alpha_limits.1
+            ),
+        );
+
+        self.rearm_policy(steering, "steering.", "STEERING", "steering");
+
+        let maximum_angle = self.positive(steering, "steering.", "maximum_angle_command");
+        self.emit(
+            "Maximum allowable steering wheel angle command magnitude. \\[degrees\\]",
+            "MAXIMUM_STEERING_ANGLE_COMMAND",
+            "f32",
+            &format!("{:?}", maximum_angle),
+        );
+
+        self.pid(
+            steering,
+            "steering.",
+            "angle_pid",
+            "steering angle",
+            "STEERING_ANGLE",
+            "torque/degree",
+        );
+    }
+
+    /// Speed scheduled limits on the torque command magnitude, sorted by
+    /// speed and within `maximum_torque`
+    fn torque_speed_schedule(&mut self, steering: &Value, maximum_torque: f64) {
+        let key = "steering.torque_speed_schedule";
+        let mut entries: Vec<(f64, f64)> = Vec::new();
+
+        match self.value(steering, "steering.", "torque_speed_schedule") {
+            Some(Value::Array(array)) => {
+                for entry in array.iter() {
+                    let pair = entry
+                        .as_array()
+                        .filter(|pair| pair.len() == 2)
+                        .and_then(|pair| Some((number(&pair[0])?, number(&pair[1])?)));
+
+                    match pair {
+                        Some(pair) => entries.push(pair),
+                        None => self.error(format!(
+                            "`{}` entries must be [speed, torque limit] pairs",
+                            key
+                        )),
+                    }
+                }
+            }
+            Some(_) => self.error(format!("`{}` is not an array", key)),
+            None => (),
+        }
+
+        self.check(
+            !entries.is_empty(),
+            format!("`{}` must hold at least one entry", key),
+        );
+        self.check(
+            entries.windows(2).all(|pair| pair[0].0 < pair[1].0)
+                && entries.iter().all(|entry| entry.0 >= 0.0),
+            format!("`{}` speeds must be positive and increasing", key),
+        );
+        self.check(
+            entries
+                .iter()
+                .all(|entry| (entry.1 > 0.0) && (entry.1 <= maximum_torque)),
+            format!(
+                "`{}` torque limits must be within (0, {}], the torque command range",
+                key, maximum_torque
+            ),
+        );
+
+        let mut value = String::from("[\n");
+        for entry in entries.iter() {
+            writeln!(value, "    ({:?}, {:?}),", entry.0, entry.1).unwrap();
+        }
+        value.push(']');
+
+        self.emit(
+            "Speed scheduled limit on the magnitude of the torque command.\n\n\
+             Each entry is a vehicle speed \\[km/h\\] and the torque limit at that\n\
+             speed, sorted by speed. The limit is interpolated between entries and\n\
+             held beyond the first and last. The smallest limit applies when the\n\
+             vehicle speed is unknown.",
+            "STEERING_TORQUE_SPEED_SCHEDULE",
+            &format!("[(f32, f32); {}]", entries.len()),
+            &value,
+        );
+    }
+
+    fn speed_control(&mut self, speed_control: &Value) {
+        let section = "speed_control.";
+
+        let target_speed = self.positive(speed_control, section, "maximum_target_speed");
+        self.emit(
+            "Maximum allowable target vehicle speed. \\[km/h\\]",
+            "MAXIMUM_SPEED_CONTROL_TARGET_SPEED",
+            "f32",
+            &format!("{:?}", target_speed),
+        );
+
+        let acceleration_limit =
+            self.positive(speed_control, section, "maximum_acceleration_limit");
+        self.emit(
+            "Maximum allowable acceleration limit, applies to both\n\
+             acceleration and deceleration. \\[m/s^2\\]",
+            "MAXIMUM_SPEED_CONTROL_ACCELERATION_LIMIT",
+            "f32",
+            &format!("{:?}", acceleration_limit),
+        );
+
+        self.pid(
+            speed_control,
+            section,
+            "pid",
+            "speed control",
+            "SPEED_CONTROL",
+            "pedal/(km/h)",
+        );
+
+        let brake_engage = self.float(speed_control, section, "brake_engage_threshold");
+        let brake_release = self.float(speed_control, section, "brake_release_threshold");
+        let throttle_release = self.float(speed_control, section, "throttle_release_threshold");
+        let throttle_engage = self.float(speed_control, section, "throttle_engage_threshold");
+        self.check(
+            (SPEED_CONTROL_OUTPUT_MIN < brake_engage)
+                && (brake_engage < brake_release)
+                && (brake_release <= throttle_release)
+                && (throttle_release < throttle_engage)
+                && (throttle_engage < SPEED_CONTROL_OUTPUT_MAX),
+            format!(
+                "`{}` pedal thresholds {}, {}, {} and {} must be ordered brake engage, \
+                 brake release, throttle release, throttle engage within the output \
+                 range ({}, {})",
+                section.trim_end_matches('.'),
+                brake_engage,
+                brake_release,
+                throttle_release,
+                throttle_engage,
+                SPEED_CONTROL_OUTPUT_MIN,
+                SPEED_CONTROL_OUTPUT_MAX
+            ),
+        );
+        self.emit(
+            "Speed control output thresholds selecting the pedal.",
+            "SPEED_CONTROL_PEDAL_THRESHOLDS",
+            "::pedal_selection::PedalThresholds",
+            &format!(
+                "::pedal_selection::PedalThresholds {{\n    throttle_engage: {:?},\n    \
+                 throttle_release: {:?},\n    brake_engage: {:?},\n    \
+                 brake_release: {:?},\n}}",
+                throttle_engage, throttle_release, brake_engage, brake_release
+            ),
+        );
+    }
+
+    fn slew_rate_limit(
+        &mut self,
+        module: &Value,
+        section: &str,
+        prefix: &str,
+        command: &str,
+        unit: &str,
+    ) {
+        let limit = self.positive(module, section, "command_slew_rate_limit");
+        self.emit(
+            &format!(
+                "Maximum rate of change of the {} command. \\[{}/second\\]",
+                command, unit
+            ),
+            &format!("{}_COMMAND_SLEW_RATE_LIMIT", prefix),
+            "f32",
+            &format!("{:?}", limit),
+        );
+    }
+
+    fn rearm_policy(&mut self, module: &Value, section: &str, prefix: &str, name: &str) {
+        let policy = match self
+            .string(module, section, "override_rearm_policy")
+            .as_str()
+        {
+            "immediate" => "Immediate".to_string(),
+            "quiet_time" => {
+                let quiet_time = self.integer(
+                    module,
+                    section,
+                    "override_rearm_quiet_time",
+                    i64::from(u32::max_value()),
+                );
+                self.check(
+                    quiet_time > 0,
+                    format!("`{}override_rearm_quiet_time` must be positive", section),
+                );
+                format!("QuietTime({})", quiet_time)
+            }
+            "acknowledge" => "Acknowledge".to_string(),
+            policy => {
+                self.error(format!(
+                    "`{}override_rearm_policy` = {:?} is not one of \"immediate\", \
+                     \"quiet_time\" or \"acknowledge\"",
+                    section, policy
+                ));
+                return;
+            }
+        };
+
+        self.emit(
+            &format!("Operator override re-arm policy of the {} module.", name),
+            &format!("{}_OVERRIDE_REARM_POLICY", prefix),
+            "::override_rearm::RearmPolicy",
+            &format!("::override_rearm::RearmPolicy::{}", policy),
+        );
+    }
+
+    fn pid(
+        &mut self,
+        module: &Value,
+        section: &str,
+        key: &str,
+        name: &str,
+        prefix: &str,
+        unit: &str,
+    ) {
+        let table = self.table(module, section, key);
+        let section = format!("{}{}.", section, key);
+
+        let mut gains = Vec::new();
+        for gain in ["proportional", "integral", "derivative"].iter() {
+            let value = self.float(table, &section, gain);
+            self.check(
+                value >= 0.0,
+                format!("`{}{}` must not be negative", section, gain),
+            );
+            gains.push(value);
+        }
+        self.emit(
+            &format!("Gains of the {} PID controller. \\[{}\\]", name, unit),
+            &format!("{}_PID_GAINS", prefix),
+            "::pid::PidGains",
+            &format!(
+                "::pid::PidGains {{\n    proportional: {:?},\n    integral: {:?},\n    \
+                 derivative: {:?},\n}}",
+                gains[0], gains[1], gains[2]
+            ),
+        );
+
+        let windup_guard = self.positive(table, &section, "windup_guard");
+        self.emit(
+            &format!("Windup guard of the {} PID controller.", name),
+            &format!("{}_PID_WINDUP_GUARD", prefix),
+            "f32",
+            &format!("{:?}", windup_guard),
+        );
+    }
+
+    /// A threshold compared to a value derived from the sensor channels,
+    /// reachable by a plausible reading along with its runtime tuning
+    /// limits
+    #[allow(clippy::too_many_arguments)]
+    fn threshold(
+        &mut self,
+        table: &Value,
+        section: &str,
+        key: &str,
+        range: &PlausibleRange,
+        max: i64,
+        limits_doc: &str,
+        limits_name: &str,
+    ) -> i64 {
+        let threshold = self.integer(table, section, key, max);
+        self.within(section, key, threshold as f64, range);
+        self.threshold_limits(
+            table,
+            section,
+            key,
+            threshold as f64,
+            range,
+            limits_doc,
+            limits_name,
+        );
+
+        threshold
+    }
+
+    /// `threshold` of a continuous value
+    fn float_threshold(
+        &mut self,
+        table: &Value,
+        section: &str,
+        key: &str,
+        range: &PlausibleRange,
+        limits_doc: &str,
+        limits_name: &str,
+    ) -> f64 {
+        let threshold = self.float(table, section, key);
+        self.within(section, key, threshold, range);
+        self.threshold_limits(
+            table,
+            section,
+            key,
+            threshold,
+            range,
+            limits_doc,
+            limits_name,
+        );
+
+        threshold
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn threshold_limits(
+        &mut self,
+        table: &Value,
+        section: &str,
+        key: &str,
+        threshold: f64,
+        range: &PlausibleRange,
+        doc: &str,
+        name: &str,
+    ) {
+        let (min, max) = self.limits(table, section, key, threshold, doc, name);
+
+        self.check(
+            (min > range.min) && (max < range.max),
+            format!(
+                "`{}{}_limits` [{}, {}] reach outside of the {} ({}, {})",
+                section, key, min, max, range.name, range.min, range.max
+            ),
+        );
+    }
+
+    fn within(&mut self, section: &str, key: &str, value: f64, range: &PlausibleRange) {
+        self.check(
+            (value > range.min) && (value < range.max),
+            format!(
+                "`{}{}` = {} is outside of the {} ({}, {})",
+                section, key, value, range.name, range.min, range.max
+            ),
+        );
+    }
+
+    /// Runtime tuning limits of a parameter, `{key}_limits`, which must
+    /// hold its default `value`
+    fn limits(
+        &mut self,
+        table: &Value,
+        section: &str,
+        key: &str,
+        value: f64,
+        doc: &str,
+        name: &str,
+    ) -> (f64, f64) {
+        let key = format!("{}_limits", key);
+        let limits = self.table(table, section, &key);
+        let limits_section = format!("{}{}.", section, key);
+
+        let min = self.float(limits, &limits_section, "min");
+        let max = self.float(limits, &limits_section, "max");
+        self.check(
+            (min <= value) && (value <= max),
+            format!(
+                "`{}{}` [{}, {}] don't hold the default {}",
+                section, key, min, max, value
+            ),
+        );
+        self.emit(
+            doc,
+            name,
+            "::params::ParamLimits",
+            &format!(
+                "::params::ParamLimits {{\n    min: {:?},\n    max: {:?},\n}}",
+                min, max
+            ),
+        );
+
+        (min, max)
+    }
+
+    fn spoof_signal(
+        &mut self,
+        module: &Value,
+        section: &str,
+        key: &str,
+        prefix: &str,
+        signal: &str,
+    ) -> StepRange {
+        let name = format!("{}.{}", section, key);
+        let table = self.table(module, &format!("{}.", section), key);
+        let voltage_min = self.float(table, &format!("{}.", name), "voltage_min");
+        let voltage_max = self.float(table, &format!("{}.", name), "voltage_max");
+
+        let range = StepRange {
+            min: (voltage_min * STEPS_PER_VOLT - STEPS_EPSILON)
+                .ceil()
+                .max(0.0) as u32,
+            max: (voltage_max * STEPS_PER_VOLT + STEPS_EPSILON)
+                .floor()
+                .max(0.0) as u32,
+        };
+
+        self.check(
+            (voltage_min >= 0.0) && (voltage_min < voltage_max),
+            format!(
+                "`{}` voltages [{}, {}] are not an increasing positive range",
+                name, voltage_min, voltage_max
+            ),
+        );
+        self.check(
+            f64::from(range.max) <= DAC_MAX,
+            format!(
+                "`{}.voltage_max` = {} is above the DAC range, {} steps",
+                name, voltage_max, range.max
+            ),
+        );
+        self.check(
+            range.min <= range.max,
+            format!(
+                "`{}` voltages [{}, {}] don't hold a single DAC step",
+                name, voltage_min, voltage_max
+            ),
+        );
+
+        let upper = signal.to_uppercase();
+        self.emit(
+            &format!(
+                "Minimum allowed voltage for the {} spoof signal voltage. \\[volts\\]",
+                signal
+            ),
+            &format!("{}_SPOOF_{}_SIGNAL_VOLTAGE_MIN", prefix, upper),
+            "f32",
+            &format!("{:?}", voltage_min),
+        );
+        self.emit(
+            &format!(
+                "Maximum allowed voltage for the {} spoof signal voltage. \\[volts\\]",
+                signal
+            ),
+            &format!("{}_SPOOF_{}_SIGNAL_VOLTAGE_MAX", prefix, upper),
+            "f32",
+            &format!("{:?}", voltage_max),
+        );
+        self.emit(
+            &format!(
+                "Minimum allowed value for the {} spoof signal value. \\[steps\\]\n\
+                 Equal to {}_SPOOF_{}_SIGNAL_VOLTAGE_MIN * STEPS_PER_VOLT, rounded up.",
+                signal, prefix, upper
+            ),
+            &format!("{}_SPOOF_{}_SIGNAL_RANGE_MIN", prefix, upper),
+            "u16",
+            &range.min.to_string(),
+        );
+        self.emit(
+            &format!(
+                "Maximum allowed value for the {} spoof signal value. \\[steps\\]\n\
+                 Equal to {}_SPOOF_{}_SIGNAL_VOLTAGE_MAX * STEPS_PER_VOLT, rounded down.",
+                signal, prefix, upper
+            ),
+            &format!("{}_SPOOF_{}_SIGNAL_RANGE_MAX", prefix, upper),
+            "u16",
+            &range.max.to_string(),
+        );
+
+        range
+    }
+
+    fn plausibility(
+        &mut self,
+        module: &Value,
+        section: &str,
+        key: &str,
+        doc: &str,
+        const_name: &str,
+        stuck_high_threshold: i64,
+    ) -> ChannelRanges {
+        let name = format!("{}.{}.", section, key);
+        let table = self.table(module, &format!("{}.", section), key);
+        let steps = i64::from(u16::max_value());
+
+        let high_min = self.integer(table, &name, "high_min", steps);
+        let high_max = self.integer(table, &name, "high_max", steps);
+        let low_min = self.integer(table, &name, "low_min", steps);
+        let low_max = self.integer(table, &name, "low_max", steps);
+
+        self.check(
+            (high_min < high_max) && (low_min < low_max),
+            format!("`{}` channel ranges are empty", name.trim_end_matches('.')),
+        );
+        self.check(
+            (high_max < stuck_high_threshold) && (low_max < stuck_high_threshold),
+            format!(
+                "`{}` channel ranges reach the stuck high threshold {}",
+                name.trim_end_matches('.'),
+                stuck_high_threshold
+            ),
+        );
+
+        let relation = match self.string(table, &name, "relation").as_str() {
+            "ratio" => {
+                let ratio = self.float(table, &name, "ratio");
+                let tolerance = self.integer(table, &name, "tolerance", steps);
+                self.check(ratio > 0.0, format!("`{}ratio` must be positive", name));
+                format!(
+                    "ChannelRelation::Ratio {{\n        ratio: {:?},\n        \
+                     tolerance: {},\n    }}",
+                    ratio, tolerance
+                )
+            }
+            relation @ "offset" | relation @ "sum" => {
+                let (min, max) = if relation == "offset" {
+                    (i64::from(i16::min_value()), i64::from(i16::max_value()))
+                } else {
+                    (0, steps)
+                };
+                let (min, max) = (
+                    self.integer_within(table, &name, "min", min, max),
+                    self.integer_within(table, &name, "max", min, max),
+                );
+                self.check(min <= max, format!("`{}` relation range is empty", name));
+                format!(
+                    "ChannelRelation::{} {{ min: {}, max: {} }}",
+                    if relation == "offset" {
+                        "Offset"
+                    } else {
+                        "Sum"
+                    },
+                    min,
+                    max
+                )
+            }
+            relation => {
+                self.error(format!(
+                    "`{}relation` = {:?} is not one of \"ratio\", \"offset\" or \"sum\"",
+                    name, relation
+                ));
+                return ChannelRanges {
+                    high_min: 0,
+                    high_max: 0,
+                    low_min: 0,
+                    low_max: 0,
+                };
+            }
+        };
+
+        self.emit(
+            &format!("{}\n\\[steps\\]", doc),
+            const_name,
+            "::dual_signal::SignalPlausibility",
+            &format!(
+                "::dual_signal::SignalPlausibility {{\n    relation: ::dual_signal::{},\n    \
+                 high_min: {},\n    high_max: {},\n    low_min: {},\n    low_max: {},\n    \
+                 stuck_high_threshold: SENSOR_STUCK_HIGH_THRESHOLD,\n}}",
+                relation, high_min, high_max, low_min, low_max
+            ),
+        );
+
+        ChannelRanges {
+            high_min,
+            high_max,
+            low_min,
+            low_max,
+        }
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Float(value) => Some(*value),
+        Value::Integer(value) => Some(*value as f64),
+        _ => None,
+    }
+}
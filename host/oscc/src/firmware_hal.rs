@@ -0,0 +1,65 @@
+//! Stand-in for the parts of the firmware HAL CAN API used by the shared
+//! protocol definitions, declared as the `nucleo_f767zi` module
+
+pub mod hal {
+    pub mod can {
+        #[derive(Copy, Clone, Debug)]
+        pub struct BaseID(u16);
+
+        impl BaseID {
+            pub fn new(id: u16) -> Self {
+                BaseID(id)
+            }
+        }
+
+        #[derive(Copy, Clone, Debug)]
+        pub enum ID {
+            BaseID(BaseID),
+        }
+
+        impl From<ID> for u32 {
+            fn from(id: ID) -> u32 {
+                match id {
+                    ID::BaseID(BaseID(id)) => u32::from(id),
+                }
+            }
+        }
+
+        /// Only named by the firmware side publisher traits
+        #[derive(Copy, Clone, Debug)]
+        pub enum CanError {}
+
+        pub struct DataFrame {
+            id: ID,
+            data: [u8; 8],
+            len: usize,
+        }
+
+        impl DataFrame {
+            pub fn new(id: ID) -> Self {
+                DataFrame {
+                    id,
+                    data: [0; 8],
+                    len: 0,
+                }
+            }
+
+            pub fn id(&self) -> ID {
+                self.id
+            }
+
+            pub fn data(&self) -> &[u8] {
+                &self.data[..self.len]
+            }
+
+            pub fn data_as_mut(&mut self) -> &mut [u8] {
+                &mut self.data[..self.len]
+            }
+
+            pub fn set_data_length(&mut self, len: usize) {
+                assert!(len <= self.data.len());
+                self.len = len;
+            }
+        }
+    }
+}
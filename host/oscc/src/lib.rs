@@ -0,0 +1,287 @@
+//! OSCC host API
+//!
+//! The Rust counterpart of the C `liboscc`, for the application side of
+//! the control CAN bus: enables and disables the OxCC modules, publishes
+//! the brake, throttle and steering commands, and hands the module
+//! reports, fault reports and forwarded OBD frames to callbacks.
+//!
+//! The frames are encoded and decoded with the protocol definitions of the
+//! firmware, `src/can_protocols`, over any CAN interface implementing
+//! `CanBus`.
+//!
+//! ```ignore
+//! let mut oscc = Oscc::new(bus);
+//! oscc.on_brake_report(|report| println!("{:?}", report));
+//! oscc.on_fault_report(|report| println!("{:?}", report));
+//! oscc.enable_all()?;
+//!
+//! loop {
+//!     oscc.publish_throttle_position(0.1)?;
+//!     oscc.poll()?;
+//! }
+//! ```
+
+// the firmware side of the shared definitions is unused here and
+// written for the firmware toolchain
+#[allow(dead_code, clippy::needless_lifetimes, clippy::new_without_default)]
+#[path = "../../../src/can_protocols/brake_can_protocol.rs"]
+mod brake_can_protocol;
+#[allow(dead_code, clippy::needless_lifetimes, clippy::new_without_default)]
+#[path = "../../../src/can_protocols/fault_can_protocol.rs"]
+mod fault_can_protocol;
+#[path = "../../../src/can_protocols/oscc_magic_byte.rs"]
+mod oscc_magic_byte;
+#[allow(dead_code, clippy::needless_lifetimes, clippy::new_without_default)]
+#[path = "../../../src/can_protocols/steering_can_protocol.rs"]
+mod steering_can_protocol;
+#[allow(dead_code, clippy::needless_lifetimes, clippy::new_without_default)]
+#[path = "../../../src/can_protocols/throttle_can_protocol.rs"]
+mod throttle_can_protocol;
+
+#[path = "firmware_hal.rs"]
+mod nucleo_f767zi;
+#[cfg(test)]
+mod tests;
+
+use brake_can_protocol::*;
+use fault_can_protocol::*;
+use nucleo_f767zi::hal::can::{BaseID, DataFrame, ID};
+use oscc_magic_byte::*;
+use steering_can_protocol::*;
+use throttle_can_protocol::*;
+
+pub use brake_can_protocol::{
+    OsccBrakeReport, OSCC_BRAKE_DTC_ACCUMULATOR_PUMP_TIMEOUT, OSCC_BRAKE_DTC_INVALID_SENSOR_VAL,
+    OSCC_BRAKE_DTC_OPERATOR_OVERRIDE, OSCC_BRAKE_DTC_PRESSURE_SENSOR_CHECK,
+    OSCC_BRAKE_DTC_SENSOR_MISMATCH, OSCC_BRAKE_DTC_SPOOF_MISMATCH,
+};
+pub use fault_can_protocol::{
+    OsccFaultReport, FAULT_ORIGIN_BRAKE, FAULT_ORIGIN_STEERING, FAULT_ORIGIN_THROTTLE,
+};
+pub use steering_can_protocol::{
+    OsccSteeringReport, OSCC_STEERING_DTC_INVALID_SENSOR_VAL, OSCC_STEERING_DTC_OPERATOR_OVERRIDE,
+    OSCC_STEERING_DTC_SENSOR_MISMATCH, OSCC_STEERING_DTC_SPOOF_MISMATCH,
+};
+pub use throttle_can_protocol::{
+    OsccThrottleReport, OSCC_THROTTLE_DTC_INVALID_SENSOR_VAL, OSCC_THROTTLE_DTC_OPERATOR_OVERRIDE,
+    OSCC_THROTTLE_DTC_SENSOR_MISMATCH, OSCC_THROTTLE_DTC_SPOOF_MISMATCH,
+};
+
+/// DLC of the enable and disable frames, same as liboscc
+const OSCC_REQUEST_CAN_DLC: u8 = 8;
+
+const CAN_MAX_DLC: usize = 8;
+
+/// A classic CAN frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CanFrame {
+    id: u32,
+    data: [u8; CAN_MAX_DLC],
+    len: usize,
+}
+
+impl CanFrame {
+    /// Panics with more than 8 data bytes
+    pub fn new(id: u32, data: &[u8]) -> Self {
+        assert!(data.len() <= CAN_MAX_DLC);
+
+        let mut frame = CanFrame {
+            id,
+            data: [0; CAN_MAX_DLC],
+            len: data.len(),
+        };
+        frame.data[..data.len()].copy_from_slice(data);
+        frame
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+
+    /// Whether the frame was sent by OSCC, as opposed to a forwarded
+    /// OBD frame
+    pub fn is_oscc(&self) -> bool {
+        (self.len >= 2)
+            && (self.data[0] == OSCC_MAGIC_BYTE_0)
+            && (self.data[1] == OSCC_MAGIC_BYTE_1)
+    }
+
+    /// Whether this is an OSCC frame with the identifier and at least
+    /// `dlc` data bytes
+    fn is_oscc_with_id(&self, id: u16, dlc: u8) -> bool {
+        self.is_oscc() && (self.id == u32::from(id)) && (self.len >= usize::from(dlc))
+    }
+
+    /// A frame built by the shared encoders
+    fn from_data_frame(frame: &DataFrame) -> Self {
+        CanFrame::new(u32::from(frame.id()), frame.data())
+    }
+
+    /// The firmware HAL frame the shared decoders expect
+    fn data_frame(&self) -> DataFrame {
+        let mut frame = DataFrame::new(ID::BaseID(BaseID::new(self.id as u16)));
+        frame.set_data_length(self.len);
+        frame.data_as_mut().copy_from_slice(self.data());
+        frame
+    }
+}
+
+/// CAN interface connected to the OxCC control CAN bus, e.g. SocketCAN
+/// or a USB adapter
+pub trait CanBus {
+    type Error;
+
+    fn transmit(&mut self, frame: &CanFrame) -> Result<(), Self::Error>;
+
+    /// Returns the next received frame without waiting, `None` when
+    /// there is none
+    fn receive(&mut self) -> Result<Option<CanFrame>, Self::Error>;
+}
+
+type Callback<T> = Box<dyn FnMut(&T)>;
+
+pub struct Oscc<B: CanBus> {
+    bus: B,
+    brake_report_callback: Option<Callback<OsccBrakeReport>>,
+    throttle_report_callback: Option<Callback<OsccThrottleReport>>,
+    steering_report_callback: Option<Callback<OsccSteeringReport>>,
+    fault_report_callback: Option<Callback<OsccFaultReport>>,
+    obd_frame_callback: Option<Callback<CanFrame>>,
+}
+
+impl<B: CanBus> Oscc<B> {
+    pub fn new(bus: B) -> Self {
+        Oscc {
+            bus,
+            brake_report_callback: None,
+            throttle_report_callback: None,
+            steering_report_callback: None,
+            fault_report_callback: None,
+            obd_frame_callback: None,
+        }
+    }
+
+    /// Gives the CAN interface back
+    pub fn into_bus(self) -> B {
+        self.bus
+    }
+
+    /// Enables the brake, throttle and steering modules, stops at the
+    /// first one failing
+    pub fn enable_all(&mut self) -> Result<(), B::Error> {
+        self.publish_request(OSCC_BRAKE_ENABLE_CAN_ID)?;
+        self.publish_request(OSCC_THROTTLE_ENABLE_CAN_ID)?;
+        self.publish_request(OSCC_STEERING_ENABLE_CAN_ID)
+    }
+
+    /// Disables the throttle, steering and brake modules, all of them are
+    /// attempted and the first failure is returned
+    pub fn disable_all(&mut self) -> Result<(), B::Error> {
+        let throttle = self.publish_request(OSCC_THROTTLE_DISABLE_CAN_ID);
+        let steering = self.publish_request(OSCC_STEERING_DISABLE_CAN_ID);
+        let brake = self.publish_request(OSCC_BRAKE_DISABLE_CAN_ID);
+
+        throttle.and(steering).and(brake)
+    }
+
+    /// Publishes a brake pedal position, clamped to \[0.0, 1.0\]
+    pub fn publish_brake_position(&mut self, position: f32) -> Result<(), B::Error> {
+        self.publish(&DataFrame::from(&OsccBrakeCommand {
+            pedal_command: clamp(position, 0.0, 1.0),
+        }))
+    }
+
+    /// Publishes an accelerator pedal position, clamped to \[0.0, 1.0\]
+    pub fn publish_throttle_position(&mut self, position: f32) -> Result<(), B::Error> {
+        self.publish(&DataFrame::from(&OsccThrottleCommand {
+            torque_request: clamp(position, 0.0, 1.0),
+        }))
+    }
+
+    /// Publishes a steering torque, clamped to \[-1.0, 1.0\]
+    pub fn publish_steering_torque(&mut self, torque: f32) -> Result<(), B::Error> {
+        self.publish(&DataFrame::from(&OsccSteeringCommand {
+            torque_request: clamp(torque, -1.0, 1.0),
+        }))
+    }
+
+    pub fn on_brake_report<F: FnMut(&OsccBrakeReport) + 'static>(&mut self, callback: F) {
+        self.brake_report_callback = Some(Box::new(callback));
+    }
+
+    pub fn on_throttle_report<F: FnMut(&OsccThrottleReport) + 'static>(&mut self, callback: F) {
+        self.throttle_report_callback = Some(Box::new(callback));
+    }
+
+    pub fn on_steering_report<F: FnMut(&OsccSteeringReport) + 'static>(&mut self, callback: F) {
+        self.steering_report_callback = Some(Box::new(callback));
+    }
+
+    pub fn on_fault_report<F: FnMut(&OsccFaultReport) + 'static>(&mut self, callback: F) {
+        self.fault_report_callback = Some(Box::new(callback));
+    }
+
+    /// Called with every frame that is not from OSCC, the OBD frames
+    /// OxCC forwards from the vehicle bus
+    pub fn on_obd_frame<F: FnMut(&CanFrame) + 'static>(&mut self, callback: F) {
+        self.obd_frame_callback = Some(Box::new(callback));
+    }
+
+    /// Receives the pending frames and hands them to the callbacks,
+    /// returns once the bus has none left
+    pub fn poll(&mut self) -> Result<(), B::Error> {
+        while let Some(frame) = self.bus.receive()? {
+            self.process_frame(&frame);
+        }
+
+        Ok(())
+    }
+
+    fn process_frame(&mut self, frame: &CanFrame) {
+        if !frame.is_oscc() {
+            if let Some(ref mut callback) = self.obd_frame_callback {
+                callback(frame);
+            }
+        } else if frame.is_oscc_with_id(OSCC_BRAKE_REPORT_CAN_ID, OSCC_BRAKE_REPORT_CAN_DLC) {
+            if let Some(ref mut callback) = self.brake_report_callback {
+                callback(&OsccBrakeReport::from(&frame.data_frame()));
+            }
+        } else if frame.is_oscc_with_id(OSCC_THROTTLE_REPORT_CAN_ID, OSCC_THROTTLE_REPORT_CAN_DLC) {
+            if let Some(ref mut callback) = self.throttle_report_callback {
+                callback(&OsccThrottleReport::from(&frame.data_frame()));
+            }
+        } else if frame.is_oscc_with_id(OSCC_STEERING_REPORT_CAN_ID, OSCC_STEERING_REPORT_CAN_DLC) {
+            if let Some(ref mut callback) = self.steering_report_callback {
+                callback(&OsccSteeringReport::from(&frame.data_frame()));
+            }
+        } else if frame.is_oscc_with_id(OSCC_FAULT_REPORT_CAN_ID, OSCC_FAULT_REPORT_CAN_DLC) {
+            if let Some(ref mut callback) = self.fault_report_callback {
+                callback(&OsccFaultReport::from(&frame.data_frame()));
+            }
+        }
+    }
+
+    /// Enable and disable requests only carry the magic bytes
+    fn publish_request(&mut self, id: u16) -> Result<(), B::Error> {
+        self.publish(&oscc_data_frame(id, OSCC_REQUEST_CAN_DLC))
+    }
+
+    fn publish(&mut self, frame: &DataFrame) -> Result<(), B::Error> {
+        self.bus.transmit(&CanFrame::from_data_frame(frame))
+    }
+}
+
+/// NaN is clamped to `min`
+fn clamp(value: f32, min: f32, max: f32) -> f32 {
+    if value > max {
+        max
+    } else if value >= min {
+        value
+    } else {
+        min
+    }
+}
@@ -0,0 +1,281 @@
+use super::*;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Records the transmitted frames and hands out the queued ones
+#[derive(Default)]
+struct MockBus {
+    sent: Vec<CanFrame>,
+    pending: VecDeque<CanFrame>,
+    /// Transmitting a frame with this identifier fails
+    failing_id: Option<u32>,
+}
+
+impl CanBus for MockBus {
+    type Error = u32;
+
+    fn transmit(&mut self, frame: &CanFrame) -> Result<(), u32> {
+        if self.failing_id == Some(frame.id()) {
+            return Err(frame.id());
+        }
+
+        self.sent.push(*frame);
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<Option<CanFrame>, u32> {
+        Ok(self.pending.pop_front())
+    }
+}
+
+const REQUEST_DATA: [u8; 8] = [OSCC_MAGIC_BYTE_0, OSCC_MAGIC_BYTE_1, 0, 0, 0, 0, 0, 0];
+
+fn sent_ids(bus: &MockBus) -> Vec<u32> {
+    bus.sent.iter().map(CanFrame::id).collect()
+}
+
+/// The only frame sent while publishing
+fn publish<F: FnOnce(&mut Oscc<MockBus>) -> Result<(), u32>>(publish: F) -> CanFrame {
+    let mut oscc = Oscc::new(MockBus::default());
+    publish(&mut oscc).unwrap();
+
+    let bus = oscc.into_bus();
+    assert_eq!(bus.sent.len(), 1);
+    bus.sent[0]
+}
+
+#[test]
+fn enable_all_sends_the_enable_requests() {
+    let mut oscc = Oscc::new(MockBus::default());
+    oscc.enable_all().unwrap();
+
+    let bus = oscc.into_bus();
+    assert_eq!(
+        sent_ids(&bus),
+        [
+            u32::from(OSCC_BRAKE_ENABLE_CAN_ID),
+            u32::from(OSCC_THROTTLE_ENABLE_CAN_ID),
+            u32::from(OSCC_STEERING_ENABLE_CAN_ID),
+        ]
+    );
+    assert!(bus.sent.iter().all(|frame| frame.data() == REQUEST_DATA));
+    assert!(bus.sent.iter().all(CanFrame::is_oscc));
+}
+
+#[test]
+fn enable_all_stops_at_the_first_failure() {
+    let mut oscc = Oscc::new(MockBus {
+        failing_id: Some(u32::from(OSCC_THROTTLE_ENABLE_CAN_ID)),
+        ..MockBus::default()
+    });
+
+    assert_eq!(
+        oscc.enable_all(),
+        Err(u32::from(OSCC_THROTTLE_ENABLE_CAN_ID))
+    );
+    assert_eq!(
+        sent_ids(&oscc.into_bus()),
+        [u32::from(OSCC_BRAKE_ENABLE_CAN_ID)]
+    );
+}
+
+#[test]
+fn disable_all_sends_the_disable_requests() {
+    let mut oscc = Oscc::new(MockBus::default());
+    oscc.disable_all().unwrap();
+
+    let bus = oscc.into_bus();
+    assert_eq!(
+        sent_ids(&bus),
+        [
+            u32::from(OSCC_THROTTLE_DISABLE_CAN_ID),
+            u32::from(OSCC_STEERING_DISABLE_CAN_ID),
+            u32::from(OSCC_BRAKE_DISABLE_CAN_ID),
+        ]
+    );
+    assert!(bus.sent.iter().all(|frame| frame.data() == REQUEST_DATA));
+}
+
+#[test]
+fn disable_all_attempts_every_module() {
+    let mut oscc = Oscc::new(MockBus {
+        failing_id: Some(u32::from(OSCC_THROTTLE_DISABLE_CAN_ID)),
+        ..MockBus::default()
+    });
+
+    assert_eq!(
+        oscc.disable_all(),
+        Err(u32::from(OSCC_THROTTLE_DISABLE_CAN_ID))
+    );
+    assert_eq!(
+        sent_ids(&oscc.into_bus()),
+        [
+            u32::from(OSCC_STEERING_DISABLE_CAN_ID),
+            u32::from(OSCC_BRAKE_DISABLE_CAN_ID),
+        ]
+    );
+}
+
+#[test]
+fn brake_command_round_trips() {
+    let frame = publish(|oscc| oscc.publish_brake_position(0.5));
+
+    assert_eq!(frame.id(), u32::from(OSCC_BRAKE_COMMAND_CAN_ID));
+    // 0.5 is 0x3F000000
+    assert_eq!(
+        frame.data(),
+        [0x05, 0xCC, 0x00, 0x00, 0x00, 0x3F, 0x00, 0x00]
+    );
+    assert_eq!(
+        OsccBrakeCommand::from(&frame.data_frame()).pedal_command,
+        0.5
+    );
+}
+
+#[test]
+fn throttle_command_round_trips() {
+    let frame = publish(|oscc| oscc.publish_throttle_position(0.25));
+
+    assert_eq!(frame.id(), u32::from(OSCC_THROTTLE_COMMAND_CAN_ID));
+    assert!(frame.is_oscc());
+    assert_eq!(
+        OsccThrottleCommand::from(&frame.data_frame()).torque_request,
+        0.25
+    );
+}
+
+#[test]
+fn steering_command_round_trips() {
+    let frame = publish(|oscc| oscc.publish_steering_torque(-0.75));
+
+    assert_eq!(frame.id(), u32::from(OSCC_STEERING_COMMAND_CAN_ID));
+    assert!(frame.is_oscc());
+    assert_eq!(
+        OsccSteeringCommand::from(&frame.data_frame()).torque_request,
+        -0.75
+    );
+}
+
+#[test]
+fn commands_are_clamped_to_their_range() {
+    let brake = |position| {
+        let frame = publish(|oscc| oscc.publish_brake_position(position));
+        OsccBrakeCommand::from(&frame.data_frame()).pedal_command
+    };
+    let throttle = |position| {
+        let frame = publish(|oscc| oscc.publish_throttle_position(position));
+        OsccThrottleCommand::from(&frame.data_frame()).torque_request
+    };
+    let steering = |torque| {
+        let frame = publish(|oscc| oscc.publish_steering_torque(torque));
+        OsccSteeringCommand::from(&frame.data_frame()).torque_request
+    };
+
+    assert_eq!(brake(1.5), 1.0);
+    assert_eq!(brake(-0.5), 0.0);
+    assert_eq!(brake(f32::NAN), 0.0);
+    assert_eq!(throttle(2.0), 1.0);
+    assert_eq!(throttle(f32::NEG_INFINITY), 0.0);
+    assert_eq!(throttle(f32::NAN), 0.0);
+    assert_eq!(steering(3.0), 1.0);
+    assert_eq!(steering(-3.0), -1.0);
+    assert_eq!(steering(f32::NAN), -1.0);
+}
+
+#[test]
+fn poll_hands_the_reports_to_their_callbacks() {
+    let mut bus = MockBus::default();
+    // layouts as sent by the firmware CAN gateway
+    bus.pending.push_back(CanFrame::new(
+        u32::from(OSCC_BRAKE_REPORT_CAN_ID),
+        &[0x05, 0xCC, 1, 0, OSCC_BRAKE_DTC_SENSOR_MISMATCH, 1, 2, 0],
+    ));
+    bus.pending.push_back(CanFrame::new(
+        u32::from(OSCC_THROTTLE_REPORT_CAN_ID),
+        &[0x05, 0xCC, 0, 1, 0, 0, 1, 0],
+    ));
+    bus.pending.push_back(CanFrame::new(
+        u32::from(OSCC_STEERING_REPORT_CAN_ID),
+        &[0x05, 0xCC, 1, 0, 0, 0, 0, 1],
+    ));
+    bus.pending.push_back(CanFrame::new(
+        u32::from(OSCC_FAULT_REPORT_CAN_ID),
+        &[0x05, 0xCC, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00],
+    ));
+
+    let reports = Rc::new(RefCell::new(Vec::new()));
+    let mut oscc = Oscc::new(bus);
+
+    let brake = reports.clone();
+    oscc.on_brake_report(move |report| {
+        brake.borrow_mut().push(format!(
+            "brake {} {} {} {} {}",
+            report.enabled,
+            report.operator_override,
+            report.dtcs,
+            report.slew_rate_limited,
+            report.override_rearm_state
+        ))
+    });
+    let throttle = reports.clone();
+    oscc.on_throttle_report(move |report| {
+        throttle.borrow_mut().push(format!(
+            "throttle {} {} {}",
+            report.enabled, report.operator_override, report.override_rearm_state
+        ))
+    });
+    let steering = reports.clone();
+    oscc.on_steering_report(move |report| {
+        steering.borrow_mut().push(format!(
+            "steering {} {}",
+            report.enabled, report.angle_control
+        ))
+    });
+    let fault = reports.clone();
+    oscc.on_fault_report(move |report| {
+        fault
+            .borrow_mut()
+            .push(format!("fault {} {}", report.fault_origin_id, report.dtcs))
+    });
+
+    oscc.poll().unwrap();
+
+    assert_eq!(
+        *reports.borrow(),
+        [
+            format!("brake true false {} true 2", OSCC_BRAKE_DTC_SENSOR_MISMATCH),
+            "throttle false true 1".to_string(),
+            "steering true true".to_string(),
+            format!("fault {} 1", FAULT_ORIGIN_THROTTLE),
+        ]
+    );
+    assert!(oscc.into_bus().pending.is_empty());
+}
+
+#[test]
+fn poll_forwards_obd_frames_and_ignores_short_reports() {
+    let obd = CanFrame::new(0x4F1, &[0x10, 0x20, 0x30]);
+
+    let mut bus = MockBus::default();
+    bus.pending.push_back(obd);
+    // an OSCC frame missing its last data byte
+    bus.pending.push_back(CanFrame::new(
+        u32::from(OSCC_BRAKE_REPORT_CAN_ID),
+        &[0x05, 0xCC, 1, 0, 0, 0, 0],
+    ));
+
+    let obd_frames = Rc::new(RefCell::new(Vec::new()));
+    let brake_reports = Rc::new(RefCell::new(0));
+    let mut oscc = Oscc::new(bus);
+
+    let frames = obd_frames.clone();
+    oscc.on_obd_frame(move |frame| frames.borrow_mut().push(*frame));
+    let reports = brake_reports.clone();
+    oscc.on_brake_report(move |_| *reports.borrow_mut() += 1);
+
+    oscc.poll().unwrap();
+
+    assert_eq!(*obd_frames.borrow(), [obd]);
+    assert_eq!(*brake_reports.borrow(), 0);
+}
@@ -0,0 +1,254 @@
+//! OxCC telemetry decoder
+//!
+//! Decodes a capture of the binary telemetry stream into CSV, or JSON
+//! with one object per line, on stdout. See `src/telemetry_protocol.rs`
+//! of the firmware for the records.
+//!
+//! Anything in between the frames, like the debug console text, and
+//! frames failing their CRC are skipped and counted on stderr.
+//!
+//! Usage: `oxcc-telemetry [--csv|--json] [capture]`, reads stdin
+//! without a capture file.
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+
+#[path = "../../../src/telemetry_protocol.rs"]
+mod telemetry_protocol;
+
+#[cfg(test)]
+mod tests;
+
+use telemetry_protocol::*;
+
+const USAGE: &str = "Usage: oxcc-telemetry [--csv|--json] [capture]";
+
+const CSV_HEADER: &str = "timestamp_ms,record,state,control_allowed,vehicle_profile,\
+                          dropped_records,module,enabled,operator_override,dtcs,\
+                          sensor_high,sensor_low,sensor_plausible,spoof_high,spoof_low,\
+                          event,source,value";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Format {
+    Csv,
+    Json,
+}
+
+fn main() {
+    let mut format = Format::Csv;
+    let mut path = None;
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--csv" => format = Format::Csv,
+            "--json" => format = Format::Json,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        }
+    }
+
+    let mut capture = Vec::new();
+    let result = match path {
+        Some(ref path) => File::open(path).and_then(|mut file| file.read_to_end(&mut capture)),
+        None => io::stdin().read_to_end(&mut capture),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to read the capture: {}", e);
+        process::exit(1);
+    }
+
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+
+    match decode(&capture, format, &mut out).and_then(|counts| out.flush().map(|_| counts)) {
+        Ok((records, invalid_frames)) => {
+            eprintln!(
+                "{} records, {} invalid frames skipped",
+                records, invalid_frames
+            );
+        }
+        Err(e) => {
+            eprintln!("Failed to write the records: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Writes the records of a capture, returns the number of records
+/// and of invalid frames
+fn decode<W: Write>(capture: &[u8], format: Format, out: &mut W) -> io::Result<(usize, usize)> {
+    let mut records = 0;
+    let mut invalid_frames = 0;
+
+    if format == Format::Csv {
+        writeln!(out, "{}", CSV_HEADER)?;
+    }
+
+    for frame in capture.split(|&byte| byte == 0) {
+        if frame.is_empty() {
+            continue;
+        }
+
+        match TelemetryRecord::decode(frame) {
+            Ok(record) => {
+                match format {
+                    Format::Csv => write_csv(out, &record)?,
+                    Format::Json => write_json(out, &record)?,
+                }
+                records += 1;
+            }
+            Err(_) => invalid_frames += 1,
+        }
+    }
+
+    Ok((records, invalid_frames))
+}
+
+fn write_csv<W: Write>(out: &mut W, record: &TelemetryRecord) -> io::Result<()> {
+    match record {
+        TelemetryRecord::System(system) => writeln!(
+            out,
+            "{},system,{},{},{},{},,,,,,,,,,,,",
+            system.timestamp,
+            state_name(system.state),
+            system.control_allowed,
+            system.vehicle_profile,
+            system.dropped_records
+        ),
+        TelemetryRecord::Module(module) => {
+            let (spoof_high, spoof_low) = match module.spoof {
+                Some((high, low)) => (high.to_string(), low.to_string()),
+                None => (String::new(), String::new()),
+            };
+
+            writeln!(
+                out,
+                "{},module,,,,,{},{},{},{},{},{},{},{},{},,,",
+                module.timestamp,
+                module_name(module.module),
+                module.enabled,
+                module.operator_override,
+                module.dtcs,
+                module.sensor_high,
+                module.sensor_low,
+                module.sensor_plausible,
+                spoof_high,
+                spoof_low
+            )
+        }
+        TelemetryRecord::Event(event) => writeln!(
+            out,
+            "{},event,,,,,,,,,,,,,,{},{},{}",
+            event.timestamp,
+            event_name(event.event),
+            event_source(event),
+            event_value(event)
+        ),
+    }
+}
+
+fn write_json<W: Write>(out: &mut W, record: &TelemetryRecord) -> io::Result<()> {
+    match record {
+        TelemetryRecord::System(system) => writeln!(
+            out,
+            "{{\"timestamp_ms\":{},\"record\":\"system\",\"state\":\"{}\",\
+             \"control_allowed\":{},\"vehicle_profile\":{},\"dropped_records\":{}}}",
+            system.timestamp,
+            state_name(system.state),
+            system.control_allowed,
+            system.vehicle_profile,
+            system.dropped_records
+        ),
+        TelemetryRecord::Module(module) => {
+            let spoof = match module.spoof {
+                Some((high, low)) => format!("{{\"high\":{},\"low\":{}}}", high, low),
+                None => "null".to_string(),
+            };
+
+            writeln!(
+                out,
+                "{{\"timestamp_ms\":{},\"record\":\"module\",\"module\":\"{}\",\
+                 \"enabled\":{},\"operator_override\":{},\"dtcs\":{},\
+                 \"sensor_high\":{},\"sensor_low\":{},\"sensor_plausible\":{},\"spoof\":{}}}",
+                module.timestamp,
+                module_name(module.module),
+                module.enabled,
+                module.operator_override,
+                module.dtcs,
+                module.sensor_high,
+                module.sensor_low,
+                module.sensor_plausible,
+                spoof
+            )
+        }
+        TelemetryRecord::Event(event) => writeln!(
+            out,
+            "{{\"timestamp_ms\":{},\"record\":\"event\",\"event\":\"{}\",\
+             \"source\":\"{}\",\"value\":\"{}\"}}",
+            event.timestamp,
+            event_name(event.event),
+            event_source(event),
+            event_value(event)
+        ),
+    }
+}
+
+/// See `system_state::SystemState`
+fn state_name(state: u8) -> String {
+    match state {
+        0 => "Boot".to_string(),
+        1 => "SelfTest".to_string(),
+        2 => "Ready".to_string(),
+        3 => "Active".to_string(),
+        4 => "Degraded".to_string(),
+        5 => "Faulted".to_string(),
+        6 => "SafeStop".to_string(),
+        _ => state.to_string(),
+    }
+}
+
+fn module_name(module: u8) -> String {
+    match module {
+        TELEMETRY_MODULE_BRAKE => "brake".to_string(),
+        TELEMETRY_MODULE_THROTTLE => "throttle".to_string(),
+        TELEMETRY_MODULE_STEERING => "steering".to_string(),
+        _ => module.to_string(),
+    }
+}
+
+fn event_name(event: u8) -> String {
+    match event {
+        TELEMETRY_EVENT_SYSTEM_STATE => "system_state".to_string(),
+        TELEMETRY_EVENT_MODULE_ENABLED => "module_enabled".to_string(),
+        TELEMETRY_EVENT_OPERATOR_OVERRIDE => "operator_override".to_string(),
+        TELEMETRY_EVENT_DTCS => "dtcs".to_string(),
+        _ => event.to_string(),
+    }
+}
+
+fn event_source(event: &EventTelemetry) -> String {
+    if event.event == TELEMETRY_EVENT_SYSTEM_STATE {
+        "system".to_string()
+    } else {
+        module_name(event.source)
+    }
+}
+
+fn event_value(event: &EventTelemetry) -> String {
+    match event.event {
+        TELEMETRY_EVENT_SYSTEM_STATE => state_name(event.value),
+        TELEMETRY_EVENT_MODULE_ENABLED | TELEMETRY_EVENT_OPERATOR_OVERRIDE => {
+            (event.value != 0).to_string()
+        }
+        _ => event.value.to_string(),
+    }
+}
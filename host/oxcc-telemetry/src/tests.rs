@@ -0,0 +1,183 @@
+use super::{decode, Format};
+use telemetry_protocol::*;
+
+/// `SYSTEM_RECORD` as sent by the firmware
+const SYSTEM_FRAME: [u8; 17] = [
+    0x00, 0x01, 0x09, 0x78, 0x56, 0x34, 0x12, 0x03, 0x01, 0x03, 0x05, 0x01, 0x01, 0x03, 0x9C, 0x1D,
+    0x00,
+];
+
+const SYSTEM_RECORD: TelemetryRecord = TelemetryRecord::System(SystemTelemetry {
+    timestamp: 0x1234_5678,
+    state: 3,
+    control_allowed: true,
+    vehicle_profile: 3,
+    dropped_records: 5,
+});
+
+/// `MODULE_RECORD` as sent by the firmware
+const MODULE_FRAME: [u8; 21] = [
+    0x00, 0x04, 0x01, 0xE8, 0x03, 0x01, 0x08, 0x02, 0x0D, 0x04, 0x10, 0x02, 0x08, 0x01, 0x06, 0x08,
+    0xFF, 0x07, 0x5D, 0x1F, 0x00,
+];
+
+const MODULE_RECORD: TelemetryRecord = TelemetryRecord::Module(ModuleTelemetry {
+    timestamp: 1000,
+    module: TELEMETRY_MODULE_STEERING,
+    enabled: true,
+    operator_override: false,
+    dtcs: 0x04,
+    sensor_high: 528,
+    sensor_low: 264,
+    sensor_plausible: true,
+    spoof: Some((2048, 2047)),
+});
+
+fn encode(record: &TelemetryRecord) -> Vec<u8> {
+    let mut frame = [0; TELEMETRY_MAX_FRAME_LEN];
+    let len = record.encode(&mut frame);
+    frame[..len].to_vec()
+}
+
+/// The frame without its zero delimiters, as split by the decoder
+fn body(frame: &[u8]) -> &[u8] {
+    &frame[1..frame.len() - 1]
+}
+
+#[test]
+fn encodes_the_firmware_frame_layout() {
+    assert_eq!(encode(&SYSTEM_RECORD), SYSTEM_FRAME.to_vec());
+    assert_eq!(encode(&MODULE_RECORD), MODULE_FRAME.to_vec());
+}
+
+#[test]
+fn decodes_the_firmware_frame_layout() {
+    assert_eq!(
+        TelemetryRecord::decode(body(&SYSTEM_FRAME)),
+        Ok(SYSTEM_RECORD)
+    );
+    assert_eq!(
+        TelemetryRecord::decode(body(&MODULE_FRAME)),
+        Ok(MODULE_RECORD)
+    );
+}
+
+#[test]
+fn round_trips_every_record_type() {
+    let records = [
+        SYSTEM_RECORD,
+        MODULE_RECORD,
+        TelemetryRecord::Module(ModuleTelemetry {
+            timestamp: u32::MAX,
+            module: TELEMETRY_MODULE_BRAKE,
+            enabled: false,
+            operator_override: true,
+            dtcs: 0,
+            sensor_high: 0,
+            sensor_low: u16::MAX,
+            sensor_plausible: false,
+            spoof: None,
+        }),
+        TelemetryRecord::Event(EventTelemetry {
+            timestamp: 0,
+            event: TELEMETRY_EVENT_OPERATOR_OVERRIDE,
+            source: TELEMETRY_MODULE_THROTTLE,
+            value: 1,
+        }),
+    ];
+
+    for record in records.iter() {
+        let frame = encode(record);
+        assert_eq!(TelemetryRecord::decode(body(&frame)), Ok(*record));
+    }
+}
+
+#[test]
+fn frames_only_hold_zeros_as_delimiters() {
+    // all zero payload bytes but the record type
+    let record = TelemetryRecord::System(SystemTelemetry {
+        timestamp: 0,
+        state: 0,
+        control_allowed: false,
+        vehicle_profile: 0,
+        dropped_records: 0,
+    });
+    let frame = encode(&record);
+
+    assert_eq!(frame.first(), Some(&0));
+    assert_eq!(frame.last(), Some(&0));
+    assert!(body(&frame).iter().all(|&byte| byte != 0));
+    assert!(frame.len() <= TELEMETRY_MAX_FRAME_LEN);
+    assert_eq!(TelemetryRecord::decode(body(&frame)), Ok(record));
+}
+
+#[test]
+fn rejects_malformed_framing() {
+    // a code byte pointing past the end of the frame
+    let mut frame = body(&SYSTEM_FRAME).to_vec();
+    frame[0] = 0x10;
+    assert_eq!(
+        TelemetryRecord::decode(&frame),
+        Err(TelemetryDecodeError::Framing)
+    );
+
+    // a truncated frame
+    let frame = &body(&MODULE_FRAME)[..5];
+    assert!(TelemetryRecord::decode(frame).is_err());
+
+    // no room for a CRC
+    assert_eq!(
+        TelemetryRecord::decode(&[0x02, 0x01]),
+        Err(TelemetryDecodeError::Framing)
+    );
+
+    // too long for any record
+    let frame = [0x01; TELEMETRY_MAX_FRAME_LEN * 2];
+    assert_eq!(
+        TelemetryRecord::decode(&frame),
+        Err(TelemetryDecodeError::Framing)
+    );
+}
+
+#[test]
+fn rejects_corrupted_frames_by_their_crc() {
+    let frame = body(&MODULE_FRAME);
+
+    // every data and CRC byte, the others are COBS code bytes
+    for index in (1..4).chain(6..13).chain(14..19) {
+        let mut corrupted = frame.to_vec();
+        corrupted[index] ^= 0x40;
+
+        assert_eq!(
+            TelemetryRecord::decode(&corrupted),
+            Err(TelemetryDecodeError::Crc),
+            "byte {} corrupted",
+            index
+        );
+    }
+}
+
+#[test]
+fn skips_console_text_and_invalid_frames() {
+    let mut corrupted = SYSTEM_FRAME.to_vec();
+    corrupted[7] ^= 0x01;
+
+    let mut capture = b"boot\r\n".to_vec();
+    capture.extend_from_slice(&SYSTEM_FRAME);
+    capture.extend_from_slice(&corrupted);
+    capture.extend_from_slice(&MODULE_FRAME);
+
+    let mut out = Vec::new();
+    assert_eq!(decode(&capture, Format::Csv, &mut out).unwrap(), (2, 2));
+
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            super::CSV_HEADER,
+            "305419896,system,Active,true,3,5,,,,,,,,,,,,",
+            "1000,module,,,,,steering,true,false,4,528,264,true,2048,2047,,,",
+        ]
+    );
+}
@@ -0,0 +1,128 @@
+use accumulator::Accumulator;
+use sys_tick;
+use types::BrakeAccumulatorPumpPin;
+use vehicle::*;
+
+const LOW_PRESSURE: f32 = BRAKE_ACCUMULATOR_PRESSURE_MIN_IN_DECIBARS - 1.0;
+const MID_PRESSURE: f32 =
+    (BRAKE_ACCUMULATOR_PRESSURE_MIN_IN_DECIBARS + BRAKE_ACCUMULATOR_PRESSURE_MAX_IN_DECIBARS) / 2.0;
+
+fn update(
+    accumulator: &mut Accumulator,
+    pump: &mut BrakeAccumulatorPumpPin,
+    millis: u32,
+    pressure: f32,
+) -> bool {
+    sys_tick::set_millis(millis);
+    accumulator.update(pump, pressure)
+}
+
+#[test]
+fn pump_starts_below_the_minimum_pressure() {
+    let mut accumulator = Accumulator::new();
+    let mut pump = BrakeAccumulatorPumpPin::default();
+
+    assert!(!update(
+        &mut accumulator,
+        &mut pump,
+        0,
+        BRAKE_ACCUMULATOR_PRESSURE_MIN_IN_DECIBARS
+    ));
+    assert!(!pump.is_high);
+
+    assert!(!update(&mut accumulator, &mut pump, 10, LOW_PRESSURE));
+    assert!(pump.is_high);
+}
+
+#[test]
+fn pump_runs_until_the_maximum_pressure() {
+    let mut accumulator = Accumulator::new();
+    let mut pump = BrakeAccumulatorPumpPin::default();
+
+    update(&mut accumulator, &mut pump, 0, LOW_PRESSURE);
+    assert!(pump.is_high);
+
+    // no chatter between the thresholds
+    update(&mut accumulator, &mut pump, 1000, MID_PRESSURE);
+    assert!(pump.is_high);
+
+    update(
+        &mut accumulator,
+        &mut pump,
+        2000,
+        BRAKE_ACCUMULATOR_PRESSURE_MAX_IN_DECIBARS,
+    );
+    assert!(!pump.is_high);
+
+    update(&mut accumulator, &mut pump, 3000, MID_PRESSURE);
+    assert!(!pump.is_high);
+    assert!(!accumulator.pump_timed_out());
+}
+
+#[test]
+fn pump_on_time_restarts_with_the_pump() {
+    let mut accumulator = Accumulator::new();
+    let mut pump = BrakeAccumulatorPumpPin::default();
+    let almost_too_long = BRAKE_ACCUMULATOR_PUMP_ON_TIME_MAX_IN_MILLISECONDS - 1000;
+
+    update(&mut accumulator, &mut pump, 0, LOW_PRESSURE);
+    update(
+        &mut accumulator,
+        &mut pump,
+        almost_too_long,
+        BRAKE_ACCUMULATOR_PRESSURE_MAX_IN_DECIBARS,
+    );
+    assert!(!pump.is_high);
+
+    update(&mut accumulator, &mut pump, 20_000, LOW_PRESSURE);
+    assert!(pump.is_high);
+    assert!(!update(
+        &mut accumulator,
+        &mut pump,
+        20_000 + almost_too_long,
+        MID_PRESSURE
+    ));
+    assert!(pump.is_high);
+    assert!(!accumulator.pump_timed_out());
+}
+
+#[test]
+fn pump_times_out_once_and_stays_off() {
+    let mut accumulator = Accumulator::new();
+    let mut pump = BrakeAccumulatorPumpPin::default();
+
+    update(&mut accumulator, &mut pump, 0, LOW_PRESSURE);
+
+    assert!(!update(
+        &mut accumulator,
+        &mut pump,
+        BRAKE_ACCUMULATOR_PUMP_ON_TIME_MAX_IN_MILLISECONDS,
+        MID_PRESSURE
+    ));
+    assert!(pump.is_high);
+
+    assert!(update(
+        &mut accumulator,
+        &mut pump,
+        BRAKE_ACCUMULATOR_PUMP_ON_TIME_MAX_IN_MILLISECONDS + 1,
+        MID_PRESSURE
+    ));
+    assert!(!pump.is_high);
+    assert!(accumulator.pump_timed_out());
+
+    // reported once, the pump stays off until the next reset
+    assert!(!update(&mut accumulator, &mut pump, 30_000, LOW_PRESSURE));
+    assert!(!pump.is_high);
+    assert!(accumulator.pump_timed_out());
+}
+
+#[test]
+fn pump_on_time_survives_the_clock_wrapping() {
+    let mut accumulator = Accumulator::new();
+    let mut pump = BrakeAccumulatorPumpPin::default();
+
+    update(&mut accumulator, &mut pump, u32::MAX - 500, LOW_PRESSURE);
+    assert!(!update(&mut accumulator, &mut pump, 500, MID_PRESSURE));
+    assert!(pump.is_high);
+    assert!(!accumulator.pump_timed_out());
+}
@@ -0,0 +1,91 @@
+use calibration::{self, CalibrationTable};
+
+const VOLTAGE_MIN: f32 = 0.30;
+const VOLTAGE_MAX: f32 = 2.00;
+
+calibration_table! {
+    /// Rising pedal curve, steeper towards the end of its travel.
+    const RISING_TABLE: [VOLTAGE_MIN, VOLTAGE_MAX] =
+        [(0.0, 0.30), (0.25, 0.62), (0.5, 1.05), (1.0, 2.00)];
+}
+
+calibration_table! {
+    /// Falling torque curve, centered on zero torque.
+    const FALLING_TABLE: [VOLTAGE_MIN, VOLTAGE_MAX] =
+        [(-10.0, 2.00), (0.0, 1.20), (10.0, 0.40)];
+}
+
+fn table(table: Option<CalibrationTable>) -> CalibrationTable {
+    table.expect("calibration_table! declares Some table")
+}
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1.0e-4,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn macro_keeps_the_entries_in_order() {
+    assert_eq!(
+        table(RISING_TABLE),
+        &[(0.0, 0.30), (0.25, 0.62), (0.5, 1.05), (1.0, 2.00)]
+    );
+    assert_eq!(
+        table(FALLING_TABLE),
+        &[(-10.0, 2.00), (0.0, 1.20), (10.0, 0.40)]
+    );
+}
+
+#[test]
+fn entries_are_returned_exactly() {
+    for &(input, volts) in table(RISING_TABLE) {
+        assert_close(calibration::interpolate(table(RISING_TABLE), input), volts);
+    }
+    for &(input, volts) in table(FALLING_TABLE) {
+        assert_close(calibration::interpolate(table(FALLING_TABLE), input), volts);
+    }
+}
+
+#[test]
+fn interpolates_between_entries() {
+    let rising = table(RISING_TABLE);
+    assert_close(calibration::interpolate(rising, 0.125), 0.46);
+    assert_close(calibration::interpolate(rising, 0.375), 0.835);
+    assert_close(calibration::interpolate(rising, 0.75), 1.525);
+
+    let falling = table(FALLING_TABLE);
+    assert_close(calibration::interpolate(falling, -5.0), 1.60);
+    assert_close(calibration::interpolate(falling, 2.5), 1.00);
+}
+
+#[test]
+fn holds_the_end_voltages_beyond_the_table() {
+    let rising = table(RISING_TABLE);
+    assert_close(calibration::interpolate(rising, -0.5), 0.30);
+    assert_close(calibration::interpolate(rising, 1.5), 2.00);
+
+    let falling = table(FALLING_TABLE);
+    assert_close(calibration::interpolate(falling, -20.0), 2.00);
+    assert_close(calibration::interpolate(falling, 20.0), 0.40);
+}
+
+#[test]
+fn output_is_monotonic() {
+    let rising = table(RISING_TABLE);
+    let mut previous = calibration::interpolate(rising, 0.0);
+    for step in 1..=100 {
+        let volts = calibration::interpolate(rising, step as f32 / 100.0);
+        assert!(
+            volts > previous,
+            "{} after {} at step {}",
+            volts,
+            previous,
+            step
+        );
+        previous = volts;
+    }
+}
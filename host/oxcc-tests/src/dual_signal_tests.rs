@@ -0,0 +1,163 @@
+use dual_signal::*;
+use std::cell::Cell;
+
+fn plausibility(relation: ChannelRelation) -> SignalPlausibility {
+    SignalPlausibility {
+        relation,
+        high_min: 100,
+        high_max: 900,
+        low_min: 50,
+        low_max: 450,
+        stuck_high_threshold: 1003,
+    }
+}
+
+#[test]
+fn ratio_model_tracks_the_scaled_low_channel() {
+    let model = plausibility(ChannelRelation::Ratio {
+        ratio: 2.0,
+        tolerance: 40,
+    });
+
+    assert!(model.is_plausible(400, 200));
+    // at the tolerance on either side
+    assert!(model.is_plausible(440, 200));
+    assert!(model.is_plausible(360, 200));
+    // just beyond it
+    assert!(model.is_mismatched(441, 200));
+    assert!(model.is_mismatched(359, 200));
+    assert!(!model.is_plausible(441, 200));
+}
+
+#[test]
+fn offset_model_bounds_the_channel_difference() {
+    let model = plausibility(ChannelRelation::Offset { min: -20, max: 300 });
+
+    assert!(model.is_plausible(400, 100));
+    assert!(model.is_plausible(400, 420));
+    assert!(!model.is_mismatched(400, 100));
+    assert!(model.is_mismatched(401, 100));
+    assert!(model.is_mismatched(400, 421));
+}
+
+#[test]
+fn sum_model_bounds_mirrored_channels() {
+    let model = SignalPlausibility {
+        relation: ChannelRelation::Sum {
+            min: 900,
+            max: 1100,
+        },
+        high_min: 68,
+        high_max: 927,
+        low_min: 68,
+        low_max: 927,
+        stuck_high_threshold: 1003,
+    };
+
+    // one channel rises as the other falls
+    assert!(model.is_plausible(500, 500));
+    assert!(model.is_plausible(800, 200));
+    assert!(model.is_plausible(200, 800));
+    assert!(model.is_plausible(450, 450));
+    assert!(model.is_plausible(550, 550));
+    // both moving the same way
+    assert!(model.is_mismatched(449, 450));
+    assert!(model.is_mismatched(551, 550));
+}
+
+#[test]
+fn readings_outside_the_channel_ranges_are_implausible() {
+    let model = plausibility(ChannelRelation::Offset {
+        min: -1000,
+        max: 1000,
+    });
+
+    assert!(!model.is_out_of_range(100, 50));
+    assert!(!model.is_out_of_range(900, 450));
+    assert!(model.is_out_of_range(99, 200));
+    assert!(model.is_out_of_range(901, 200));
+    assert!(model.is_out_of_range(400, 49));
+    assert!(model.is_out_of_range(400, 451));
+    assert!(!model.is_plausible(400, 451));
+}
+
+#[test]
+fn stuck_high_is_detected_on_either_channel() {
+    let model = SignalPlausibility {
+        relation: ChannelRelation::Offset {
+            min: -1100,
+            max: 1100,
+        },
+        high_min: 0,
+        high_max: 1100,
+        low_min: 0,
+        low_max: 1100,
+        stuck_high_threshold: 1003,
+    };
+
+    assert!(!model.is_stuck_high(1002, 1002));
+    assert!(model.is_stuck_high(1003, 500));
+    assert!(model.is_stuck_high(500, 1003));
+    // within the channel ranges and relation, stuck all the same
+    assert!(!model.is_out_of_range(1050, 500));
+    assert!(!model.is_mismatched(1050, 500));
+    assert!(!model.is_plausible(1050, 500));
+}
+
+struct FixedReader {
+    high: Cell<u16>,
+    low: Cell<u16>,
+}
+
+impl HighLowReader for FixedReader {
+    fn read_high(&self) -> u16 {
+        self.high.get()
+    }
+
+    fn read_low(&self) -> u16 {
+        self.low.get()
+    }
+}
+
+#[test]
+fn dual_signal_reading_holds_the_last_update() {
+    let model = plausibility(ChannelRelation::Ratio {
+        ratio: 2.0,
+        tolerance: 40,
+    });
+    let mut signal = DualSignal::new(
+        0,
+        0,
+        FixedReader {
+            high: Cell::new(400),
+            low: Cell::new(200),
+        },
+    );
+
+    signal.update();
+    assert_eq!(
+        signal.reading(&model),
+        SensorReading {
+            high: 400,
+            low: 200,
+            plausible: true,
+        }
+    );
+    assert_eq!(signal.average(), 300);
+    assert_eq!(signal.diff(), 200);
+
+    // no new sample until the next update
+    signal.reader().low.set(300);
+    assert_eq!(signal.low(), 200);
+
+    signal.update();
+    assert_eq!(
+        signal.reading(&model),
+        SensorReading {
+            high: 400,
+            low: 300,
+            plausible: false,
+        }
+    );
+    assert_eq!(signal.diff(), 100);
+}
@@ -0,0 +1,155 @@
+//! Host unit tests of the target independent firmware modules
+//!
+//! The firmware sources are included as they are, the crates they
+//! depend on are replaced by the minimal stand-ins below.
+//!
+//! ```bash
+//! cargo test --target x86_64-unknown-linux-gnu
+//! ```
+
+// the firmware is no_std and uses `core` paths
+extern crate core;
+
+#[allow(dead_code)]
+#[path = "../../../src/brake/kia_soul_petrol/accumulator.rs"]
+mod accumulator;
+#[allow(dead_code)]
+#[macro_use]
+#[path = "../../../src/calibration.rs"]
+mod calibration;
+#[allow(dead_code)]
+#[path = "../../../src/dual_signal.rs"]
+mod dual_signal;
+#[allow(dead_code)]
+#[path = "../../../src/override_rearm.rs"]
+mod override_rearm;
+#[allow(dead_code)]
+#[path = "../../../src/pedal_selection.rs"]
+mod pedal_selection;
+#[allow(dead_code)]
+#[path = "../../../src/pid.rs"]
+mod pid;
+#[allow(dead_code)]
+#[path = "../../../src/brake/kia_soul_petrol/pressure_controller.rs"]
+mod pressure_controller;
+#[allow(dead_code)]
+#[path = "../../../src/slew_rate_limiter.rs"]
+mod slew_rate_limiter;
+#[allow(dead_code)]
+#[path = "../../../src/system_state.rs"]
+mod system_state;
+
+#[cfg(test)]
+mod accumulator_tests;
+#[cfg(test)]
+mod calibration_tests;
+#[cfg(test)]
+mod dual_signal_tests;
+#[cfg(test)]
+mod override_rearm_tests;
+#[cfg(test)]
+mod pedal_selection_tests;
+#[cfg(test)]
+mod pid_tests;
+#[cfg(test)]
+mod pressure_controller_tests;
+#[cfg(test)]
+mod slew_rate_limiter_tests;
+#[cfg(test)]
+mod system_state_tests;
+
+/// Stand-in for the board constants used by the firmware
+mod board {
+    pub const DAC_SAMPLE_AVERAGE_COUNT: u32 = 20;
+}
+
+/// Stand-in for the parts of the board support crate used by the firmware
+mod nucleo_f767zi {
+    pub mod hal {
+        pub mod prelude {
+            /// Stand-in for the `embedded-hal` output pin
+            pub trait OutputPin {
+                fn set_high(&mut self);
+                fn set_low(&mut self);
+            }
+        }
+    }
+}
+
+/// Stand-in for the pin types of the board, the pins record their level
+mod types {
+    use nucleo_f767zi::hal::prelude::OutputPin;
+
+    #[derive(Default)]
+    pub struct BrakeAccumulatorPumpPin {
+        pub is_high: bool,
+    }
+
+    impl OutputPin for BrakeAccumulatorPumpPin {
+        fn set_high(&mut self) {
+            self.is_high = true;
+        }
+
+        fn set_low(&mut self) {
+            self.is_high = false;
+        }
+    }
+}
+
+/// Stand-in for the Kia Soul Petrol profile constants used by the brake,
+/// the values of `src/vehicles/kial_soul_petrol.toml`
+mod vehicle {
+    use pid::PidGains;
+
+    pub const BRAKE_ACCUMULATOR_PRESSURE_MIN_IN_DECIBARS: f32 = 777.6;
+    pub const BRAKE_ACCUMULATOR_PRESSURE_MAX_IN_DECIBARS: f32 = 878.0;
+    pub const BRAKE_ACCUMULATOR_PUMP_ON_TIME_MAX_IN_MILLISECONDS: u32 = 10_000;
+
+    pub const BRAKE_PID_GAINS: PidGains = PidGains {
+        proportional: 0.65,
+        integral: 1.75,
+        derivative: 0.0,
+    };
+    pub const BRAKE_PID_WINDUP_GUARD: f32 = 30.0;
+    pub const BRAKE_PID_ACCUMULATOR_SOLENOID_CLAMPED_MAX: f32 = 110.0;
+    pub const BRAKE_PID_RELEASE_SOLENOID_CLAMPED_MAX: f32 = 60.0;
+}
+
+/// Stand-in for the parts of the `num` crate used by the firmware
+mod num {
+    pub fn abs(a: i32) -> i32 {
+        a.abs()
+    }
+
+    pub fn clamp<T: PartialOrd>(a: T, min: T, max: T) -> T {
+        if a < min {
+            min
+        } else if a > max {
+            max
+        } else {
+            a
+        }
+    }
+}
+
+/// Stand-in for the SysTick clock, the tests set the time
+mod sys_tick {
+    use std::cell::Cell;
+
+    thread_local! {
+        static MILLIS: Cell<u32> = const { Cell::new(0) };
+    }
+
+    #[cfg(test)]
+    pub fn set_millis(millis: u32) {
+        MILLIS.with(|m| m.set(millis));
+    }
+
+    pub fn millis() -> u32 {
+        MILLIS.with(Cell::get)
+    }
+
+    pub fn millis_since(earlier: u32) -> u32 {
+        millis().wrapping_sub(earlier)
+    }
+}
@@ -0,0 +1,109 @@
+use override_rearm::{OverrideRearm, RearmPolicy, RearmState};
+use sys_tick;
+
+fn overridden(policy: RearmPolicy) -> OverrideRearm {
+    sys_tick::set_millis(0);
+    let mut rearm = OverrideRearm::new(policy);
+    rearm.override_detected();
+    rearm
+}
+
+#[test]
+fn starts_armed() {
+    let mut rearm = OverrideRearm::new(RearmPolicy::Acknowledge);
+
+    assert_eq!(rearm.state(), RearmState::Armed);
+    assert!(rearm.update(false));
+    assert_eq!(rearm.state(), RearmState::Armed);
+}
+
+#[test]
+fn stays_overridden_above_the_threshold() {
+    for &policy in &[
+        RearmPolicy::Immediate,
+        RearmPolicy::QuietTime(100),
+        RearmPolicy::Acknowledge,
+    ] {
+        let mut rearm = overridden(policy);
+
+        assert!(!rearm.update(false));
+        assert_eq!(rearm.state(), RearmState::Overridden);
+    }
+}
+
+#[test]
+fn immediate_rearms_below_the_threshold() {
+    let mut rearm = overridden(RearmPolicy::Immediate);
+
+    assert!(rearm.update(true));
+    assert_eq!(rearm.state(), RearmState::Armed);
+}
+
+#[test]
+fn quiet_time_rearms_after_the_duration() {
+    let mut rearm = overridden(RearmPolicy::QuietTime(100));
+
+    sys_tick::set_millis(1000);
+    assert!(!rearm.update(true));
+    assert_eq!(rearm.state(), RearmState::QuietTime);
+
+    sys_tick::set_millis(1099);
+    assert!(!rearm.update(true));
+    assert_eq!(rearm.state(), RearmState::QuietTime);
+
+    sys_tick::set_millis(1100);
+    assert!(rearm.update(true));
+    assert_eq!(rearm.state(), RearmState::Armed);
+}
+
+#[test]
+fn quiet_time_restarts_on_a_new_override() {
+    let mut rearm = overridden(RearmPolicy::QuietTime(100));
+
+    sys_tick::set_millis(1000);
+    assert!(!rearm.update(true));
+
+    // back above the threshold before the quiet time elapsed
+    sys_tick::set_millis(1050);
+    assert!(!rearm.update(false));
+    assert_eq!(rearm.state(), RearmState::Overridden);
+
+    sys_tick::set_millis(1100);
+    assert!(!rearm.update(true));
+    assert_eq!(rearm.state(), RearmState::QuietTime);
+
+    sys_tick::set_millis(1200);
+    assert!(rearm.update(true));
+}
+
+#[test]
+fn acknowledge_rearms_on_acknowledgement() {
+    let mut rearm = overridden(RearmPolicy::Acknowledge);
+
+    assert!(!rearm.update(true));
+    assert_eq!(rearm.state(), RearmState::AwaitingAcknowledge);
+
+    rearm.acknowledge();
+    assert_eq!(rearm.state(), RearmState::Armed);
+    assert!(rearm.update(true));
+}
+
+#[test]
+fn acknowledgement_is_ignored_above_the_threshold() {
+    let mut rearm = overridden(RearmPolicy::Acknowledge);
+
+    // acknowledged before the input was released
+    rearm.acknowledge();
+    assert_eq!(rearm.state(), RearmState::Overridden);
+
+    assert!(!rearm.update(true));
+    assert_eq!(rearm.state(), RearmState::AwaitingAcknowledge);
+}
+
+#[test]
+fn reported_states() {
+    assert_eq!(u8::from(RearmState::Armed), 0);
+    assert_eq!(u8::from(RearmState::Overridden), 1);
+    assert_eq!(u8::from(RearmState::QuietTime), 2);
+    assert_eq!(u8::from(RearmState::AwaitingAcknowledge), 3);
+}
@@ -0,0 +1,87 @@
+use pedal_selection::{select_pedal, Pedal, PedalThresholds};
+
+/// The thresholds of the vehicle profiles
+const THRESHOLDS: PedalThresholds = PedalThresholds {
+    throttle_engage: 0.05,
+    throttle_release: 0.0,
+    brake_engage: -0.1,
+    brake_release: -0.02,
+};
+
+fn select(pedal: Pedal, output: f32) -> Pedal {
+    select_pedal(pedal, output, &THRESHOLDS)
+}
+
+/// Pedals selected along the outputs, starting released
+fn sweep(outputs: &[f32]) -> Vec<Pedal> {
+    let mut pedal = Pedal::Released;
+
+    outputs
+        .iter()
+        .map(|&output| {
+            pedal = select(pedal, output);
+            pedal
+        })
+        .collect()
+}
+
+#[test]
+fn released_within_the_engage_thresholds() {
+    assert_eq!(select(Pedal::Released, 0.0), Pedal::Released);
+    assert_eq!(select(Pedal::Released, 0.049), Pedal::Released);
+    assert_eq!(select(Pedal::Released, -0.099), Pedal::Released);
+}
+
+#[test]
+fn engages_at_the_engage_thresholds() {
+    assert_eq!(select(Pedal::Released, 0.05), Pedal::Throttle);
+    assert_eq!(select(Pedal::Released, 1.0), Pedal::Throttle);
+    assert_eq!(select(Pedal::Released, -0.1), Pedal::Brake);
+    assert_eq!(select(Pedal::Released, -1.0), Pedal::Brake);
+}
+
+#[test]
+fn throttle_held_until_its_release_threshold() {
+    assert_eq!(select(Pedal::Throttle, 0.01), Pedal::Throttle);
+    assert_eq!(select(Pedal::Throttle, 0.0), Pedal::Released);
+    assert_eq!(select(Pedal::Throttle, -0.05), Pedal::Released);
+}
+
+#[test]
+fn brake_held_until_its_release_threshold() {
+    assert_eq!(select(Pedal::Brake, -0.05), Pedal::Brake);
+    assert_eq!(select(Pedal::Brake, -0.02), Pedal::Released);
+    assert_eq!(select(Pedal::Brake, 0.0), Pedal::Released);
+}
+
+#[test]
+fn switches_pedals_only_past_the_engage_threshold() {
+    // a large swing switches straight over
+    assert_eq!(select(Pedal::Throttle, -0.5), Pedal::Brake);
+    assert_eq!(select(Pedal::Brake, 0.5), Pedal::Throttle);
+}
+
+#[test]
+fn output_noise_does_not_chatter() {
+    assert_eq!(sweep(&[0.06, 0.03, 0.01, 0.04, 0.02]), [Pedal::Throttle; 5]);
+    assert_eq!(
+        sweep(&[-0.12, -0.05, -0.09, -0.03, -0.08]),
+        [Pedal::Brake; 5]
+    );
+}
+
+#[test]
+fn moves_through_the_band_between_the_pedals() {
+    assert_eq!(
+        sweep(&[0.1, 0.0, -0.05, -0.1, -0.02, 0.03, 0.05]),
+        [
+            Pedal::Throttle,
+            Pedal::Released,
+            Pedal::Released,
+            Pedal::Brake,
+            Pedal::Released,
+            Pedal::Released,
+            Pedal::Throttle,
+        ]
+    );
+}
@@ -0,0 +1,129 @@
+use pid::{Pid, PidGains};
+
+const DT: f32 = 0.01;
+
+fn gains(proportional: f32, integral: f32, derivative: f32) -> PidGains {
+    PidGains {
+        proportional,
+        integral,
+        derivative,
+    }
+}
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1.0e-4,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn proportional_step_response() {
+    let mut pid = Pid::new(gains(2.0, 0.0, 0.0), 10.0, -5.0, 5.0);
+
+    // the output follows the error without any memory
+    assert_close(pid.update(1.0, 0.0, DT), 2.0);
+    assert_close(pid.update(1.0, 0.0, DT), 2.0);
+    assert_close(pid.update(1.0, 0.25, DT), 1.5);
+    assert_close(pid.update(1.0, 1.0, DT), 0.0);
+    assert_close(pid.update(1.0, 1.5, DT), -1.0);
+
+    // and is clamped to the output limits
+    assert_close(pid.update(10.0, 0.0, DT), 5.0);
+    assert_close(pid.update(-10.0, 0.0, DT), -5.0);
+}
+
+#[test]
+fn integral_ramps_on_a_constant_error() {
+    let mut pid = Pid::new(gains(0.0, 2.0, 0.0), 10.0, -5.0, 5.0);
+
+    for step in 1..=10 {
+        assert_close(pid.update(1.0, 0.0, 0.1), 2.0 * 0.1 * step as f32);
+    }
+}
+
+#[test]
+fn integral_is_clamped_to_the_windup_guard() {
+    let mut pid = Pid::new(gains(0.0, 1.0, 0.0), 0.5, -10.0, 10.0);
+
+    for _ in 0..100 {
+        pid.update(1.0, 0.0, 0.1);
+    }
+    assert_close(pid.update(1.0, 0.0, 0.1), 0.5);
+
+    // unwinds from the guard, not from the accumulated error
+    assert_close(pid.update(-1.0, 0.0, 0.1), 0.4);
+}
+
+#[test]
+fn integral_stops_while_the_output_is_saturated() {
+    let mut pid = Pid::new(gains(0.0, 1.0, 0.0), 100.0, 0.0, 1.0);
+
+    assert_close(pid.update(1.0, 0.0, 1.0), 1.0);
+    for _ in 0..10 {
+        assert_close(pid.update(1.0, 0.0, 1.0), 1.0);
+    }
+
+    // recovers as soon as the error changes sign
+    assert_close(pid.update(-1.0, 0.0, 1.0), 0.0);
+}
+
+#[test]
+fn derivative_acts_on_a_measurement_step() {
+    let mut pid = Pid::new(gains(0.0, 0.0, 0.5), 10.0, -10.0, 10.0);
+
+    // no previous measurement yet
+    assert_close(pid.update(0.0, 0.0, 0.1), 0.0);
+
+    assert_close(pid.update(0.0, 1.0, 0.1), -5.0);
+    assert_close(pid.update(0.0, 1.0, 0.1), 0.0);
+    assert_close(pid.update(0.0, 0.5, 0.1), 2.5);
+
+    // a non-positive time step has no derivative
+    assert_close(pid.update(0.0, 2.0, 0.0), 0.0);
+}
+
+#[test]
+fn derivative_ignores_a_setpoint_step() {
+    let mut pid = Pid::new(gains(0.0, 0.0, 0.5), 10.0, -10.0, 10.0);
+
+    pid.update(0.0, 1.0, 0.1);
+    assert_close(pid.update(5.0, 1.0, 0.1), 0.0);
+}
+
+#[test]
+fn initialize_is_bumpless() {
+    let mut pid = Pid::new(gains(2.0, 0.5, 0.1), 20.0, -20.0, 20.0);
+
+    pid.initialize(3.0, 1.0, 10.0);
+
+    // continues from the applied output, only the integral moves on
+    assert_close(pid.update(3.0, 1.0, 0.0), 10.0);
+    assert_close(pid.update(3.0, 1.0, DT), 10.0 + 0.5 * 2.0 * DT);
+}
+
+#[test]
+fn initialize_without_integral_gain() {
+    let mut pid = Pid::new(gains(2.0, 0.0, 0.1), 20.0, -20.0, 20.0);
+
+    pid.initialize(3.0, 1.0, 10.0);
+
+    // nothing to seed, the output is the proportional term
+    assert_close(pid.update(3.0, 1.0, DT), 4.0);
+}
+
+#[test]
+fn reset_clears_the_state() {
+    let mut pid = Pid::new(gains(0.0, 1.0, 0.5), 10.0, -10.0, 10.0);
+
+    pid.initialize(1.0, 1.0, 5.0);
+    pid.reset();
+
+    assert_close(pid.update(1.0, 1.0, 0.1), 0.0);
+
+    // no derivative on the first measurement after a reset
+    pid.reset();
+    assert_close(pid.update(3.0, 3.0, 0.1), 0.0);
+}
@@ -0,0 +1,92 @@
+use pressure_controller::PressureController;
+use sys_tick;
+use vehicle::*;
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1.0e-3,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+fn update(controller: &mut PressureController, millis: u32, setpoint: f32, pressure: f32) -> f32 {
+    sys_tick::set_millis(millis);
+    controller.update(setpoint, pressure)
+}
+
+#[test]
+fn first_update_is_proportional() {
+    let mut controller = PressureController::new();
+
+    // the integral only starts with the second update
+    assert_close(
+        update(&mut controller, 5000, 100.0, 90.0),
+        BRAKE_PID_GAINS.proportional * 10.0,
+    );
+}
+
+#[test]
+fn integral_is_timed_by_the_clock() {
+    let mut controller = PressureController::new();
+    let proportional = BRAKE_PID_GAINS.proportional * 10.0;
+
+    update(&mut controller, 1000, 100.0, 90.0);
+
+    // 10 decibars for 100 ms
+    assert_close(
+        update(&mut controller, 1100, 100.0, 90.0),
+        proportional + BRAKE_PID_GAINS.integral * 1.0,
+    );
+
+    // and another 200 ms
+    assert_close(
+        update(&mut controller, 1300, 100.0, 90.0),
+        proportional + BRAKE_PID_GAINS.integral * 3.0,
+    );
+}
+
+#[test]
+fn integral_survives_the_clock_wrapping() {
+    let mut controller = PressureController::new();
+    let proportional = BRAKE_PID_GAINS.proportional * 10.0;
+
+    update(&mut controller, u32::MAX - 49, 100.0, 90.0);
+    assert_close(
+        update(&mut controller, 50, 100.0, 90.0),
+        proportional + BRAKE_PID_GAINS.integral * 1.0,
+    );
+}
+
+#[test]
+fn output_is_clamped_to_the_solenoid_ranges() {
+    let mut controller = PressureController::new();
+
+    assert_close(
+        update(&mut controller, 0, 1000.0, 0.0),
+        BRAKE_PID_ACCUMULATOR_SOLENOID_CLAMPED_MAX,
+    );
+
+    controller.reset();
+    assert_close(
+        update(&mut controller, 100, 0.0, 1000.0),
+        -BRAKE_PID_RELEASE_SOLENOID_CLAMPED_MAX,
+    );
+}
+
+#[test]
+fn reset_clears_the_integral_and_the_timing() {
+    let mut controller = PressureController::new();
+
+    update(&mut controller, 0, 100.0, 90.0);
+    update(&mut controller, 1000, 100.0, 90.0);
+
+    controller.reset();
+
+    // a long time since the last update, yet only the proportional term
+    assert_close(
+        update(&mut controller, 60_000, 100.0, 95.0),
+        BRAKE_PID_GAINS.proportional * 5.0,
+    );
+}
@@ -0,0 +1,141 @@
+use slew_rate_limiter::SlewRateLimiter;
+use sys_tick;
+
+/// 1.0 units/second, 0.001 per millisecond
+const MAX_RATE: f32 = 1.0;
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1.0e-4,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+fn limiter_at(millis: u32, output: f32) -> SlewRateLimiter {
+    sys_tick::set_millis(millis);
+    let mut limiter = SlewRateLimiter::new(MAX_RATE, 0.0);
+    limiter.reset(output);
+    limiter
+}
+
+#[test]
+fn increase_is_rate_limited() {
+    let mut limiter = limiter_at(1000, 0.0);
+
+    sys_tick::set_millis(1100);
+    assert_close(limiter.limit(1.0), 0.1);
+    assert!(limiter.is_active());
+
+    sys_tick::set_millis(1300);
+    assert_close(limiter.limit(1.0), 0.3);
+    assert!(limiter.is_active());
+}
+
+#[test]
+fn negative_increase_is_rate_limited() {
+    let mut limiter = limiter_at(1000, 0.0);
+
+    sys_tick::set_millis(1250);
+    assert_close(limiter.limit(-1.0), -0.25);
+    assert!(limiter.is_active());
+}
+
+#[test]
+fn update_steps_towards_the_last_target() {
+    let mut limiter = limiter_at(0, 0.0);
+    limiter.limit(0.5);
+
+    // no further commands, the output keeps moving each iteration
+    for millis in 1..=250 {
+        sys_tick::set_millis(millis);
+        limiter.update();
+    }
+    assert_close(limiter.update(), 0.25);
+    assert!(limiter.is_active());
+
+    sys_tick::set_millis(500);
+    assert_close(limiter.update(), 0.5);
+    assert!(!limiter.is_active());
+
+    // stays at the target
+    sys_tick::set_millis(1000);
+    assert_close(limiter.update(), 0.5);
+    assert!(!limiter.is_active());
+}
+
+#[test]
+fn uses_the_real_elapsed_time() {
+    let mut limiter = limiter_at(0, 0.0);
+
+    // longer intervals than a command period count in full
+    sys_tick::set_millis(400);
+    assert_close(limiter.limit(1.0), 0.4);
+}
+
+#[test]
+fn decrease_is_immediate() {
+    let mut limiter = limiter_at(0, 0.8);
+
+    assert_close(limiter.limit(0.2), 0.2);
+    assert!(!limiter.is_active());
+
+    assert_close(limiter.limit(0.0), 0.0);
+    assert!(!limiter.is_active());
+}
+
+#[test]
+fn negative_decrease_is_immediate() {
+    let mut limiter = limiter_at(0, -0.8);
+
+    assert_close(limiter.limit(-0.1), -0.1);
+    assert!(!limiter.is_active());
+}
+
+#[test]
+fn reversal_releases_then_limits() {
+    let mut limiter = limiter_at(0, 0.6);
+
+    // the positive output is released at once, the negative side is limited
+    sys_tick::set_millis(100);
+    assert_close(limiter.limit(-0.6), -0.1);
+    assert!(limiter.is_active());
+}
+
+#[test]
+fn decrease_cancels_the_limited_increase() {
+    let mut limiter = limiter_at(0, 0.0);
+
+    sys_tick::set_millis(200);
+    assert_close(limiter.limit(1.0), 0.2);
+
+    sys_tick::set_millis(210);
+    assert_close(limiter.limit(0.1), 0.1);
+    assert!(!limiter.is_active());
+}
+
+#[test]
+fn handles_the_tick_counter_wrapping() {
+    let mut limiter = limiter_at(u32::MAX - 49, 0.0);
+
+    sys_tick::set_millis(50);
+    assert_close(limiter.limit(1.0), 0.1);
+}
+
+#[test]
+fn reset_restarts_from_the_output() {
+    let mut limiter = limiter_at(0, 0.0);
+
+    sys_tick::set_millis(100);
+    limiter.limit(1.0);
+    assert!(limiter.is_active());
+
+    sys_tick::set_millis(5000);
+    limiter.reset(0.0);
+    assert!(!limiter.is_active());
+    assert_close(limiter.update(), 0.0);
+
+    sys_tick::set_millis(5050);
+    assert_close(limiter.limit(1.0), 0.05);
+}
@@ -0,0 +1,188 @@
+use system_state::SystemEvent::*;
+use system_state::{SystemEvent, SystemState, SystemStateMachine};
+
+/// State machine driven through the events
+fn after(events: &[SystemEvent]) -> SystemStateMachine {
+    let mut state_machine = SystemStateMachine::new();
+
+    for &event in events {
+        state_machine.handle(event);
+    }
+
+    state_machine
+}
+
+/// State machine in `Ready`, after a passed self test
+fn ready() -> SystemStateMachine {
+    after(&[
+        BootComplete {
+            unexpected_reset: false,
+        },
+        SelfTestPassed,
+    ])
+}
+
+#[test]
+fn boots_into_the_self_test() {
+    let mut state_machine = SystemStateMachine::new();
+
+    assert_eq!(state_machine.state(), SystemState::Boot);
+    assert_eq!(state_machine.handle(SelfTestPassed), None);
+    assert_eq!(
+        state_machine.handle(BootComplete {
+            unexpected_reset: false
+        }),
+        Some(SystemState::SelfTest)
+    );
+}
+
+#[test]
+fn unexpected_reset_is_faulted() {
+    let state_machine = after(&[BootComplete {
+        unexpected_reset: true,
+    }]);
+
+    assert_eq!(state_machine.state(), SystemState::Faulted);
+}
+
+#[test]
+fn self_test_result() {
+    assert_eq!(ready().state(), SystemState::Ready);
+
+    let state_machine = after(&[
+        BootComplete {
+            unexpected_reset: false,
+        },
+        SelfTestFailed,
+    ]);
+    assert_eq!(state_machine.state(), SystemState::Faulted);
+}
+
+#[test]
+fn self_test_ignores_other_events() {
+    let mut state_machine = after(&[BootComplete {
+        unexpected_reset: false,
+    }]);
+
+    for &event in &[ControlEnabled, ModuleFault, Error, ClearFaults] {
+        assert_eq!(state_machine.handle(event), None);
+    }
+    assert_eq!(state_machine.state(), SystemState::SelfTest);
+}
+
+#[test]
+fn active_while_control_is_enabled() {
+    let mut state_machine = ready();
+
+    assert_eq!(
+        state_machine.handle(ControlEnabled),
+        Some(SystemState::Active)
+    );
+    assert_eq!(state_machine.handle(ControlEnabled), None);
+    assert_eq!(
+        state_machine.handle(ControlDisabled),
+        Some(SystemState::Ready)
+    );
+}
+
+#[test]
+fn degraded_while_control_can_is_unhealthy() {
+    let mut state_machine = ready();
+    state_machine.handle(ControlEnabled);
+
+    assert_eq!(
+        state_machine.handle(ControlCanDegraded),
+        Some(SystemState::Degraded)
+    );
+    // control is disabled on entering Degraded
+    assert_eq!(state_machine.handle(ControlDisabled), None);
+    assert_eq!(
+        state_machine.handle(ControlCanRecovered),
+        Some(SystemState::Ready)
+    );
+}
+
+#[test]
+fn fault_goes_through_safe_stop() {
+    for &event in &[ModuleFault, Error] {
+        let mut state_machine = ready();
+        state_machine.handle(ControlEnabled);
+
+        assert_eq!(state_machine.handle(event), Some(SystemState::SafeStop));
+        assert_eq!(state_machine.handle(ControlEnabled), None);
+        assert_eq!(
+            state_machine.handle(ControlDisabled),
+            Some(SystemState::Faulted)
+        );
+    }
+}
+
+#[test]
+fn degraded_fault_goes_to_safe_stop() {
+    let mut state_machine = ready();
+    state_machine.handle(ControlCanDegraded);
+
+    assert_eq!(
+        state_machine.handle(ModuleFault),
+        Some(SystemState::SafeStop)
+    );
+}
+
+#[test]
+fn faulted_only_leaves_through_clear_faults() {
+    let mut state_machine = after(&[BootComplete {
+        unexpected_reset: true,
+    }]);
+
+    for &event in &[SelfTestPassed, ControlDisabled, ControlCanRecovered] {
+        assert_eq!(state_machine.handle(event), None);
+    }
+    assert_eq!(
+        state_machine.handle(ClearFaults),
+        Some(SystemState::SelfTest)
+    );
+}
+
+#[test]
+fn control_allowed_only_when_ready_or_active() {
+    let mut state_machine = SystemStateMachine::new();
+    assert!(!state_machine.control_allowed());
+
+    state_machine.handle(BootComplete {
+        unexpected_reset: false,
+    });
+    assert!(!state_machine.control_allowed());
+
+    state_machine.handle(SelfTestPassed);
+    assert!(state_machine.control_allowed());
+
+    state_machine.handle(ControlEnabled);
+    assert!(state_machine.control_allowed());
+
+    state_machine.handle(ControlCanDegraded);
+    assert!(!state_machine.control_allowed());
+
+    state_machine.handle(ControlCanRecovered);
+    state_machine.handle(Error);
+    assert!(!state_machine.control_allowed());
+
+    state_machine.handle(ControlDisabled);
+    assert!(!state_machine.control_allowed());
+}
+
+#[test]
+fn reported_states() {
+    let states = [
+        SystemState::Boot,
+        SystemState::SelfTest,
+        SystemState::Ready,
+        SystemState::Active,
+        SystemState::Degraded,
+        SystemState::Faulted,
+        SystemState::SafeStop,
+    ];
+
+    for (id, &state) in states.iter().enumerate() {
+        assert_eq!(usize::from(u8::from(state)), id);
+    }
+}
@@ -3,7 +3,9 @@ MEMORY
     /* NOTE K = KiBi = 1024 bytes */
 
     /* STM32F767ZI 2 MByte FLASH, 512 KByte RAM */
-    FLASH (rx) : ORIGIN = 0x08000000, LENGTH = 2048K
+    /* the last 256 KByte sector (11) holds the runtime parameters, */
+    /* see src/params.rs */
+    FLASH (rx) : ORIGIN = 0x08000000, LENGTH = 1792K
     RAM (xrw)  : ORIGIN = 0x20000000, LENGTH = 512K
 }
 
@@ -22,3 +24,13 @@ MEMORY
 
 /* Size of the heap (in bytes) */
 /* _heap_size = 1024; */
+
+/* Firmware image info (length and CRC), patched after linking */
+/* by scripts/patch-image-crc, see src/firmware_image.rs */
+SECTIONS
+{
+  .image_info : ALIGN(4)
+  {
+    KEEP(*(.image_info));
+  } > FLASH
+} INSERT AFTER .rodata;
@@ -21,7 +21,12 @@ use nucleo_f767zi::hal::stm32f7x7;
 use nucleo_f767zi::hal::stm32f7x7::{ADC1, ADC2, ADC3, IWDG};
 use nucleo_f767zi::led::Leds;
 use nucleo_f767zi::UserButtonPin;
-use vehicle::FAULT_HYSTERESIS;
+use params::{self, Param};
+#[cfg(feature = "kia-soul-petrol")]
+use solenoid_pwm::SolenoidPwm;
+use sys_tick;
+use vehicle::STEPS_PER_VOLT;
+use wwdg::Wwdg;
 
 pub use types::*;
 
@@ -69,19 +74,40 @@ pub const ADC_RESOLUTION: AdcResolution = AdcResolution::Bits10;
 /// pretty stable.
 pub const DAC_SAMPLE_AVERAGE_COUNT: u32 = 20;
 
+/// Number of ADC steps per volt corresponding to 1024 steps (2^10,
+/// see `ADC_RESOLUTION`) across 5 volts.
+pub const ADC_STEPS_PER_VOLT: f32 = 204.8;
+
+/// Whether the DAC spoof outputs are verified through the module
+/// ADC channels while control is enabled.
+///
+/// Enabled by the `spoof-readback-verification` feature.
+pub const SPOOF_READBACK_VERIFICATION: bool = cfg!(feature = "spoof-readback-verification");
+
+/// Maximum allowed difference between a commanded spoof output and
+/// its ADC readback. \[ADC steps\]
+pub const SPOOF_READBACK_TOLERANCE: u16 = 20;
+
+/// Converts a DAC output value into the ADC reading it is expected
+/// to produce
+pub fn dac_steps_to_adc_steps(dac_steps: u16) -> u16 {
+    ((f32::from(dac_steps) / STEPS_PER_VOLT) * ADC_STEPS_PER_VOLT) as u16
+}
+
 pub struct FullBoard {
     pub debug_console: DebugConsole,
     pub leds: Leds,
     pub user_button: UserButtonPin,
     pub can_publish_timer: CanPublishTimer,
     pub wdg: Iwdg<IWDG>,
+    pub wwdg: Wwdg,
     pub reset_conditions: ResetConditions,
     control_can: ControlCan,
     obd_can: ObdCan,
-    brake_pedal_position_sensor: BrakePedalPositionSensor,
+    brake_sensor: BrakeSensor,
     accelerator_position_sensor: AcceleratorPositionSensor,
     torque_sensor: TorqueSensor,
-    brake_dac: BrakeDac,
+    brake_actuator: BrakeActuator,
     throttle_dac: ThrottleDac,
     steering_dac: SteeringDac,
     brake_pins: BrakePins,
@@ -92,12 +118,19 @@ pub struct FullBoard {
     throttle_grounded_fault_timer: ThrottleGroundedFaultTimer,
     throttle_override_fault_timer: ThrottleOverrideFaultTimer,
     steering_grounded_fault_timer: SteeringGroundedFaultTimer,
+    brake_mismatch_fault_timer: BrakeMismatchFaultTimer,
+    throttle_mismatch_fault_timer: ThrottleMismatchFaultTimer,
+    steering_mismatch_fault_timer: SteeringMismatchFaultTimer,
+    brake_readback_fault_timer: BrakeReadbackFaultTimer,
+    throttle_readback_fault_timer: ThrottleReadbackFaultTimer,
+    steering_readback_fault_timer: SteeringReadbackFaultTimer,
 }
 
 pub struct Board {
     pub leds: Leds,
     pub user_button: UserButtonPin,
     pub wdg: Iwdg<IWDG>,
+    pub wwdg: Wwdg,
     pub reset_conditions: ResetConditions,
 }
 
@@ -127,6 +160,7 @@ impl FullBoard {
         let mut gpioe = peripherals.GPIOE.split(&mut rcc.ahb1);
         let mut gpiof = peripherals.GPIOF.split(&mut rcc.ahb1);
 
+        #[cfg(not(feature = "kia-soul-petrol"))]
         let brake_pins = BrakePins {
             spoof_enable: gpiod
                 .pd12
@@ -143,13 +177,59 @@ impl FullBoard {
         };
 
         // TODO - move these once DAC impl is ready
+        #[cfg(not(feature = "kia-soul-petrol"))]
         let brake_sck: BrakeSpiSckPin = gpioa.pa5.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+        #[cfg(not(feature = "kia-soul-petrol"))]
         let brake_miso: BrakeSpiMisoPin = gpioa.pa6.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+        #[cfg(not(feature = "kia-soul-petrol"))]
         let brake_mosi: BrakeSpiMosiPin = gpioa.pa7.into_af5(&mut gpioa.moder, &mut gpioa.afrl);
+        #[cfg(not(feature = "kia-soul-petrol"))]
         let brake_nss: BrakeSpiNssPin = gpioa
             .pa4
             .into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper);
 
+        #[cfg(feature = "kia-soul-petrol")]
+        let brake_pins = BrakePins {
+            master_cylinder_solenoid: gpiod
+                .pd12
+                .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper),
+            brake_light_enable: gpiod
+                .pd13
+                .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper),
+            accumulator_pump: gpiod
+                .pd14
+                .into_push_pull_output(&mut gpiod.moder, &mut gpiod.otyper),
+            pressure_check_1: gpiof
+                .pf13
+                .into_push_pull_output(&mut gpiof.moder, &mut gpiof.otyper),
+            pressure_check_2: gpiof
+                .pf12
+                .into_push_pull_output(&mut gpiof.moder, &mut gpiof.otyper),
+            master_cylinder_pressure_sensor_1: gpioa
+                .pa3
+                .into_analog_input(&mut gpioa.moder, &mut gpioa.pupdr),
+            master_cylinder_pressure_sensor_2: gpioc
+                .pc0
+                .into_analog_input(&mut gpioc.moder, &mut gpioc.pupdr),
+            accumulator_pressure_sensor: gpioa
+                .pa0
+                .into_analog_input(&mut gpioa.moder, &mut gpioa.pupdr),
+            wheel_pressure_sensor_front_left: gpioa
+                .pa4
+                .into_analog_input(&mut gpioa.moder, &mut gpioa.pupdr),
+            wheel_pressure_sensor_front_right: gpioa
+                .pa5
+                .into_analog_input(&mut gpioa.moder, &mut gpioa.pupdr),
+        };
+
+        #[cfg(feature = "kia-soul-petrol")]
+        let brake_solenoid_pins: BrakeSolenoidPins = (
+            gpioe.pe9.into_af1(&mut gpioe.moder, &mut gpioe.afrh),
+            gpioe.pe11.into_af1(&mut gpioe.moder, &mut gpioe.afrh),
+            gpioe.pe13.into_af1(&mut gpioe.moder, &mut gpioe.afrh),
+            gpioe.pe14.into_af1(&mut gpioe.moder, &mut gpioe.afrh),
+        );
+
         let throttle_pins = ThrottlePins {
             spoof_enable: gpioe
                 .pe2
@@ -229,6 +309,9 @@ impl FullBoard {
         };
         assert!(adc_clock <= 30_000_000);
 
+        // SysTick drives the monotonic millisecond clock
+        sys_tick::start(core_peripherals.SYST, clocks.sysclk().0);
+
         let mut leds = Leds::new(led_r, led_g, led_b);
         for led in leds.iter_mut() {
             led.off();
@@ -249,6 +332,8 @@ impl FullBoard {
         // loopback/silent mode can be used for testing
         // &CanConfig { loopback_mode: true, silent_mode: true,
         // ..CanConfig::default() },
+        // the self test temporarily switches control CAN into loopback/silent
+        // mode, see `CanGatewayModule::control_can_loopback_test`
         let control_can = Can::can1(
             peripherals.CAN1,
             (can1_tx, can1_rx),
@@ -277,15 +362,25 @@ impl FullBoard {
                 .expect("Failed to configure OBD CAN filter");
         }
 
-        let brake_spi: BrakeSpi = Spi::spi1(
-            peripherals.SPI1,
-            (brake_sck, brake_miso, brake_mosi),
-            DAC_MODE,
-            1.mhz().into(),
-            clocks,
-            &mut rcc.apb2,
+        #[cfg(not(feature = "kia-soul-petrol"))]
+        let brake_actuator: BrakeActuator = Mcp4922::new(
+            Spi::spi1(
+                peripherals.SPI1,
+                (brake_sck, brake_miso, brake_mosi),
+                DAC_MODE,
+                1.mhz().into(),
+                clocks,
+                &mut rcc.apb2,
+            ),
+            brake_nss,
         );
 
+        // APB2 timers are clocked at twice PCLK2 since the APB2
+        // prescaler is not 1
+        #[cfg(feature = "kia-soul-petrol")]
+        let brake_actuator: BrakeActuator =
+            SolenoidPwm::new(peripherals.TIM1, brake_solenoid_pins, clocks.pclk2().0 * 2);
+
         let throttle_spi: ThrottleSpi = Spi::spi2(
             peripherals.SPI2,
             (throttle_sck, throttle_miso, throttle_mosi),
@@ -304,6 +399,9 @@ impl FullBoard {
             &mut rcc.apb1,
         );
 
+        // applied on reset, the fault timers run at a fixed rate
+        let fault_hysteresis = params::get(Param::FaultHysteresis) as u32;
+
         FullBoard {
             debug_console: DebugConsole::new(serial),
             leds,
@@ -320,10 +418,11 @@ impl FullBoard {
                 peripherals.IWDG,
                 IwdgConfig::from(WatchdogTimeout::Wdto50ms),
             ),
+            wwdg: Wwdg::new(peripherals.WWDG),
             reset_conditions,
             control_can,
             obd_can,
-            brake_pedal_position_sensor: BrakePedalPositionSensor {
+            brake_sensor: BrakeSensor {
                 adc1: Adc::adc1(
                     peripherals.ADC1,
                     &mut c_adc,
@@ -350,7 +449,7 @@ impl FullBoard {
                     ADC_RESOLUTION,
                 ),
             },
-            brake_dac: Mcp4922::new(brake_spi, brake_nss),
+            brake_actuator,
             throttle_dac: Mcp4922::new(throttle_spi, throttle_nss),
             steering_dac: Mcp4922::new(steering_spi, steering_nss),
             brake_pins,
@@ -358,34 +457,70 @@ impl FullBoard {
             steering_pins,
             brake_grounded_fault_timer: BrakeGroundedFaultTimer::tim3(
                 peripherals.TIM3,
-                (1000 / FAULT_HYSTERESIS).hz(),
+                (1000 / fault_hysteresis).hz(),
                 clocks,
                 &mut rcc.apb1,
             ),
             brake_override_fault_timer: BrakeOverrideFaultTimer::tim4(
                 peripherals.TIM4,
-                (1000 / FAULT_HYSTERESIS).hz(),
+                (1000 / fault_hysteresis).hz(),
                 clocks,
                 &mut rcc.apb1,
             ),
             throttle_grounded_fault_timer: ThrottleGroundedFaultTimer::tim5(
                 peripherals.TIM5,
-                (1000 / FAULT_HYSTERESIS).hz(),
+                (1000 / fault_hysteresis).hz(),
                 clocks,
                 &mut rcc.apb1,
             ),
             throttle_override_fault_timer: ThrottleOverrideFaultTimer::tim6(
                 peripherals.TIM6,
-                (1000 / FAULT_HYSTERESIS).hz(),
+                (1000 / fault_hysteresis).hz(),
                 clocks,
                 &mut rcc.apb1,
             ),
             steering_grounded_fault_timer: SteeringGroundedFaultTimer::tim7(
                 peripherals.TIM7,
-                (1000 / FAULT_HYSTERESIS).hz(),
+                (1000 / fault_hysteresis).hz(),
+                clocks,
+                &mut rcc.apb1,
+            ),
+            brake_mismatch_fault_timer: BrakeMismatchFaultTimer::tim12(
+                peripherals.TIM12,
+                (1000 / fault_hysteresis).hz(),
+                clocks,
+                &mut rcc.apb1,
+            ),
+            throttle_mismatch_fault_timer: ThrottleMismatchFaultTimer::tim13(
+                peripherals.TIM13,
+                (1000 / fault_hysteresis).hz(),
                 clocks,
                 &mut rcc.apb1,
             ),
+            steering_mismatch_fault_timer: SteeringMismatchFaultTimer::tim14(
+                peripherals.TIM14,
+                (1000 / fault_hysteresis).hz(),
+                clocks,
+                &mut rcc.apb1,
+            ),
+            brake_readback_fault_timer: BrakeReadbackFaultTimer::tim9(
+                peripherals.TIM9,
+                (1000 / fault_hysteresis).hz(),
+                clocks,
+                &mut rcc.apb2,
+            ),
+            throttle_readback_fault_timer: ThrottleReadbackFaultTimer::tim10(
+                peripherals.TIM10,
+                (1000 / fault_hysteresis).hz(),
+                clocks,
+                &mut rcc.apb2,
+            ),
+            steering_readback_fault_timer: SteeringReadbackFaultTimer::tim11(
+                peripherals.TIM11,
+                (1000 / fault_hysteresis).hz(),
+                clocks,
+                &mut rcc.apb2,
+            ),
         }
     }
 
@@ -393,20 +528,26 @@ impl FullBoard {
         self,
     ) -> (
         Board,
-        BrakeDac,
+        BrakeActuator,
         BrakePins,
-        BrakePedalPositionSensor,
+        BrakeSensor,
         BrakeGroundedFaultTimer,
         BrakeOverrideFaultTimer,
+        BrakeMismatchFaultTimer,
+        BrakeReadbackFaultTimer,
         AcceleratorPositionSensor,
         ThrottleDac,
         ThrottlePins,
         ThrottleGroundedFaultTimer,
         ThrottleOverrideFaultTimer,
+        ThrottleMismatchFaultTimer,
+        ThrottleReadbackFaultTimer,
         TorqueSensor,
         SteeringDac,
         SteeringPins,
         SteeringGroundedFaultTimer,
+        SteeringMismatchFaultTimer,
+        SteeringReadbackFaultTimer,
         DebugConsole,
         CanPublishTimer,
         ControlCan,
@@ -418,13 +559,14 @@ impl FullBoard {
             user_button,
             can_publish_timer,
             wdg,
+            wwdg,
             reset_conditions,
             control_can,
             obd_can,
-            brake_pedal_position_sensor,
+            brake_sensor,
             accelerator_position_sensor,
             torque_sensor,
-            brake_dac,
+            brake_actuator,
             throttle_dac,
             steering_dac,
             brake_pins,
@@ -435,28 +577,41 @@ impl FullBoard {
             throttle_grounded_fault_timer,
             throttle_override_fault_timer,
             steering_grounded_fault_timer,
+            brake_mismatch_fault_timer,
+            throttle_mismatch_fault_timer,
+            steering_mismatch_fault_timer,
+            brake_readback_fault_timer,
+            throttle_readback_fault_timer,
+            steering_readback_fault_timer,
         } = self;
         (
             Board {
                 leds,
                 user_button,
                 wdg,
+                wwdg,
                 reset_conditions,
             },
-            brake_dac,
+            brake_actuator,
             brake_pins,
-            brake_pedal_position_sensor,
+            brake_sensor,
             brake_grounded_fault_timer,
             brake_override_fault_timer,
+            brake_mismatch_fault_timer,
+            brake_readback_fault_timer,
             accelerator_position_sensor,
             throttle_dac,
             throttle_pins,
             throttle_grounded_fault_timer,
             throttle_override_fault_timer,
+            throttle_mismatch_fault_timer,
+            throttle_readback_fault_timer,
             torque_sensor,
             steering_dac,
             steering_pins,
             steering_grounded_fault_timer,
+            steering_mismatch_fault_timer,
+            steering_readback_fault_timer,
             debug_console,
             can_publish_timer,
             control_can,
@@ -471,11 +626,25 @@ impl Board {
     }
 }
 
+/// Brake sensor owned by the vehicle's brake module
+#[cfg(not(feature = "kia-soul-petrol"))]
+pub type BrakeSensor = BrakePedalPositionSensor;
+#[cfg(feature = "kia-soul-petrol")]
+pub type BrakeSensor = BrakePressureSensor;
+
+/// Brake actuator owned by the vehicle's brake module
+#[cfg(not(feature = "kia-soul-petrol"))]
+pub type BrakeActuator = BrakeDac;
+#[cfg(feature = "kia-soul-petrol")]
+pub type BrakeActuator = BrakeSolenoidPwm;
+
 // brake module owns ADC1
+#[cfg(not(feature = "kia-soul-petrol"))]
 pub struct BrakePedalPositionSensor {
     adc1: Adc<ADC1>,
 }
 
+#[cfg(not(feature = "kia-soul-petrol"))]
 impl HighLowReader for BrakePedalPositionSensor {
     fn read_high(&self) -> u16 {
         self.adc1.read(AdcChannel::Adc123In3, ADC_SAMPLE_TIME)
@@ -485,6 +654,36 @@ impl HighLowReader for BrakePedalPositionSensor {
     }
 }
 
+// brake module owns ADC1
+// the high/low channels are the two master cylinder pressure sensors
+#[cfg(feature = "kia-soul-petrol")]
+pub struct BrakePressureSensor {
+    adc1: Adc<ADC1>,
+}
+
+#[cfg(feature = "kia-soul-petrol")]
+impl BrakePressureSensor {
+    pub fn read_accumulator(&self) -> u16 {
+        self.adc1.read(AdcChannel::Adc123In0, ADC_SAMPLE_TIME)
+    }
+    pub fn read_wheel_front_left(&self) -> u16 {
+        self.adc1.read(AdcChannel::Adc12In4, ADC_SAMPLE_TIME)
+    }
+    pub fn read_wheel_front_right(&self) -> u16 {
+        self.adc1.read(AdcChannel::Adc12In5, ADC_SAMPLE_TIME)
+    }
+}
+
+#[cfg(feature = "kia-soul-petrol")]
+impl HighLowReader for BrakePressureSensor {
+    fn read_high(&self) -> u16 {
+        self.adc1.read(AdcChannel::Adc123In3, ADC_SAMPLE_TIME)
+    }
+    fn read_low(&self) -> u16 {
+        self.adc1.read(AdcChannel::Adc123In10, ADC_SAMPLE_TIME)
+    }
+}
+
 // throttle module owns ADC2
 pub struct AcceleratorPositionSensor {
     adc2: Adc<ADC2>,
@@ -1,22 +1,24 @@
 //! Kia Soul EV/Niro brake module
 
 use super::types::*;
-use board::BrakePedalPositionSensor;
+use board::{BrakePedalPositionSensor, SPOOF_READBACK_VERIFICATION};
 use brake_can_protocol::*;
-use core::fmt::Write;
 use dac_mcp4922::DacOutput;
 use dtc::DtcBitfield;
-use dual_signal::DualSignal;
+use dual_signal::{DualSignal, SensorReading};
 use fault_can_protocol::*;
 use fault_condition::FaultCondition;
-use nucleo_f767zi::debug_console::DebugConsole;
 use nucleo_f767zi::hal::can::CanFrame;
 use nucleo_f767zi::hal::prelude::*;
 use num;
 use oscc_magic_byte::*;
+use override_can_protocol::*;
+use override_rearm::OverrideRearm;
 use oxcc_error::OxccError;
-use ranges;
-use vehicle::*;
+use params::{self, Param};
+use slew_rate_limiter::SlewRateLimiter;
+use system_state;
+use vehicle::{self, *};
 
 struct BrakeControlState<DTCS: DtcBitfield> {
     enabled: bool,
@@ -43,6 +45,12 @@ pub struct BrakeModule {
     control_state: BrakeControlState<u8>,
     grounded_fault_state: FaultCondition<BrakeGroundedFaultTimer>,
     operator_override_state: FaultCondition<BrakeOverrideFaultTimer>,
+    override_rearm: OverrideRearm,
+    sensor_mismatch_fault_state: FaultCondition<BrakeMismatchFaultTimer>,
+    readback_fault_state: FaultCondition<BrakeReadbackFaultTimer>,
+    commanded_spoof: Option<(u16, u16)>,
+    command_slew_rate_limiter: SlewRateLimiter,
+    brake_command: f32,
     brake_report: OsccBrakeReport,
     fault_report: OsccFaultReport,
     brake_dac: BrakeDac,
@@ -60,13 +68,26 @@ impl UnpreparedBrakeModule {
         brake_pedal_position_sensor: BrakePedalPositionSensor,
         grounded_fault_timer: BrakeGroundedFaultTimer,
         override_timer: BrakeOverrideFaultTimer,
+        mismatch_fault_timer: BrakeMismatchFaultTimer,
+        readback_fault_timer: BrakeReadbackFaultTimer,
     ) -> Self {
+        let profile = vehicle::profile();
+
         UnpreparedBrakeModule {
             brake_module: BrakeModule {
                 brake_pedal_position: DualSignal::new(0, 0, brake_pedal_position_sensor),
                 control_state: BrakeControlState::new(u8::default()),
                 grounded_fault_state: FaultCondition::new(grounded_fault_timer),
                 operator_override_state: FaultCondition::new(override_timer),
+                override_rearm: OverrideRearm::new(profile.brake_override_rearm_policy),
+                sensor_mismatch_fault_state: FaultCondition::new(mismatch_fault_timer),
+                readback_fault_state: FaultCondition::new(readback_fault_timer),
+                commanded_spoof: None,
+                command_slew_rate_limiter: SlewRateLimiter::new(
+                    profile.brake_command_slew_rate_limit,
+                    MINIMUM_BRAKE_COMMAND,
+                ),
+                brake_command: MINIMUM_BRAKE_COMMAND,
                 brake_report: OsccBrakeReport::new(),
                 fault_report: OsccFaultReport {
                     fault_origin_id: FAULT_ORIGIN_BRAKE,
@@ -87,7 +108,7 @@ impl UnpreparedBrakeModule {
 }
 
 impl BrakeModule {
-    pub fn disable_control(&mut self, debug_console: &mut DebugConsole) -> Result<(), OxccError> {
+    pub fn disable_control(&mut self) -> Result<(), OxccError> {
         if self.control_state.enabled {
             self.brake_pedal_position.prevent_signal_discontinuity();
 
@@ -100,7 +121,9 @@ impl BrakeModule {
             self.brake_pins.spoof_enable.set_low();
             self.brake_pins.brake_light_enable.set_low();
             self.control_state.enabled = false;
-            writeln!(debug_console, "Brake control disabled");
+            self.commanded_spoof = None;
+            self.brake_command = MINIMUM_BRAKE_COMMAND;
+            info!("Brake control disabled");
 
             return if let Err(e) = result {
                 Err(OxccError::from(e))
@@ -112,7 +135,12 @@ impl BrakeModule {
         Ok(())
     }
 
-    fn enable_control(&mut self, debug_console: &mut DebugConsole) -> Result<(), OxccError> {
+    pub fn enable_control(&mut self) -> Result<(), OxccError> {
+        if !system_state::control_allowed() {
+            warn!("Brake enable refused, control not allowed in this system state");
+            return Ok(());
+        }
+
         if !self.control_state.enabled && !self.control_state.operator_override {
             self.brake_pedal_position.prevent_signal_discontinuity();
 
@@ -126,7 +154,9 @@ impl BrakeModule {
             } else {
                 self.brake_pins.spoof_enable.set_high();
                 self.control_state.enabled = true;
-                writeln!(debug_console, "Brake control enabled");
+                self.commanded_spoof = None;
+                self.command_slew_rate_limiter.reset(MINIMUM_BRAKE_COMMAND);
+                info!("Brake control enabled");
                 Ok(())
             };
         }
@@ -140,29 +170,34 @@ impl BrakeModule {
         spoof_command_low: u16,
     ) -> Result<(), OxccError> {
         if self.control_state.enabled {
-            let spoof_high = BrakeSpoofHighSignal::clamp(spoof_command_high);
-            let spoof_low = BrakeSpoofLowSignal::clamp(spoof_command_low);
+            let profile = vehicle::profile();
+            let spoof_high = profile.brake_spoof_high_signal.clamp(spoof_command_high);
+            let spoof_low = profile.brake_spoof_low_signal.clamp(spoof_command_low);
 
-            if (spoof_high.val() > &BRAKE_LIGHT_SPOOF_HIGH_THRESHOLD)
-                || (spoof_low.val() > &BRAKE_LIGHT_SPOOF_LOW_THRESHOLD)
+            if (spoof_high > profile.brake_light_spoof_high_threshold)
+                || (spoof_low > profile.brake_light_spoof_low_threshold)
             {
                 self.brake_pins.brake_light_enable.set_high();
             } else {
                 self.brake_pins.brake_light_enable.set_low();
             }
 
+            let commanded_spoof = (spoof_high, spoof_low);
+
             // TODO - revisit this, enforce high->A, low->B
             self.brake_dac
-                .output_ab(ranges::coerce(spoof_high), ranges::coerce(spoof_low))?;
+                .output_ab(DacOutput::clamp(spoof_high), DacOutput::clamp(spoof_low))?;
+
+            self.commanded_spoof = Some(commanded_spoof);
         }
 
         Ok(())
     }
 
-    pub fn check_for_faults(
-        &mut self,
-        debug_console: &mut DebugConsole,
-    ) -> Result<Option<&OsccFaultReport>, OxccError> {
+    pub fn check_for_faults(&mut self) -> Result<Option<&OsccFaultReport>, OxccError> {
+        // sampled every iteration, the interlock also uses the readings
+        self.brake_pedal_position.update();
+
         if !self.control_state.enabled && !self.control_state.dtcs.are_any_set() {
             // Assumes this module already went through the proper transition into a faulted
             // and disabled state, and we do not want to double-report a possible duplicate
@@ -170,21 +205,32 @@ impl BrakeModule {
             return Ok(None);
         }
 
-        self.brake_pedal_position.update();
-
         let brake_pedal_position_average = self.brake_pedal_position.average();
+        let override_threshold = params::get(Param::BrakeOverrideThreshold) as u32;
 
-        let operator_overridden: bool = self.operator_override_state.condition_exceeded_duration(
-            brake_pedal_position_average >= BRAKE_PEDAL_OVERRIDE_THRESHOLD.into(),
-        );
+        let operator_overridden: bool = self
+            .operator_override_state
+            .condition_exceeded_duration(brake_pedal_position_average >= override_threshold);
 
         let inputs_grounded: bool = self
             .grounded_fault_state
             .check_voltage_grounded(&self.brake_pedal_position);
 
+        let inputs_implausible: bool = self.sensor_mismatch_fault_state.check_signal_implausible(
+            &self.brake_pedal_position,
+            &vehicle::profile().brake_pedal_position_sensor_plausibility,
+        );
+
+        let outputs_mismatched: bool = match self.commanded_spoof {
+            Some((spoof_high, spoof_low)) if SPOOF_READBACK_VERIFICATION => self
+                .readback_fault_state
+                .check_spoof_readback(&self.brake_pedal_position, spoof_high, spoof_low),
+            _ => self.readback_fault_state.condition_exceeded_duration(false),
+        };
+
         // sensor pins tied to ground - a value of zero indicates disconnection
         if inputs_grounded {
-            self.disable_control(debug_console)?;
+            self.disable_control()?;
 
             self.control_state
                 .dtcs
@@ -192,14 +238,31 @@ impl BrakeModule {
 
             self.update_fault_report();
 
-            writeln!(
-                debug_console,
-                "Bad value read from brake pedal position sensor"
-            );
+            error!("Bad value read from brake pedal position sensor");
+
+            Ok(Some(&self.fault_report))
+        } else if inputs_implausible {
+            self.disable_control()?;
+
+            self.control_state.dtcs.set(OSCC_BRAKE_DTC_SENSOR_MISMATCH);
+
+            self.update_fault_report();
+
+            error!("Implausible value read from brake pedal position sensor");
+
+            Ok(Some(&self.fault_report))
+        } else if outputs_mismatched {
+            self.disable_control()?;
+
+            self.control_state.dtcs.set(OSCC_BRAKE_DTC_SPOOF_MISMATCH);
+
+            self.update_fault_report();
+
+            error!("Brake spoof output readback mismatch");
 
             Ok(Some(&self.fault_report))
         } else if operator_overridden && !self.control_state.operator_override {
-            self.disable_control(debug_console)?;
+            self.disable_control()?;
 
             self.control_state
                 .dtcs
@@ -208,14 +271,21 @@ impl BrakeModule {
             self.update_fault_report();
 
             self.control_state.operator_override = true;
+            self.override_rearm.override_detected();
 
-            writeln!(debug_console, "Brake operator override");
+            warn!("Brake operator override");
 
             Ok(Some(&self.fault_report))
-        } else {
+        } else if self
+            .override_rearm
+            .update(brake_pedal_position_average < override_threshold)
+        {
             self.control_state.dtcs.clear_all();
             self.control_state.operator_override = false;
             Ok(None)
+        } else {
+            // overridden, waiting to be re-armed
+            Ok(None)
         }
     }
 
@@ -223,31 +293,92 @@ impl BrakeModule {
         self.fault_report.dtcs = self.control_state.dtcs;
     }
 
+    /// Brake command currently applied, the minimum command
+    /// if control is disabled
+    pub fn brake_command(&self) -> f32 {
+        self.brake_command
+    }
+
+    /// Brake pedal position sensor average, as last sampled by
+    /// `check_for_faults` \[steps\]
+    pub fn measured_pedal_position(&self) -> u32 {
+        self.brake_pedal_position.average()
+    }
+
+    /// Power-on self test of the sensor idle readings
+    ///
+    /// The brake pedal is expected to be released.
+    pub fn self_test_sensor(&mut self) -> bool {
+        self.brake_pedal_position.update();
+
+        let brake_pedal_position_average = self.brake_pedal_position.average();
+        let override_threshold = params::get(Param::BrakeOverrideThreshold) as u32;
+
+        vehicle::profile()
+            .brake_pedal_position_sensor_plausibility
+            .is_plausible(
+                self.brake_pedal_position.high(),
+                self.brake_pedal_position.low(),
+            )
+            && !(brake_pedal_position_average >= override_threshold)
+    }
+
+    /// Commanded DAC outputs (high, low), `None` while not spoofing
+    pub fn commanded_spoof(&self) -> Option<(u16, u16)> {
+        self.commanded_spoof
+    }
+
+    /// Reads the brake pedal position sensor
+    pub fn read_sensor(&mut self) -> SensorReading {
+        self.brake_pedal_position.update();
+        self.brake_pedal_position
+            .reading(&vehicle::profile().brake_pedal_position_sensor_plausibility)
+    }
+
+    /// Power-on self test of the DAC, the sensor readings are passed through
+    /// as done when control is disabled
+    pub fn self_test_dac(&mut self) -> bool {
+        self.brake_dac
+            .output_ab(
+                DacOutput::clamp(self.brake_pedal_position.low()),
+                DacOutput::clamp(self.brake_pedal_position.high()),
+            )
+            .is_ok()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.control_state.enabled
+    }
+
     pub fn supply_brake_report(&mut self) -> &OsccBrakeReport {
         self.brake_report.enabled = self.control_state.enabled;
         self.brake_report.operator_override = self.control_state.operator_override;
+        self.brake_report.override_rearm_state = self.override_rearm.state().into();
         self.brake_report.dtcs = self.control_state.dtcs;
+        self.brake_report.slew_rate_limited = self.command_slew_rate_limiter.is_active();
         &self.brake_report
     }
 
-    pub fn process_rx_frame(
-        &mut self,
-        can_frame: &CanFrame,
-        debug_console: &mut DebugConsole,
-    ) -> Result<(), OxccError> {
+    pub fn process_rx_frame(&mut self, can_frame: &CanFrame) -> Result<(), OxccError> {
         if let CanFrame::DataFrame(ref frame) = can_frame {
             let id: u32 = frame.id().into();
             let data = frame.data();
 
             if (data[0] == OSCC_MAGIC_BYTE_0) && (data[1] == OSCC_MAGIC_BYTE_1) {
                 if id == OSCC_BRAKE_ENABLE_CAN_ID.into() {
-                    self.enable_control(debug_console)?;
+                    self.enable_control()?;
                 } else if id == OSCC_BRAKE_DISABLE_CAN_ID.into() {
-                    self.disable_control(debug_console)?;
+                    self.disable_control()?;
                 } else if id == OSCC_BRAKE_COMMAND_CAN_ID.into() {
                     self.process_brake_command(&OsccBrakeCommand::from(frame))?;
+                } else if id == OXCC_OVERRIDE_ACKNOWLEDGE_CAN_ID.into() {
+                    if (data.len() == OXCC_OVERRIDE_ACKNOWLEDGE_CAN_DLC as usize)
+                        && OxccOverrideAcknowledge::from(frame).acknowledges(FAULT_ORIGIN_BRAKE)
+                    {
+                        self.override_rearm.acknowledge();
+                    }
                 } else if id == OSCC_FAULT_REPORT_CAN_ID.into() {
-                    self.process_fault_report(&OsccFaultReport::from(frame), debug_console)?;
+                    self.process_fault_report(&OsccFaultReport::from(frame))?;
                 }
             }
         }
@@ -255,41 +386,58 @@ impl BrakeModule {
         Ok(())
     }
 
-    fn process_fault_report(
-        &mut self,
-        fault_report: &OsccFaultReport,
-        debug_console: &mut DebugConsole,
-    ) -> Result<(), OxccError> {
-        writeln!(
-            debug_console,
+    fn process_fault_report(&mut self, fault_report: &OsccFaultReport) -> Result<(), OxccError> {
+        warn!(
             "Fault report received from: {} DTCs: {}",
             fault_report.fault_origin_id, fault_report.dtcs
         );
 
-        self.disable_control(debug_console)
+        self.disable_control()
     }
 
     fn process_brake_command(&mut self, command: &OsccBrakeCommand) -> Result<(), OxccError> {
-        let clamped_position = num::clamp(
-            command.pedal_command,
-            MINIMUM_BRAKE_COMMAND,
-            MAXIMUM_BRAKE_COMMAND,
-        );
+        self.command_position(command.pedal_command)
+    }
 
-        let spoof_voltage_low: f32 = num::clamp(
-            brake_position_to_volts_low(clamped_position),
-            BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN,
-            BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX,
-        );
+    /// Applies a brake pedal position command, as if received over CAN
+    pub fn command_position(&mut self, pedal_command: f32) -> Result<(), OxccError> {
+        let clamped_position =
+            num::clamp(pedal_command, MINIMUM_BRAKE_COMMAND, MAXIMUM_BRAKE_COMMAND);
 
-        let spoof_voltage_high: f32 = num::clamp(
-            brake_position_to_volts_high(clamped_position),
-            BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN,
-            BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX,
-        );
+        let clamped_position = self.command_slew_rate_limiter.limit(clamped_position);
+
+        self.output_brake_position(clamped_position)
+    }
+
+    /// Steps the slew rate limited position towards the last command,
+    /// called every main loop iteration
+    pub fn update_command(&mut self) -> Result<(), OxccError> {
+        if !self.control_state.enabled {
+            return Ok(());
+        }
+
+        let was_limited = self.command_slew_rate_limiter.is_active();
+        let position = self.command_slew_rate_limiter.update();
+
+        if was_limited {
+            self.output_brake_position(position)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn output_brake_position(&mut self, clamped_position: f32) -> Result<(), OxccError> {
+        if self.control_state.enabled {
+            self.brake_command = clamped_position;
+        }
 
-        let spoof_value_low = (STEPS_PER_VOLT * spoof_voltage_low) as u16;
-        let spoof_value_high = (STEPS_PER_VOLT * spoof_voltage_high) as u16;
+        let profile = vehicle::profile();
+        let spoof_value_low = profile
+            .brake_spoof_low_signal
+            .position_to_steps(clamped_position);
+        let spoof_value_high = profile
+            .brake_spoof_high_signal
+            .position_to_steps(clamped_position);
 
         self.update_brake(spoof_value_high, spoof_value_low)
     }
@@ -0,0 +1,66 @@
+//! Kia Soul Petrol brake accumulator
+//!
+//! The pump relay keeps the accumulator pressure between
+//! `BRAKE_ACCUMULATOR_PRESSURE_MIN_IN_DECIBARS` and
+//! `BRAKE_ACCUMULATOR_PRESSURE_MAX_IN_DECIBARS`, whether or not
+//! brake control is enabled.
+//!
+//! A pump running for longer than the on-time limit without reaching
+//! the maximum pressure is turned off and stays off until the next reset.
+
+use nucleo_f767zi::hal::prelude::*;
+use sys_tick;
+use types::BrakeAccumulatorPumpPin;
+use vehicle::*;
+
+pub struct Accumulator {
+    /// Time the pump was turned on, `None` while it is off
+    pump_started: Option<u32>,
+    pump_timed_out: bool,
+}
+
+impl Accumulator {
+    pub const fn new() -> Self {
+        Accumulator {
+            pump_started: None,
+            pump_timed_out: false,
+        }
+    }
+
+    /// Runs the pump from the accumulator pressure \[decibars\]
+    ///
+    /// Returns true once, when the pump on-time limit is exceeded.
+    pub fn update(&mut self, pump: &mut BrakeAccumulatorPumpPin, pressure: f32) -> bool {
+        if self.pump_timed_out {
+            return false;
+        }
+
+        match self.pump_started {
+            None if pressure < BRAKE_ACCUMULATOR_PRESSURE_MIN_IN_DECIBARS => {
+                pump.set_high();
+                self.pump_started = Some(sys_tick::millis());
+            }
+            Some(_) if pressure >= BRAKE_ACCUMULATOR_PRESSURE_MAX_IN_DECIBARS => {
+                pump.set_low();
+                self.pump_started = None;
+            }
+            Some(started)
+                if sys_tick::millis_since(started)
+                    > BRAKE_ACCUMULATOR_PUMP_ON_TIME_MAX_IN_MILLISECONDS =>
+            {
+                pump.set_low();
+                self.pump_started = None;
+                self.pump_timed_out = true;
+                return true;
+            }
+            _ => (),
+        }
+
+        false
+    }
+
+    /// Whether the pump was shut off by the on-time limit
+    pub fn pump_timed_out(&self) -> bool {
+        self.pump_timed_out
+    }
+}
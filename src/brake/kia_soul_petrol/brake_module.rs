@@ -1,3 +1,568 @@
 //! Kia Soul Petrol brake module
+//!
+//! The petrol brake is actuated hydraulically, the master cylinder
+//! solenoid isolates the driver's pedal while the accumulator and
+//! release solenoids are PWM driven by a PID controller to reach
+//! the commanded pressure at the front wheels.
+//!
+//! The accumulator pump and the pressure sensor check are
+//! serviced alongside the fault checks.
 
-unimplemented!();
+use super::types::*;
+use accumulator::Accumulator;
+use board::BrakePressureSensor;
+use brake_can_protocol::*;
+use dtc::DtcBitfield;
+use dual_signal::{DualSignal, SensorReading};
+use fault_can_protocol::*;
+use fault_condition::FaultCondition;
+use nucleo_f767zi::hal::can::CanFrame;
+use nucleo_f767zi::hal::prelude::*;
+use num;
+use oscc_magic_byte::*;
+use override_can_protocol::*;
+use override_rearm::OverrideRearm;
+use oxcc_error::OxccError;
+use params::{self, Param};
+use pressure_controller::PressureController;
+use pressure_sensor_check::PressureSensorCheck;
+use slew_rate_limiter::SlewRateLimiter;
+use system_state;
+use vehicle::{self, *};
+
+struct BrakeControlState<DTCS: DtcBitfield> {
+    enabled: bool,
+    operator_override: bool,
+    dtcs: DTCS,
+}
+
+impl<DTCS> BrakeControlState<DTCS>
+where
+    DTCS: DtcBitfield,
+{
+    pub const fn new(dtcs: DTCS) -> Self {
+        BrakeControlState {
+            enabled: false,
+            operator_override: false,
+            dtcs,
+        }
+    }
+}
+
+/// Brake module
+pub struct BrakeModule {
+    master_cylinder_pressure: DualSignal<BrakePressureSensor>,
+    control_state: BrakeControlState<u8>,
+    grounded_fault_state: FaultCondition<BrakeGroundedFaultTimer>,
+    operator_override_state: FaultCondition<BrakeOverrideFaultTimer>,
+    override_rearm: OverrideRearm,
+    sensor_mismatch_fault_state: FaultCondition<BrakeMismatchFaultTimer>,
+    wheel_pressure_fault_state: FaultCondition<BrakeReadbackFaultTimer>,
+    pressure_controller: PressureController,
+    accumulator: Accumulator,
+    pressure_sensor_check: PressureSensorCheck,
+    command_slew_rate_limiter: SlewRateLimiter,
+    brake_command: f32,
+    brake_report: OsccBrakeReport,
+    fault_report: OsccFaultReport,
+    brake_solenoids: BrakeSolenoidPwm,
+    brake_pins: BrakePins,
+}
+
+pub struct UnpreparedBrakeModule {
+    brake_module: BrakeModule,
+}
+
+impl UnpreparedBrakeModule {
+    pub fn new(
+        brake_solenoids: BrakeSolenoidPwm,
+        brake_pins: BrakePins,
+        brake_pressure_sensor: BrakePressureSensor,
+        grounded_fault_timer: BrakeGroundedFaultTimer,
+        override_timer: BrakeOverrideFaultTimer,
+        mismatch_fault_timer: BrakeMismatchFaultTimer,
+        wheel_pressure_fault_timer: BrakeReadbackFaultTimer,
+    ) -> Self {
+        let profile = vehicle::profile();
+
+        UnpreparedBrakeModule {
+            brake_module: BrakeModule {
+                master_cylinder_pressure: DualSignal::new(0, 0, brake_pressure_sensor),
+                control_state: BrakeControlState::new(u8::default()),
+                grounded_fault_state: FaultCondition::new(grounded_fault_timer),
+                operator_override_state: FaultCondition::new(override_timer),
+                override_rearm: OverrideRearm::new(profile.brake_override_rearm_policy),
+                sensor_mismatch_fault_state: FaultCondition::new(mismatch_fault_timer),
+                wheel_pressure_fault_state: FaultCondition::new(wheel_pressure_fault_timer),
+                pressure_controller: PressureController::new(),
+                accumulator: Accumulator::new(),
+                pressure_sensor_check: PressureSensorCheck::new(),
+                command_slew_rate_limiter: SlewRateLimiter::new(
+                    profile.brake_command_slew_rate_limit,
+                    MINIMUM_BRAKE_COMMAND,
+                ),
+                brake_command: MINIMUM_BRAKE_COMMAND,
+                brake_report: OsccBrakeReport::new(),
+                fault_report: OsccFaultReport {
+                    fault_origin_id: FAULT_ORIGIN_BRAKE,
+                    dtcs: 0,
+                },
+                brake_solenoids,
+                brake_pins,
+            },
+        }
+    }
+
+    pub fn prepare_module(self) -> BrakeModule {
+        let mut brake_module = self.brake_module;
+        brake_module.brake_solenoids.disable();
+        brake_module.brake_pins.master_cylinder_solenoid.set_low();
+        brake_module.brake_pins.brake_light_enable.set_low();
+        brake_module.brake_pins.accumulator_pump.set_low();
+        brake_module
+            .pressure_sensor_check
+            .release(&mut brake_module.brake_pins);
+        brake_module
+    }
+}
+
+impl BrakeModule {
+    pub fn disable_control(&mut self) -> Result<(), OxccError> {
+        if self.control_state.enabled {
+            // close the actuation and release paths before giving
+            // the brakes back to the driver
+            self.brake_solenoids.disable();
+            self.brake_pins.master_cylinder_solenoid.set_low();
+            self.brake_pins.brake_light_enable.set_low();
+            self.control_state.enabled = false;
+            self.pressure_controller.reset();
+            self.brake_command = MINIMUM_BRAKE_COMMAND;
+            info!("Brake control disabled");
+        }
+
+        Ok(())
+    }
+
+    pub fn enable_control(&mut self) -> Result<(), OxccError> {
+        if !system_state::control_allowed() {
+            warn!("Brake enable refused, control not allowed in this system state");
+            return Ok(());
+        }
+
+        if !self.control_state.enabled && !self.control_state.operator_override {
+            if self.accumulator.pump_timed_out() {
+                warn!("Brake accumulator pump timed out, control unavailable");
+                return Ok(());
+            }
+
+            if self.pressure_sensor_check.has_failed() {
+                warn!("Brake pressure sensor check failed, control unavailable");
+                return Ok(());
+            }
+
+            let accumulator_pressure = self.accumulator_pressure();
+
+            if accumulator_pressure < BRAKE_ACCUMULATOR_PRESSURE_MIN_IN_DECIBARS {
+                warn!(
+                    "Brake accumulator pressure too low to enable: {} decibars",
+                    accumulator_pressure
+                );
+                return Ok(());
+            }
+
+            // the check pins override the wheel pressure readings
+            self.pressure_sensor_check.release(&mut self.brake_pins);
+
+            self.brake_solenoids.disable();
+            self.brake_pins.master_cylinder_solenoid.set_high();
+            self.control_state.enabled = true;
+            self.pressure_controller.reset();
+            self.command_slew_rate_limiter.reset(MINIMUM_BRAKE_COMMAND);
+            info!("Brake control enabled");
+        }
+
+        Ok(())
+    }
+
+    /// Runs one step of the pressure control loop
+    fn update_brake(&mut self) {
+        if !self.control_state.enabled {
+            return;
+        }
+
+        let wheel_pressure = self.wheel_pressure();
+
+        if wheel_pressure >= BRAKE_LIGHT_PRESSURE_THRESHOLD_IN_DECIBARS {
+            self.brake_pins.brake_light_enable.set_high();
+        } else {
+            self.brake_pins.brake_light_enable.set_low();
+        }
+
+        if self.brake_command <= MINIMUM_BRAKE_COMMAND {
+            // nothing requested, fully release the wheel pressure
+            self.pressure_controller.reset();
+            self.brake_solenoids.set_accumulator_duty(0);
+            self.brake_solenoids
+                .set_release_duty(BRAKE_RELEASE_SOLENOID_DUTY_CYCLE_MAX as u8);
+            return;
+        }
+
+        let pressure_target = interpolate(
+            brake_position_to_pedal(self.brake_command),
+            MINIMUM_BRAKE_COMMAND,
+            MAXIMUM_BRAKE_COMMAND,
+            BRAKE_PRESSURE_MIN_IN_DECIBARS,
+            BRAKE_PRESSURE_MAX_IN_DECIBARS,
+        );
+
+        let pid_output = self
+            .pressure_controller
+            .update(pressure_target, wheel_pressure);
+
+        if pid_output < BRAKE_PID_OUTPUT_MIN {
+            // pressure too high, release
+            let release_duty = interpolate(
+                num::clamp(
+                    -pid_output,
+                    BRAKE_PID_RELEASE_SOLENOID_CLAMPED_MIN,
+                    BRAKE_PID_RELEASE_SOLENOID_CLAMPED_MAX,
+                ),
+                BRAKE_PID_RELEASE_SOLENOID_CLAMPED_MIN,
+                BRAKE_PID_RELEASE_SOLENOID_CLAMPED_MAX,
+                BRAKE_RELEASE_SOLENOID_DUTY_CYCLE_MIN,
+                BRAKE_RELEASE_SOLENOID_DUTY_CYCLE_MAX,
+            );
+
+            self.brake_solenoids.set_accumulator_duty(0);
+            self.brake_solenoids.set_release_duty(release_duty as u8);
+        } else if pid_output > BRAKE_PID_OUTPUT_MAX {
+            // pressure too low, actuate
+            let accumulator_duty = interpolate(
+                num::clamp(
+                    pid_output,
+                    BRAKE_PID_ACCUMULATOR_SOLENOID_CLAMPED_MIN,
+                    BRAKE_PID_ACCUMULATOR_SOLENOID_CLAMPED_MAX,
+                ),
+                BRAKE_PID_ACCUMULATOR_SOLENOID_CLAMPED_MIN,
+                BRAKE_PID_ACCUMULATOR_SOLENOID_CLAMPED_MAX,
+                BRAKE_ACCUMULATOR_SOLENOID_DUTY_CYCLE_MIN,
+                BRAKE_ACCUMULATOR_SOLENOID_DUTY_CYCLE_MAX,
+            );
+
+            self.brake_solenoids.set_release_duty(0);
+            self.brake_solenoids
+                .set_accumulator_duty(accumulator_duty as u8);
+        } else {
+            // within range, hold the pressure
+            self.brake_solenoids.disable();
+        }
+    }
+
+    /// Checks for faults and steps the pressure control loop, which
+    /// then runs on the fresh sensor readings every main loop iteration
+    pub fn check_for_faults(&mut self) -> Result<Option<&OsccFaultReport>, OxccError> {
+        // the accumulator is kept charged and the pressure sensors are
+        // checked whether or not control is enabled, the master cylinder
+        // pressure is sampled every iteration for the interlock
+        self.master_cylinder_pressure.update();
+
+        let accumulator_pressure = self.accumulator_pressure();
+        let pump_timed_out = self
+            .accumulator
+            .update(&mut self.brake_pins.accumulator_pump, accumulator_pressure);
+
+        let sensor_check_failed = if self.control_state.enabled {
+            false
+        } else {
+            self.pressure_sensor_check
+                .update(&mut self.brake_pins, self.master_cylinder_pressure.reader())
+        };
+
+        if pump_timed_out {
+            self.disable_control()?;
+
+            self.control_state
+                .dtcs
+                .set(OSCC_BRAKE_DTC_ACCUMULATOR_PUMP_TIMEOUT);
+
+            self.update_fault_report();
+
+            error!(
+                "Brake accumulator pump exceeded its on-time at {} decibars",
+                accumulator_pressure
+            );
+
+            return Ok(Some(&self.fault_report));
+        } else if sensor_check_failed {
+            self.disable_control()?;
+
+            self.control_state
+                .dtcs
+                .set(OSCC_BRAKE_DTC_PRESSURE_SENSOR_CHECK);
+
+            self.update_fault_report();
+
+            error!("Brake pressure sensor check failed");
+
+            return Ok(Some(&self.fault_report));
+        }
+
+        if !self.control_state.enabled && !self.control_state.dtcs.are_any_set() {
+            // Assumes this module already went through the proper transition into a faulted
+            // and disabled state, and we do not want to double-report a possible duplicate
+            // fault.
+            return Ok(None);
+        }
+
+        let master_cylinder_pressure = self.master_cylinder_pressure_average();
+        let override_threshold = params::get(Param::BrakeOverrideThreshold);
+
+        let operator_overridden: bool = self
+            .operator_override_state
+            .condition_exceeded_duration(master_cylinder_pressure >= override_threshold);
+
+        let inputs_grounded: bool = self
+            .grounded_fault_state
+            .check_voltage_grounded(&self.master_cylinder_pressure);
+
+        let inputs_implausible: bool = self.sensor_mismatch_fault_state.check_signal_implausible(
+            &self.master_cylinder_pressure,
+            &BRAKE_PRESSURE_SENSOR_PLAUSIBILITY,
+        );
+
+        let wheel_inputs_grounded: bool = self
+            .wheel_pressure_fault_state
+            .condition_exceeded_duration(self.wheel_pressure_sensors_grounded());
+
+        // sensor pins tied to ground - a value of zero indicates disconnection
+        if inputs_grounded || wheel_inputs_grounded {
+            self.disable_control()?;
+
+            self.control_state
+                .dtcs
+                .set(OSCC_BRAKE_DTC_INVALID_SENSOR_VAL);
+
+            self.update_fault_report();
+
+            error!("Bad value read from brake pressure sensor");
+
+            Ok(Some(&self.fault_report))
+        } else if inputs_implausible {
+            self.disable_control()?;
+
+            self.control_state.dtcs.set(OSCC_BRAKE_DTC_SENSOR_MISMATCH);
+
+            self.update_fault_report();
+
+            error!("Implausible value read from master cylinder pressure sensor");
+
+            Ok(Some(&self.fault_report))
+        } else if operator_overridden && !self.control_state.operator_override {
+            self.disable_control()?;
+
+            self.control_state
+                .dtcs
+                .set(OSCC_BRAKE_DTC_OPERATOR_OVERRIDE);
+
+            self.update_fault_report();
+
+            self.control_state.operator_override = true;
+            self.override_rearm.override_detected();
+
+            warn!("Brake operator override");
+
+            Ok(Some(&self.fault_report))
+        } else if self
+            .override_rearm
+            .update(master_cylinder_pressure < override_threshold)
+        {
+            self.control_state.dtcs.clear_all();
+            if self.accumulator.pump_timed_out() {
+                // the pump stays off until reset
+                self.control_state
+                    .dtcs
+                    .set(OSCC_BRAKE_DTC_ACCUMULATOR_PUMP_TIMEOUT);
+            }
+            if self.pressure_sensor_check.has_failed() {
+                // the sensors stay suspect until reset
+                self.control_state
+                    .dtcs
+                    .set(OSCC_BRAKE_DTC_PRESSURE_SENSOR_CHECK);
+            }
+            self.control_state.operator_override = false;
+            self.update_brake();
+            Ok(None)
+        } else {
+            // overridden, waiting to be re-armed
+            Ok(None)
+        }
+    }
+
+    fn update_fault_report(&mut self) {
+        self.fault_report.dtcs = self.control_state.dtcs;
+    }
+
+    fn master_cylinder_pressure_average(&self) -> f32 {
+        raw_adc_to_pressure(self.master_cylinder_pressure.average() as u16)
+    }
+
+    /// Average of the front wheel pressures \[decibars\]
+    fn wheel_pressure(&self) -> f32 {
+        let sensor = self.master_cylinder_pressure.reader();
+
+        (raw_adc_to_pressure(sensor.read_wheel_front_left())
+            + raw_adc_to_pressure(sensor.read_wheel_front_right()))
+            / 2.0
+    }
+
+    fn wheel_pressure_sensors_grounded(&self) -> bool {
+        let sensor = self.master_cylinder_pressure.reader();
+
+        (sensor.read_wheel_front_left() == 0) || (sensor.read_wheel_front_right() == 0)
+    }
+
+    fn accumulator_pressure(&self) -> f32 {
+        raw_adc_to_pressure(self.master_cylinder_pressure.reader().read_accumulator())
+    }
+
+    /// Brake command currently applied, the minimum command
+    /// if control is disabled
+    pub fn brake_command(&self) -> f32 {
+        self.brake_command
+    }
+
+    /// Master cylinder pressure average, as last sampled by
+    /// `check_for_faults` \[decibars\]
+    pub fn measured_pedal_position(&self) -> f32 {
+        self.master_cylinder_pressure_average()
+    }
+
+    /// Power-on self test of the sensor idle readings
+    ///
+    /// The brake pedal is expected to be released.
+    pub fn self_test_sensor(&mut self) -> bool {
+        self.master_cylinder_pressure.update();
+
+        BRAKE_PRESSURE_SENSOR_PLAUSIBILITY.is_plausible(
+            self.master_cylinder_pressure.high(),
+            self.master_cylinder_pressure.low(),
+        ) && !self.wheel_pressure_sensors_grounded()
+            && !(self.master_cylinder_pressure_average()
+                >= params::get(Param::BrakeOverrideThreshold))
+    }
+
+    /// Commanded DAC outputs, always `None` as the brake solenoids
+    /// are driven by PWM
+    pub fn commanded_spoof(&self) -> Option<(u16, u16)> {
+        None
+    }
+
+    /// Reads the master cylinder pressure sensors
+    pub fn read_sensor(&mut self) -> SensorReading {
+        self.master_cylinder_pressure.update();
+        self.master_cylinder_pressure
+            .reading(&BRAKE_PRESSURE_SENSOR_PLAUSIBILITY)
+    }
+
+    /// Power-on self test of the solenoid PWM outputs, which take the
+    /// place of the DAC on this vehicle
+    pub fn self_test_dac(&mut self) -> bool {
+        self.brake_solenoids.disable();
+        self.brake_solenoids.verify(0, 0)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.control_state.enabled
+    }
+
+    pub fn supply_brake_report(&mut self) -> &OsccBrakeReport {
+        self.brake_report.enabled = self.control_state.enabled;
+        self.brake_report.operator_override = self.control_state.operator_override;
+        self.brake_report.override_rearm_state = self.override_rearm.state().into();
+        self.brake_report.dtcs = self.control_state.dtcs;
+        self.brake_report.slew_rate_limited = self.command_slew_rate_limiter.is_active();
+        &self.brake_report
+    }
+
+    pub fn process_rx_frame(&mut self, can_frame: &CanFrame) -> Result<(), OxccError> {
+        if let CanFrame::DataFrame(ref frame) = can_frame {
+            let id: u32 = frame.id().into();
+            let data = frame.data();
+
+            if (data[0] == OSCC_MAGIC_BYTE_0) && (data[1] == OSCC_MAGIC_BYTE_1) {
+                if id == OSCC_BRAKE_ENABLE_CAN_ID.into() {
+                    self.enable_control()?;
+                } else if id == OSCC_BRAKE_DISABLE_CAN_ID.into() {
+                    self.disable_control()?;
+                } else if id == OSCC_BRAKE_COMMAND_CAN_ID.into() {
+                    self.process_brake_command(&OsccBrakeCommand::from(frame))?;
+                } else if id == OXCC_OVERRIDE_ACKNOWLEDGE_CAN_ID.into() {
+                    if (data.len() == OXCC_OVERRIDE_ACKNOWLEDGE_CAN_DLC as usize)
+                        && OxccOverrideAcknowledge::from(frame).acknowledges(FAULT_ORIGIN_BRAKE)
+                    {
+                        self.override_rearm.acknowledge();
+                    }
+                } else if id == OSCC_FAULT_REPORT_CAN_ID.into() {
+                    self.process_fault_report(&OsccFaultReport::from(frame))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn process_fault_report(&mut self, fault_report: &OsccFaultReport) -> Result<(), OxccError> {
+        warn!(
+            "Fault report received from: {} DTCs: {}",
+            fault_report.fault_origin_id, fault_report.dtcs
+        );
+
+        self.disable_control()
+    }
+
+    fn process_brake_command(&mut self, command: &OsccBrakeCommand) -> Result<(), OxccError> {
+        self.command_position(command.pedal_command)
+    }
+
+    /// Applies a brake pedal position command, as if received over CAN
+    ///
+    /// The command only sets the pressure target, the solenoids are
+    /// driven by the control loop.
+    pub fn command_position(&mut self, pedal_command: f32) -> Result<(), OxccError> {
+        let clamped_position =
+            num::clamp(pedal_command, MINIMUM_BRAKE_COMMAND, MAXIMUM_BRAKE_COMMAND);
+
+        let clamped_position = self.command_slew_rate_limiter.limit(clamped_position);
+
+        if self.control_state.enabled {
+            self.brake_command = clamped_position;
+        }
+
+        Ok(())
+    }
+
+    /// Steps the slew rate limited pressure target towards the last
+    /// command, called every main loop iteration
+    pub fn update_command(&mut self) -> Result<(), OxccError> {
+        if self.control_state.enabled {
+            self.brake_command = self.command_slew_rate_limiter.update();
+        }
+
+        Ok(())
+    }
+}
+
+/// Converts a pressure sensor reading, clamped to the brake
+/// system range \[decibars\]
+fn raw_adc_to_pressure(steps: u16) -> f32 {
+    num::clamp(
+        brake_pressure_sensor_steps_to_decibars(f32::from(steps)),
+        BRAKE_PRESSURE_MIN_IN_DECIBARS,
+        BRAKE_PRESSURE_MAX_IN_DECIBARS,
+    )
+}
+
+/// Linear mapping of `x` from the input range to the output range
+fn interpolate(x: f32, in_min: f32, in_max: f32, out_min: f32, out_max: f32) -> f32 {
+    (x - in_min) * (out_max - out_min) / (in_max - in_min) + out_min
+}
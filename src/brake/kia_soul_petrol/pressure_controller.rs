@@ -0,0 +1,51 @@
+//! Kia Soul Petrol brake pressure controller
+//!
+//! A PID controller of the front wheel pressure, timed by the
+//! millisecond clock. A positive output drives the accumulator solenoid
+//! up to `BRAKE_PID_ACCUMULATOR_SOLENOID_CLAMPED_MAX`, a negative one the
+//! release solenoid down to `-BRAKE_PID_RELEASE_SOLENOID_CLAMPED_MAX`.
+
+use pid::Pid;
+use sys_tick;
+use vehicle::{
+    BRAKE_PID_ACCUMULATOR_SOLENOID_CLAMPED_MAX, BRAKE_PID_GAINS,
+    BRAKE_PID_RELEASE_SOLENOID_CLAMPED_MAX, BRAKE_PID_WINDUP_GUARD,
+};
+
+pub struct PressureController {
+    pid: Pid,
+    last_update: Option<u32>,
+}
+
+impl PressureController {
+    pub const fn new() -> Self {
+        PressureController {
+            pid: Pid::new(
+                BRAKE_PID_GAINS,
+                BRAKE_PID_WINDUP_GUARD,
+                -BRAKE_PID_RELEASE_SOLENOID_CLAMPED_MAX,
+                BRAKE_PID_ACCUMULATOR_SOLENOID_CLAMPED_MAX,
+            ),
+            last_update: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.pid.reset();
+        self.last_update = None;
+    }
+
+    /// Returns the controller output for the given pressures \[decibars\]
+    ///
+    /// The first update after a reset only has the proportional term.
+    pub fn update(&mut self, setpoint: f32, pressure: f32) -> f32 {
+        let now = sys_tick::millis();
+        let dt = match self.last_update {
+            Some(last_update) => now.wrapping_sub(last_update) as f32 / 1000.0,
+            None => 0.0,
+        };
+        self.last_update = Some(now);
+
+        self.pid.update(setpoint, pressure, dt)
+    }
+}
@@ -0,0 +1,102 @@
+//! Kia Soul Petrol brake pressure sensor check
+//!
+//! Asserting the pressure check pins (PCK1/PCK2) switches the front wheel
+//! pressure sensor inputs to a reference, which must then read within
+//! `BRAKE_PRESSURE_SENSOR_CHECK_VALUE_MIN/MAX`.
+//!
+//! The check overrides the readings used by the pressure control loop,
+//! so it only runs while control is disabled. Once failed, the check
+//! stops and stays failed until the next reset.
+
+use board::BrakePressureSensor;
+use nucleo_f767zi::hal::prelude::*;
+use sys_tick;
+use types::BrakePins;
+use vehicle::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum CheckState {
+    /// Check pins released since the given time
+    Idle(u32),
+    /// Check pins asserted since the given time
+    Asserted(u32),
+}
+
+pub struct PressureSensorCheck {
+    state: CheckState,
+    consecutive_failures: u8,
+    failed: bool,
+}
+
+impl PressureSensorCheck {
+    pub const fn new() -> Self {
+        PressureSensorCheck {
+            state: CheckState::Idle(0),
+            consecutive_failures: 0,
+            failed: false,
+        }
+    }
+
+    /// Steps the periodic check
+    ///
+    /// Returns true once, when `BRAKE_PRESSURE_SENSOR_CHECK_FAILURE_LIMIT`
+    /// consecutive checks have failed.
+    pub fn update(&mut self, brake_pins: &mut BrakePins, sensor: &BrakePressureSensor) -> bool {
+        if self.failed {
+            return false;
+        }
+
+        match self.state {
+            CheckState::Idle(since) => {
+                if sys_tick::millis_since(since)
+                    >= BRAKE_PRESSURE_SENSOR_CHECK_INTERVAL_IN_MILLISECONDS
+                {
+                    brake_pins.pressure_check_1.set_high();
+                    brake_pins.pressure_check_2.set_high();
+                    self.state = CheckState::Asserted(sys_tick::millis());
+                }
+
+                false
+            }
+            CheckState::Asserted(since) => {
+                if sys_tick::millis_since(since)
+                    < BRAKE_PRESSURE_SENSOR_CHECK_SETTLE_TIME_IN_MILLISECONDS
+                {
+                    return false;
+                }
+
+                let passed = is_check_value(sensor.read_wheel_front_left())
+                    && is_check_value(sensor.read_wheel_front_right());
+
+                self.release(brake_pins);
+
+                if passed {
+                    self.consecutive_failures = 0;
+                    false
+                } else {
+                    self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+                    self.failed =
+                        self.consecutive_failures >= BRAKE_PRESSURE_SENSOR_CHECK_FAILURE_LIMIT;
+                    self.failed
+                }
+            }
+        }
+    }
+
+    /// Whether the failure limit was reached
+    pub fn has_failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Releases the check pins, the interval restarts
+    pub fn release(&mut self, brake_pins: &mut BrakePins) {
+        brake_pins.pressure_check_1.set_low();
+        brake_pins.pressure_check_2.set_low();
+        self.state = CheckState::Idle(sys_tick::millis());
+    }
+}
+
+fn is_check_value(value: u16) -> bool {
+    (value >= BRAKE_PRESSURE_SENSOR_CHECK_VALUE_MIN)
+        && (value <= BRAKE_PRESSURE_SENSOR_CHECK_VALUE_MAX)
+}
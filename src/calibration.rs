@@ -0,0 +1,121 @@
+//! Calibration tables
+//!
+//! Piecewise-linear alternative to the straight line spoof voltage
+//! conversions of the vehicle profiles, for pedal and torque sensors
+//! whose measured curves are not linear.
+//!
+//! Tables are declared with `calibration_table!`, which rejects at compile
+//! time a table that leaves its voltage bounds or is not monotonic.
+
+/// Calibration table entries, each an input (position or torque) and
+/// the spoof voltage at that input \[volts\]
+///
+/// Inputs are strictly increasing and voltages strictly monotonic,
+/// increasing or decreasing.
+pub type CalibrationTable = &'static [(f32, f32)];
+
+/// Declares an optional calibration table constant of the vehicle profile
+///
+/// The first bracketed pair is the voltage bounds the table must stay
+/// inside, typically the `*_SIGNAL_VOLTAGE_MIN/MAX` of the signal.
+///
+/// ```ignore
+/// calibration_table! {
+///     /// Measured accelerator pedal low signal curve.
+///     const THROTTLE_SPOOF_LOW_SIGNAL_CALIBRATION_TABLE: [
+///         THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN,
+///         THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX
+///     ] = [(0.0, 0.30), (0.25, 0.62), (0.5, 1.05), (1.0, 2.00)];
+/// }
+/// ```
+///
+/// A table that breaks the bounds, with inputs that are not strictly
+/// increasing, or with voltages that change direction, fails to build
+/// with an "index out of bounds" error on the offending entry.
+///
+/// The host tests in `host/oxcc-tests` declare their tables with it.
+// unused while no vehicle profile sets a table
+#[allow(unused_macros)]
+macro_rules! calibration_table {
+    (
+        $(#[$attr:meta])*
+        $vis:vis const $name:ident: [$min:expr, $max:expr] = [
+            ($input_first:expr, $volts_first:expr),
+            ($input_second:expr, $volts_second:expr)
+            $(, ($input:expr, $volts:expr))* $(,)*
+        ];
+    ) => {
+        $(#[$attr])*
+        $vis const $name: Option<::calibration::CalibrationTable> = Some(&calibration_table!(
+            @entries
+            [
+                (
+                    $input_first,
+                    [$volts_first][
+                        (!((($volts_first) >= ($min)) & (($volts_first) <= ($max)))) as usize
+                    ]
+                ),
+            ]
+            ($input_first, $volts_first);
+            ($min, $max, ($volts_second) - ($volts_first));
+            ($input_second, $volts_second) $(, ($input, $volts))*
+        ));
+    };
+
+    // every entry after the first is checked against its predecessor,
+    // indexing past the one element array fails the constant evaluation
+    (
+        @entries [$($entries:tt)*]
+        ($input_previous:expr, $volts_previous:expr);
+        ($min:expr, $max:expr, $direction:expr);
+        ($input:expr, $volts:expr) $(, ($input_rest:expr, $volts_rest:expr))*
+    ) => {
+        calibration_table!(
+            @entries
+            [
+                $($entries)*
+                (
+                    $input,
+                    [$volts][
+                        (!((($input) > ($input_previous))
+                            & (($volts) >= ($min))
+                            & (($volts) <= ($max))
+                            & (((($volts) - ($volts_previous)) * ($direction)) > 0.0)))
+                            as usize
+                    ]
+                ),
+            ]
+            ($input, $volts);
+            ($min, $max, $direction);
+            $(($input_rest, $volts_rest)),*
+        )
+    };
+
+    (
+        @entries [$($entries:tt)*]
+        ($input_previous:expr, $volts_previous:expr);
+        ($min:expr, $max:expr, $direction:expr);
+    ) => {
+        [$($entries)*]
+    };
+}
+
+/// Interpolates the voltage at `input` \[volts\]
+///
+/// The voltage is held at the first and last entries beyond the table.
+pub fn interpolate(table: CalibrationTable, input: f32) -> f32 {
+    if input <= table[0].0 {
+        return table[0].1;
+    }
+
+    for window in table.windows(2) {
+        let (input_a, volts_a) = window[0];
+        let (input_b, volts_b) = window[1];
+
+        if input <= input_b {
+            return volts_a + ((input - input_a) / (input_b - input_a)) * (volts_b - volts_a);
+        }
+    }
+
+    table[table.len() - 1].1
+}
@@ -2,14 +2,69 @@
 
 use brake_can_protocol::*;
 use fault_can_protocol::*;
+use fault_record::FaultRecord;
+use fault_record_can_protocol::*;
+use identity_can_protocol::*;
 use nucleo_f767zi::hal::can::{CanError, CanFrame, DataFrame, RxFifo};
 use nucleo_f767zi::hal::prelude::*;
+use nucleo_f767zi::hal::stm32f7x7::{CAN1, CAN2};
+use obd_can_protocol::*;
 use oscc_magic_byte::*;
 use oxcc_error::OxccError;
+use param_can_protocol::*;
+use self_test::SelfTestResult;
+use self_test_can_protocol::*;
 use steering_can_protocol::*;
+use sys_tick;
+use system_state_can_protocol::*;
 use throttle_can_protocol::*;
 use types::*;
-use vehicle::*;
+use vehicle;
+
+/// Number of consecutive control CAN transmit timeouts after which
+/// the control CAN bus is considered unhealthy
+const CONTROL_CAN_TX_TIMEOUT_LIMIT: u32 = 10;
+
+/// Maximum time to wait for a bxCAN mode change or a loopback frame,
+/// kept short so the self test stays within the watchdog deadlines. \[ms\]
+const CONTROL_CAN_SELF_TEST_TIMEOUT: u32 = 5;
+
+/// Payload of the self test loopback frame, following the magic bytes
+const CONTROL_CAN_LOOPBACK_PATTERN: [u8; 6] = [0x55, 0xAA, 0x33, 0xCC, 0x0F, 0xF0];
+
+/// Frame counters since boot, saturating
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CanStatistics {
+    pub control_rx_frames: u32,
+    pub control_tx_frames: u32,
+    pub control_tx_timeouts: u32,
+    pub control_tx_errors: u32,
+    pub obd_rx_frames: u32,
+    pub obd_republished_frames: u32,
+}
+
+/// bxCAN error state, decoded from the CAN_ESR register
+#[derive(Copy, Clone, Debug)]
+pub struct CanErrorState {
+    pub transmit_errors: u8,
+    pub receive_errors: u8,
+    /// Last error code, 0 is no error
+    pub last_error_code: u8,
+    pub bus_off: bool,
+    pub error_passive: bool,
+}
+
+impl From<u32> for CanErrorState {
+    fn from(esr: u32) -> Self {
+        CanErrorState {
+            transmit_errors: ((esr >> 16) & 0xFF) as _,
+            receive_errors: ((esr >> 24) & 0xFF) as _,
+            last_error_code: ((esr >> 4) & 0x07) as _,
+            bus_off: (esr & (1 << 2)) != 0,
+            error_passive: (esr & (1 << 1)) != 0,
+        }
+    }
+}
 
 pub struct CanGatewayModule {
     can_publish_timer: CanPublishTimer,
@@ -19,6 +74,17 @@ pub struct CanGatewayModule {
     throttle_report_can_frame: DataFrame,
     brake_report_can_frame: DataFrame,
     steering_report_can_frame: DataFrame,
+    system_state_report_can_frame: DataFrame,
+    fault_record_report_can_frame: DataFrame,
+    self_test_report_can_frame: DataFrame,
+    identity_report_can_frame: DataFrame,
+    param_response_can_frame: DataFrame,
+    control_can_tx_timeouts: u32,
+    statistics: CanStatistics,
+    obd_wheel_speed: Option<ObdWheelSpeed>,
+    obd_wheel_speed_timestamp: u32,
+    obd_steering_wheel_angle: Option<ObdSteeringWheelAngle>,
+    obd_steering_wheel_angle_timestamp: u32,
 }
 
 impl CanGatewayModule {
@@ -35,6 +101,17 @@ impl CanGatewayModule {
             brake_report_can_frame: default_brake_report_data_frame(),
             throttle_report_can_frame: default_throttle_report_data_frame(),
             steering_report_can_frame: default_steering_report_data_frame(),
+            system_state_report_can_frame: default_system_state_report_data_frame(),
+            fault_record_report_can_frame: default_fault_record_report_data_frame(),
+            self_test_report_can_frame: default_self_test_report_data_frame(),
+            identity_report_can_frame: default_identity_report_data_frame(),
+            param_response_can_frame: default_param_response_data_frame(),
+            control_can_tx_timeouts: 0,
+            statistics: CanStatistics::default(),
+            obd_wheel_speed: None,
+            obd_wheel_speed_timestamp: 0,
+            obd_steering_wheel_angle: None,
+            obd_steering_wheel_angle_timestamp: 0,
         }
     }
 
@@ -42,6 +119,8 @@ impl CanGatewayModule {
         // poll both OBD CAN FIFOs
         for fifo in &[RxFifo::Fifo0, RxFifo::Fifo1] {
             if let Ok(rx_frame) = self.obd_can().receive(fifo) {
+                self.statistics.obd_rx_frames = self.statistics.obd_rx_frames.saturating_add(1);
+                self.process_obd_frame(&rx_frame);
                 self.republish_obd_frame_to_control_can_bus(&rx_frame)?;
             }
         }
@@ -55,22 +134,184 @@ impl CanGatewayModule {
     ) -> Result<(), OxccError> {
         let id: u32 = frame.id().into();
 
-        let mut is_a_match = (id == KIA_SOUL_OBD_STEERING_WHEEL_ANGLE_CAN_ID.into())
-            || (id == KIA_SOUL_OBD_WHEEL_SPEED_CAN_ID.into())
-            || (id == KIA_SOUL_OBD_BRAKE_PRESSURE_CAN_ID.into());
+        let profile = vehicle::profile();
+
+        let is_a_match = (id == profile.obd_steering_wheel_angle_can_id.into())
+            || (id == profile.obd_wheel_speed_can_id.into())
+            || (id == profile.obd_brake_pressure_can_id.into())
+            || profile
+                .obd_throttle_pressure_can_id
+                .map_or(false, |throttle_pressure_can_id| {
+                    id == throttle_pressure_can_id.into()
+                });
 
-        #[cfg(feature = "kia-soul-ev")]
+        if is_a_match {
+            let result = self.control_can.transmit(&frame);
+            self.count_control_can_tx(&result);
+            if result.is_ok() {
+                self.statistics.obd_republished_frames =
+                    self.statistics.obd_republished_frames.saturating_add(1);
+            }
+            result?;
+        }
+
+        Ok(())
+    }
+
+    /// Keeps track of the OBD data used by OxCC itself
+    fn process_obd_frame(&mut self, frame: &CanFrame) {
+        if let CanFrame::DataFrame(ref frame) = frame {
+            let id: u32 = frame.id().into();
+
+            let profile = vehicle::profile();
+
+            if id == profile.obd_wheel_speed_can_id.into() {
+                // short frames are dropped, the readings then go stale
+                if frame.data().len() >= OBD_WHEEL_SPEED_CAN_DLC as usize {
+                    self.obd_wheel_speed = Some(ObdWheelSpeed::from(frame));
+                    self.obd_wheel_speed_timestamp = sys_tick::millis();
+                }
+            } else if id == profile.obd_steering_wheel_angle_can_id.into() {
+                if frame.data().len() >= OBD_STEERING_WHEEL_ANGLE_CAN_DLC as usize {
+                    self.obd_steering_wheel_angle = Some(ObdSteeringWheelAngle::from(frame));
+                    self.obd_steering_wheel_angle_timestamp = sys_tick::millis();
+                }
+            }
+        }
+    }
+
+    /// Vehicle speed from the OBD wheel speed frame \[km/h\]
+    ///
+    /// Returns `None` if no wheel speed frame has been received
+    /// or the last one is stale.
+    pub fn vehicle_speed(&self) -> Option<f32> {
+        if sys_tick::millis_since(self.obd_wheel_speed_timestamp)
+            > vehicle::profile().obd_wheel_speed_timeout
         {
-            if id == KIA_SOUL_OBD_THROTTLE_PRESSURE_CAN_ID.into() {
-                is_a_match = true;
+            return None;
+        }
+
+        self.obd_wheel_speed
+            .map(|wheel_speed| wheel_speed.vehicle_speed())
+    }
+
+    /// Steering wheel angle from the OBD steering wheel angle frame \[degrees\]
+    ///
+    /// Returns `None` if no steering wheel angle frame has been received
+    /// or the last one is stale.
+    pub fn steering_wheel_angle(&self) -> Option<f32> {
+        if sys_tick::millis_since(self.obd_steering_wheel_angle_timestamp)
+            > vehicle::profile().obd_steering_wheel_angle_timeout
+        {
+            return None;
+        }
+
+        self.obd_steering_wheel_angle
+            .map(|steering_wheel_angle| steering_wheel_angle.angle)
+    }
+
+    /// Whether the control CAN bus is accepting transmitted frames, with
+    /// the controller neither bus-off nor error passive
+    pub fn control_can_healthy(&self) -> bool {
+        let error_state = self.control_can_error_state();
+
+        (self.control_can_tx_timeouts < CONTROL_CAN_TX_TIMEOUT_LIMIT)
+            && !error_state.bus_off
+            && !error_state.error_passive
+    }
+
+    /// Receives a frame from one of the control CAN FIFOs
+    pub fn receive_control_can_frame(&mut self, fifo: &RxFifo) -> Result<CanFrame, CanError> {
+        let result = self.control_can.receive(fifo);
+
+        if result.is_ok() {
+            self.statistics.control_rx_frames = self.statistics.control_rx_frames.saturating_add(1);
+        }
+
+        result
+    }
+
+    pub fn statistics(&self) -> CanStatistics {
+        self.statistics
+    }
+
+    pub fn control_can_error_state(&self) -> CanErrorState {
+        CanErrorState::from(unsafe { (*CAN1::ptr()).esr.read().bits() })
+    }
+
+    pub fn obd_can_error_state(&self) -> CanErrorState {
+        CanErrorState::from(unsafe { (*CAN2::ptr()).esr.read().bits() })
+    }
+
+    fn count_control_can_tx(&mut self, tx_result: &Result<(), CanError>) {
+        let statistics = &mut self.statistics;
+
+        match tx_result {
+            Ok(()) => statistics.control_tx_frames = statistics.control_tx_frames.saturating_add(1),
+            Err(CanError::Timeout) => {
+                statistics.control_tx_timeouts = statistics.control_tx_timeouts.saturating_add(1)
             }
+            Err(_) => statistics.control_tx_errors = statistics.control_tx_errors.saturating_add(1),
         }
+    }
 
-        if is_a_match {
-            self.control_can().transmit(&frame)?;
+    fn update_control_can_health(&mut self, tx_result: &Result<(), CanError>) {
+        self.count_control_can_tx(tx_result);
+
+        match tx_result {
+            Err(CanError::Timeout) => {
+                self.control_can_tx_timeouts = self.control_can_tx_timeouts.saturating_add(1)
+            }
+            _ => self.control_can_tx_timeouts = 0,
         }
+    }
 
-        Ok(())
+    /// Control CAN self test
+    ///
+    /// Switches CAN1 into silent loopback mode, so nothing is put on the bus,
+    /// transmits a frame and expects to receive it back unmodified.
+    /// Normal mode is restored afterwards, any other frames pending in the
+    /// FIFOs are dropped.
+    pub fn control_can_loopback_test(&mut self) -> bool {
+        if !set_control_can_loopback(true) {
+            set_control_can_loopback(false);
+            return false;
+        }
+
+        let mut loopback_frame = default_self_test_loopback_data_frame();
+        loopback_frame.set_data_length(OXCC_SELF_TEST_LOOPBACK_CAN_DLC as _);
+        {
+            let data = loopback_frame.data_as_mut();
+            data[0] = OSCC_MAGIC_BYTE_0;
+            data[1] = OSCC_MAGIC_BYTE_1;
+            data[2..].copy_from_slice(&CONTROL_CAN_LOOPBACK_PATTERN);
+        }
+
+        let mut received = false;
+
+        if self.control_can.transmit(&loopback_frame.into()).is_ok() {
+            let start = sys_tick::millis();
+
+            while !received && (sys_tick::millis_since(start) <= CONTROL_CAN_SELF_TEST_TIMEOUT) {
+                for fifo in &[RxFifo::Fifo0, RxFifo::Fifo1] {
+                    if let Ok(CanFrame::DataFrame(ref frame)) = self.control_can.receive(fifo) {
+                        let id: u32 = frame.id().into();
+                        let data = frame.data();
+
+                        if (id == OXCC_SELF_TEST_LOOPBACK_CAN_ID.into())
+                            && (data.len() == OXCC_SELF_TEST_LOOPBACK_CAN_DLC as usize)
+                            && (data[0] == OSCC_MAGIC_BYTE_0)
+                            && (data[1] == OSCC_MAGIC_BYTE_1)
+                            && (data[2..] == CONTROL_CAN_LOOPBACK_PATTERN)
+                        {
+                            received = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        set_control_can_loopback(false) && received
     }
 
     // TODO - hide these details, switch to a publisher approach
@@ -87,6 +328,37 @@ impl CanGatewayModule {
     }
 }
 
+/// Switches CAN1 in and out of silent loopback mode
+///
+/// The HAL only applies `CanConfig::loopback_mode` at construction time,
+/// so the bxCAN is put back into initialization mode to change it.
+fn set_control_can_loopback(enabled: bool) -> bool {
+    let can1 = unsafe { &*CAN1::ptr() };
+
+    can1.mcr.modify(|_, w| w.inrq().set_bit());
+    if !wait_for_control_can(|| can1.msr.read().inak().bit_is_set()) {
+        return false;
+    }
+
+    can1.btr
+        .modify(|_, w| w.lbkm().bit(enabled).silm().bit(enabled));
+
+    can1.mcr.modify(|_, w| w.inrq().clear_bit());
+    wait_for_control_can(|| can1.msr.read().inak().bit_is_clear())
+}
+
+fn wait_for_control_can<F: Fn() -> bool>(condition: F) -> bool {
+    let start = sys_tick::millis();
+
+    while !condition() {
+        if sys_tick::millis_since(start) > CONTROL_CAN_SELF_TEST_TIMEOUT {
+            return false;
+        }
+    }
+
+    true
+}
+
 impl FaultReportPublisher for CanGatewayModule {
     fn publish_fault_report(&mut self, fault_report: &OsccFaultReport) -> Result<(), CanError> {
         {
@@ -104,8 +376,11 @@ impl FaultReportPublisher for CanGatewayModule {
             data[6] = fault_report.dtcs;
         }
 
-        self.control_can
-            .transmit(&self.fault_report_can_frame.into())
+        let result = self
+            .control_can
+            .transmit(&self.fault_report_can_frame.into());
+        self.update_control_can_health(&result);
+        result
     }
 }
 
@@ -122,10 +397,15 @@ impl BrakeReportPublisher for CanGatewayModule {
             data[2] = brake_report.enabled as _;
             data[3] = brake_report.operator_override as _;
             data[4] = brake_report.dtcs;
+            data[5] = brake_report.slew_rate_limited as _;
+            data[6] = brake_report.override_rearm_state;
         }
 
-        self.control_can
-            .transmit(&self.brake_report_can_frame.into())
+        let result = self
+            .control_can
+            .transmit(&self.brake_report_can_frame.into());
+        self.update_control_can_health(&result);
+        result
     }
 }
 
@@ -145,10 +425,15 @@ impl ThrottleReportPublisher for CanGatewayModule {
             data[2] = throttle_report.enabled as _;
             data[3] = throttle_report.operator_override as _;
             data[4] = throttle_report.dtcs;
+            data[5] = throttle_report.slew_rate_limited as _;
+            data[6] = throttle_report.override_rearm_state;
         }
 
-        self.control_can
-            .transmit(&self.throttle_report_can_frame.into())
+        let result = self
+            .control_can
+            .transmit(&self.throttle_report_can_frame.into());
+        self.update_control_can_health(&result);
+        result
     }
 }
 
@@ -168,9 +453,153 @@ impl SteeringReportPublisher for CanGatewayModule {
             data[2] = steering_report.enabled as _;
             data[3] = steering_report.operator_override as _;
             data[4] = steering_report.dtcs;
+            data[5] = steering_report.slew_rate_limited as _;
+            data[6] = steering_report.override_rearm_state;
+            data[7] = steering_report.angle_control as _;
+        }
+
+        let result = self
+            .control_can
+            .transmit(&self.steering_report_can_frame.into());
+        self.update_control_can_health(&result);
+        result
+    }
+}
+
+impl SystemStateReportPublisher for CanGatewayModule {
+    fn publish_system_state_report(
+        &mut self,
+        system_state_report: &OxccSystemStateReport,
+    ) -> Result<(), CanError> {
+        {
+            self.system_state_report_can_frame
+                .set_data_length(OXCC_SYSTEM_STATE_REPORT_CAN_DLC as _);
+
+            let data = self.system_state_report_can_frame.data_as_mut();
+
+            data[0] = OSCC_MAGIC_BYTE_0;
+            data[1] = OSCC_MAGIC_BYTE_1;
+            data[2] = system_state_report.state;
+            data[3] = system_state_report.control_allowed as _;
+            data[4] = system_state_report.vehicle_profile;
+        }
+
+        let result = self
+            .control_can
+            .transmit(&self.system_state_report_can_frame.into());
+        self.update_control_can_health(&result);
+        result
+    }
+}
+
+impl FaultRecordReportPublisher for CanGatewayModule {
+    fn publish_fault_record_report(&mut self, fault_record: &FaultRecord) -> Result<(), CanError> {
+        {
+            self.fault_record_report_can_frame
+                .set_data_length(OXCC_FAULT_RECORD_REPORT_CAN_DLC as _);
+
+            let data = self.fault_record_report_can_frame.data_as_mut();
+
+            data[0] = OSCC_MAGIC_BYTE_0;
+            data[1] = OSCC_MAGIC_BYTE_1;
+            data[2] = fault_record.brake_dtcs;
+            data[3] = fault_record.throttle_dtcs;
+            data[4] = fault_record.steering_dtcs;
+            data[5] = fault_record.fault_reason;
+            data[6] = fault_record.reset_cause;
+            data[7] = fault_record.lockout;
+        }
+
+        let result = self
+            .control_can
+            .transmit(&self.fault_record_report_can_frame.into());
+        self.update_control_can_health(&result);
+        result
+    }
+}
+
+impl SelfTestReportPublisher for CanGatewayModule {
+    fn publish_self_test_report(
+        &mut self,
+        self_test_result: &SelfTestResult,
+    ) -> Result<(), CanError> {
+        {
+            self.self_test_report_can_frame
+                .set_data_length(OXCC_SELF_TEST_REPORT_CAN_DLC as _);
+
+            let data = self.self_test_report_can_frame.data_as_mut();
+
+            data[0] = OSCC_MAGIC_BYTE_0;
+            data[1] = OSCC_MAGIC_BYTE_1;
+            data[2] = self_test_result.completed as _;
+            data[3] = self_test_result.passed() as _;
+            data[4] = (self_test_result.failures & 0xFF) as _;
+            data[5] = ((self_test_result.failures >> 8) & 0xFF) as _;
+        }
+
+        let result = self
+            .control_can
+            .transmit(&self.self_test_report_can_frame.into());
+        self.update_control_can_health(&result);
+        result
+    }
+}
+
+impl IdentityReportPublisher for CanGatewayModule {
+    fn publish_identity_report(
+        &mut self,
+        identity_report: &OxccIdentityReport,
+    ) -> Result<(), CanError> {
+        {
+            self.identity_report_can_frame
+                .set_data_length(OXCC_IDENTITY_REPORT_CAN_DLC as _);
+
+            let data = self.identity_report_can_frame.data_as_mut();
+
+            data[0] = OSCC_MAGIC_BYTE_0;
+            data[1] = OSCC_MAGIC_BYTE_1;
+            data[2] = (identity_report.image_crc & 0xFF) as _;
+            data[3] = ((identity_report.image_crc >> 8) & 0xFF) as _;
+            data[4] = ((identity_report.image_crc >> 16) & 0xFF) as _;
+            data[5] = ((identity_report.image_crc >> 24) & 0xFF) as _;
+            data[6] = identity_report.version_major;
+            data[7] = identity_report.version_minor;
+        }
+
+        let result = self
+            .control_can
+            .transmit(&self.identity_report_can_frame.into());
+        self.update_control_can_health(&result);
+        result
+    }
+}
+
+impl ParamResponsePublisher for CanGatewayModule {
+    fn publish_param_response(
+        &mut self,
+        param_response: &OxccParamResponse,
+    ) -> Result<(), CanError> {
+        {
+            self.param_response_can_frame
+                .set_data_length(OXCC_PARAM_RESPONSE_CAN_DLC as _);
+
+            let data = self.param_response_can_frame.data_as_mut();
+            let value = param_response.value.to_bits();
+
+            data[0] = OSCC_MAGIC_BYTE_0;
+            data[1] = OSCC_MAGIC_BYTE_1;
+            data[2] = (param_response.operation & 0x0F) | (param_response.status << 4);
+            data[3] = param_response.param_id;
+            data[4] = (value & 0xFF) as _;
+            data[5] = ((value >> 8) & 0xFF) as _;
+            data[6] = ((value >> 16) & 0xFF) as _;
+            data[7] = ((value >> 24) & 0xFF) as _;
         }
 
-        self.control_can
-            .transmit(&self.steering_report_can_frame.into())
+        let result = self
+            .control_can
+            .transmit(&self.param_response_can_frame.into());
+        self.update_control_can_health(&result);
+        result
     }
 }
@@ -1,17 +1,23 @@
 //! Brake CAN protocol
 
 use nucleo_f767zi::hal::can::{BaseID, CanError, DataFrame, ID};
+use oscc_magic_byte::oscc_data_frame;
 
 pub const OSCC_BRAKE_ENABLE_CAN_ID: u16 = 0x70;
 pub const OSCC_BRAKE_DISABLE_CAN_ID: u16 = 0x71;
 pub const OSCC_BRAKE_COMMAND_CAN_ID: u16 = 0x72;
 pub const OSCC_BRAKE_REPORT_CAN_ID: u16 = 0x73;
 
+pub const OSCC_BRAKE_COMMAND_CAN_DLC: u8 = 8;
 pub const OSCC_BRAKE_REPORT_CAN_DLC: u8 = 8;
 
 // TODO - enum
 pub const OSCC_BRAKE_DTC_INVALID_SENSOR_VAL: u8 = 0;
 pub const OSCC_BRAKE_DTC_OPERATOR_OVERRIDE: u8 = 1;
+pub const OSCC_BRAKE_DTC_SENSOR_MISMATCH: u8 = 2;
+pub const OSCC_BRAKE_DTC_SPOOF_MISMATCH: u8 = 3;
+pub const OSCC_BRAKE_DTC_ACCUMULATOR_PUMP_TIMEOUT: u8 = 4;
+pub const OSCC_BRAKE_DTC_PRESSURE_SENSOR_CHECK: u8 = 5;
 
 pub struct OsccBrakeCommand {
     pub pedal_command: f32,
@@ -33,10 +39,49 @@ impl<'a> From<&'a DataFrame> for OsccBrakeCommand {
     }
 }
 
+/// Encodes a command published by the host `oscc` crate
+impl<'a> From<&'a OsccBrakeCommand> for DataFrame {
+    fn from(command: &OsccBrakeCommand) -> Self {
+        let mut frame = oscc_data_frame(OSCC_BRAKE_COMMAND_CAN_ID, OSCC_BRAKE_COMMAND_CAN_DLC);
+
+        {
+            let raw_brake_request = command.pedal_command.to_bits();
+            let data = frame.data_as_mut();
+
+            data[2] = (raw_brake_request & 0xFF) as _;
+            data[3] = ((raw_brake_request >> 8) & 0xFF) as _;
+            data[4] = ((raw_brake_request >> 16) & 0xFF) as _;
+            data[5] = ((raw_brake_request >> 24) & 0xFF) as _;
+        }
+
+        frame
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct OsccBrakeReport {
     pub enabled: bool,
     pub operator_override: bool,
     pub dtcs: u8,
+    pub slew_rate_limited: bool,
+    /// See `override_rearm::RearmState`
+    pub override_rearm_state: u8,
+}
+
+/// Decodes a report published by OxCC, used by the host `oscc` crate
+impl<'a> From<&'a DataFrame> for OsccBrakeReport {
+    fn from(f: &DataFrame) -> Self {
+        assert_eq!(u32::from(f.id()), u32::from(OSCC_BRAKE_REPORT_CAN_ID));
+        let data = f.data();
+
+        OsccBrakeReport {
+            enabled: data[2] != 0,
+            operator_override: data[3] != 0,
+            dtcs: data[4],
+            slew_rate_limited: data[5] != 0,
+            override_rearm_state: data[6],
+        }
+    }
 }
 
 pub trait BrakeReportSupplier {
@@ -57,6 +102,8 @@ impl OsccBrakeReport {
             enabled: false,
             operator_override: false,
             dtcs: 0,
+            slew_rate_limited: false,
+            override_rearm_state: 0,
         }
     }
 }
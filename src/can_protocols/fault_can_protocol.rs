@@ -12,6 +12,7 @@ pub const FAULT_ORIGIN_STEERING: u32 = 1;
 pub const FAULT_ORIGIN_THROTTLE: u32 = 2;
 
 // TODO - fix this organization
+#[derive(Clone, Debug)]
 pub struct OsccFaultReport {
     pub fault_origin_id: u32,
     pub dtcs: u8,
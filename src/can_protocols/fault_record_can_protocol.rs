@@ -0,0 +1,16 @@
+//! OxCC persistent fault record CAN protocol
+
+use fault_record::FaultRecord;
+use nucleo_f767zi::hal::can::{BaseID, CanError, DataFrame, ID};
+
+pub const OXCC_FAULT_RECORD_REPORT_CAN_ID: u16 = 0xA2;
+
+pub const OXCC_FAULT_RECORD_REPORT_CAN_DLC: u8 = 8;
+
+pub trait FaultRecordReportPublisher {
+    fn publish_fault_record_report(&mut self, fault_record: &FaultRecord) -> Result<(), CanError>;
+}
+
+pub fn default_fault_record_report_data_frame() -> DataFrame {
+    DataFrame::new(ID::BaseID(BaseID::new(OXCC_FAULT_RECORD_REPORT_CAN_ID)))
+}
@@ -0,0 +1,25 @@
+//! OxCC firmware identity CAN protocol
+
+use nucleo_f767zi::hal::can::{BaseID, CanError, DataFrame, ID};
+
+pub const OXCC_IDENTITY_REPORT_CAN_ID: u16 = 0xA5;
+
+pub const OXCC_IDENTITY_REPORT_CAN_DLC: u8 = 8;
+
+pub struct OxccIdentityReport {
+    /// CRC of the firmware image, 0 if unpatched
+    pub image_crc: u32,
+    pub version_major: u8,
+    pub version_minor: u8,
+}
+
+pub trait IdentityReportPublisher {
+    fn publish_identity_report(
+        &mut self,
+        identity_report: &OxccIdentityReport,
+    ) -> Result<(), CanError>;
+}
+
+pub fn default_identity_report_data_frame() -> DataFrame {
+    DataFrame::new(ID::BaseID(BaseID::new(OXCC_IDENTITY_REPORT_CAN_ID)))
+}
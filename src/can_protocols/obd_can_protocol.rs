@@ -0,0 +1,74 @@
+//! Vehicle OBD CAN protocol
+
+use nucleo_f767zi::hal::can::DataFrame;
+use vehicle;
+
+/// Raw wheel speeds only use the lower 14 bits
+const WHEEL_SPEED_MASK: u16 = 0x3FFF;
+
+/// Data bytes decoded from the OBD steering wheel angle frame,
+/// shorter frames are dropped
+pub const OBD_STEERING_WHEEL_ANGLE_CAN_DLC: u8 = 2;
+
+/// Steering wheel angle decoded from the OBD steering wheel angle
+/// frame \[degrees\]
+#[derive(Copy, Clone, Debug)]
+pub struct ObdSteeringWheelAngle {
+    pub angle: f32,
+}
+
+impl<'a> From<&'a DataFrame> for ObdSteeringWheelAngle {
+    fn from(f: &DataFrame) -> Self {
+        let profile = vehicle::profile();
+        assert_eq!(
+            u32::from(f.id()),
+            u32::from(profile.obd_steering_wheel_angle_can_id)
+        );
+        let data = f.data();
+
+        let raw_angle = (u16::from(data[0]) | (u16::from(data[1]) << 8)) as i16;
+
+        ObdSteeringWheelAngle {
+            angle: f32::from(raw_angle) * profile.obd_steering_wheel_angle_scalar,
+        }
+    }
+}
+
+/// Data bytes of the OBD wheel speed frame, shorter frames are dropped
+pub const OBD_WHEEL_SPEED_CAN_DLC: u8 = 8;
+
+/// Wheel speeds decoded from the OBD wheel speed frame \[km/h\]
+#[derive(Copy, Clone, Debug)]
+pub struct ObdWheelSpeed {
+    pub front_left: f32,
+    pub front_right: f32,
+    pub rear_left: f32,
+    pub rear_right: f32,
+}
+
+impl ObdWheelSpeed {
+    /// Vehicle speed, the average of the four wheel speeds \[km/h\]
+    pub fn vehicle_speed(&self) -> f32 {
+        (self.front_left + self.front_right + self.rear_left + self.rear_right) / 4.0
+    }
+}
+
+impl<'a> From<&'a DataFrame> for ObdWheelSpeed {
+    fn from(f: &DataFrame) -> Self {
+        let profile = vehicle::profile();
+        assert_eq!(u32::from(f.id()), u32::from(profile.obd_wheel_speed_can_id));
+        let data = f.data();
+
+        let wheel_speed = |lsb: u8, msb: u8| -> f32 {
+            let raw = (u16::from(lsb) | (u16::from(msb) << 8)) & WHEEL_SPEED_MASK;
+            f32::from(raw) * profile.obd_wheel_speed_scalar
+        };
+
+        ObdWheelSpeed {
+            front_left: wheel_speed(data[0], data[1]),
+            front_right: wheel_speed(data[2], data[3]),
+            rear_left: wheel_speed(data[4], data[5]),
+            rear_right: wheel_speed(data[6], data[7]),
+        }
+    }
+}
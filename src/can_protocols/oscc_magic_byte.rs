@@ -1,4 +1,25 @@
 //! OSCC magic byte constants
 
+use nucleo_f767zi::hal::can::{BaseID, DataFrame, ID};
+
 pub const OSCC_MAGIC_BYTE_0: u8 = 0x05;
 pub const OSCC_MAGIC_BYTE_1: u8 = 0xCC;
+
+/// A frame of `dlc` data bytes starting with the magic bytes, the rest
+/// zeroed, e.g. an enable or disable request
+pub fn oscc_data_frame(id: u16, dlc: u8) -> DataFrame {
+    let mut frame = DataFrame::new(ID::BaseID(BaseID::new(id)));
+    frame.set_data_length(dlc as _);
+
+    {
+        let data = frame.data_as_mut();
+
+        for byte in data.iter_mut() {
+            *byte = 0;
+        }
+        data[0] = OSCC_MAGIC_BYTE_0;
+        data[1] = OSCC_MAGIC_BYTE_1;
+    }
+
+    frame
+}
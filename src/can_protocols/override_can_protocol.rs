@@ -0,0 +1,34 @@
+//! OxCC operator override acknowledge CAN protocol
+
+use nucleo_f767zi::hal::can::DataFrame;
+
+pub const OXCC_OVERRIDE_ACKNOWLEDGE_CAN_ID: u16 = 0xA6;
+
+pub const OXCC_OVERRIDE_ACKNOWLEDGE_CAN_DLC: u8 = 8;
+
+/// Acknowledges operator overrides, re-arming the modules using the
+/// `RearmPolicy::Acknowledge` policy
+pub struct OxccOverrideAcknowledge {
+    /// Bitfield of the acknowledged modules, bit index is the fault origin ID
+    pub fault_origins: u8,
+}
+
+impl<'a> From<&'a DataFrame> for OxccOverrideAcknowledge {
+    fn from(f: &DataFrame) -> Self {
+        assert_eq!(
+            u32::from(f.id()),
+            u32::from(OXCC_OVERRIDE_ACKNOWLEDGE_CAN_ID)
+        );
+        let data = f.data();
+
+        OxccOverrideAcknowledge {
+            fault_origins: data[2],
+        }
+    }
+}
+
+impl OxccOverrideAcknowledge {
+    pub fn acknowledges(&self, fault_origin_id: u32) -> bool {
+        (fault_origin_id < 8) && (self.fault_origins & (1 << fault_origin_id) != 0)
+    }
+}
@@ -0,0 +1,81 @@
+//! OxCC runtime parameter CAN protocol
+
+use nucleo_f767zi::hal::can::{BaseID, CanError, DataFrame, ID};
+
+pub const OXCC_PARAM_REQUEST_CAN_ID: u16 = 0xA9;
+
+pub const OXCC_PARAM_REQUEST_CAN_DLC: u8 = 8;
+
+pub const OXCC_PARAM_RESPONSE_CAN_ID: u16 = 0xAA;
+
+pub const OXCC_PARAM_RESPONSE_CAN_DLC: u8 = 8;
+
+pub const OXCC_PARAM_OPERATION_READ: u8 = 0;
+/// Sets the current value, it is lost on reset unless saved
+pub const OXCC_PARAM_OPERATION_WRITE: u8 = 1;
+/// Saves all current values to flash, the parameter ID is ignored
+pub const OXCC_PARAM_OPERATION_SAVE: u8 = 2;
+/// Sets all values back to the defaults, the parameter ID is ignored
+pub const OXCC_PARAM_OPERATION_RESTORE_DEFAULTS: u8 = 3;
+/// Saves the vehicle profile with the ID given as the parameter ID,
+/// along with its default values, the profile is active after a reset
+pub const OXCC_PARAM_OPERATION_SELECT_VEHICLE_PROFILE: u8 = 4;
+
+pub const OXCC_PARAM_STATUS_OK: u8 = 0;
+pub const OXCC_PARAM_STATUS_UNKNOWN_PARAM: u8 = 1;
+pub const OXCC_PARAM_STATUS_UNKNOWN_OPERATION: u8 = 2;
+pub const OXCC_PARAM_STATUS_OUT_OF_LIMITS: u8 = 3;
+pub const OXCC_PARAM_STATUS_CONTROL_ENABLED: u8 = 4;
+pub const OXCC_PARAM_STATUS_STORE_FULL: u8 = 5;
+pub const OXCC_PARAM_STATUS_FLASH_ERROR: u8 = 6;
+pub const OXCC_PARAM_STATUS_UNKNOWN_VEHICLE_PROFILE: u8 = 7;
+
+/// Parameter read/write request, see `params::Param` for the IDs
+pub struct OxccParamRequest {
+    pub operation: u8,
+    pub param_id: u8,
+    /// Value to write, ignored by the other operations
+    pub value: f32,
+}
+
+impl<'a> From<&'a DataFrame> for OxccParamRequest {
+    fn from(f: &DataFrame) -> Self {
+        assert_eq!(u32::from(f.id()), u32::from(OXCC_PARAM_REQUEST_CAN_ID));
+        let data = f.data();
+
+        let raw_value: u32 = u32::from(data[4])
+            | (u32::from(data[5]) << 8)
+            | (u32::from(data[6]) << 16)
+            | (u32::from(data[7]) << 24);
+
+        OxccParamRequest {
+            operation: data[2],
+            param_id: data[3],
+            value: f32::from_bits(raw_value),
+        }
+    }
+}
+
+/// Response to every `OxccParamRequest`
+///
+/// The operation and status share data[2], the operation in the
+/// low nibble and the status in the high nibble.
+pub struct OxccParamResponse {
+    pub operation: u8,
+    pub param_id: u8,
+    pub status: u8,
+    /// Current value after the operation, 0 for an unknown parameter
+    /// and a vehicle profile selection
+    pub value: f32,
+}
+
+pub trait ParamResponsePublisher {
+    fn publish_param_response(
+        &mut self,
+        param_response: &OxccParamResponse,
+    ) -> Result<(), CanError>;
+}
+
+pub fn default_param_response_data_frame() -> DataFrame {
+    DataFrame::new(ID::BaseID(BaseID::new(OXCC_PARAM_RESPONSE_CAN_ID)))
+}
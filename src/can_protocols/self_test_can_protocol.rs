@@ -0,0 +1,26 @@
+//! OxCC power-on self test CAN protocol
+
+use nucleo_f767zi::hal::can::{BaseID, CanError, DataFrame, ID};
+use self_test::SelfTestResult;
+
+pub const OXCC_SELF_TEST_REPORT_CAN_ID: u16 = 0xA3;
+/// Only ever transmitted in silent loopback mode
+pub const OXCC_SELF_TEST_LOOPBACK_CAN_ID: u16 = 0xA4;
+
+pub const OXCC_SELF_TEST_REPORT_CAN_DLC: u8 = 8;
+pub const OXCC_SELF_TEST_LOOPBACK_CAN_DLC: u8 = 8;
+
+pub trait SelfTestReportPublisher {
+    fn publish_self_test_report(
+        &mut self,
+        self_test_result: &SelfTestResult,
+    ) -> Result<(), CanError>;
+}
+
+pub fn default_self_test_report_data_frame() -> DataFrame {
+    DataFrame::new(ID::BaseID(BaseID::new(OXCC_SELF_TEST_REPORT_CAN_ID)))
+}
+
+pub fn default_self_test_loopback_data_frame() -> DataFrame {
+    DataFrame::new(ID::BaseID(BaseID::new(OXCC_SELF_TEST_LOOPBACK_CAN_ID)))
+}
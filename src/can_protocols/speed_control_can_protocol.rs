@@ -0,0 +1,39 @@
+//! OxCC speed control CAN protocol
+
+use nucleo_f767zi::hal::can::DataFrame;
+
+pub const OXCC_SPEED_CONTROL_COMMAND_CAN_ID: u16 = 0xA8;
+
+pub const OXCC_SPEED_CONTROL_COMMAND_CAN_DLC: u8 = 8;
+
+/// Speed control command, OxCC arbitrates between the throttle and
+/// brake to reach the target vehicle speed
+///
+/// Both the throttle and brake modules must be enabled.
+pub struct OxccSpeedControlCommand {
+    /// Whether speed control is requested, zero releases it
+    pub enable: bool,
+    /// Target vehicle speed \[km/h\], 0.01 km/h per bit
+    pub target_speed: f32,
+    /// Acceleration limit \[m/s^2\], 0.01 m/s^2 per bit
+    pub acceleration_limit: f32,
+}
+
+impl<'a> From<&'a DataFrame> for OxccSpeedControlCommand {
+    fn from(f: &DataFrame) -> Self {
+        assert_eq!(
+            u32::from(f.id()),
+            u32::from(OXCC_SPEED_CONTROL_COMMAND_CAN_ID)
+        );
+        let data = f.data();
+
+        let raw_target_speed: u16 = u16::from(data[3]) | (u16::from(data[4]) << 8);
+        let raw_acceleration_limit: u16 = u16::from(data[5]) | (u16::from(data[6]) << 8);
+
+        OxccSpeedControlCommand {
+            enable: data[2] != 0,
+            target_speed: f32::from(raw_target_speed) * 0.01,
+            acceleration_limit: f32::from(raw_acceleration_limit) * 0.01,
+        }
+    }
+}
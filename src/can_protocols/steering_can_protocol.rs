@@ -1,17 +1,22 @@
 //! Steering CAN protocol
 
 use nucleo_f767zi::hal::can::{BaseID, CanError, DataFrame, ID};
+use oscc_magic_byte::oscc_data_frame;
 
 pub const OSCC_STEERING_ENABLE_CAN_ID: u16 = 0x80;
 pub const OSCC_STEERING_DISABLE_CAN_ID: u16 = 0x81;
 pub const OSCC_STEERING_COMMAND_CAN_ID: u16 = 0x82;
 pub const OSCC_STEERING_REPORT_CAN_ID: u16 = 0x83;
+pub const OXCC_STEERING_ANGLE_COMMAND_CAN_ID: u16 = 0xA7;
 
+pub const OSCC_STEERING_COMMAND_CAN_DLC: u8 = 8;
 pub const OSCC_STEERING_REPORT_CAN_DLC: u8 = 8;
 
 // TODO - enum
 pub const OSCC_STEERING_DTC_INVALID_SENSOR_VAL: u8 = 0;
 pub const OSCC_STEERING_DTC_OPERATOR_OVERRIDE: u8 = 1;
+pub const OSCC_STEERING_DTC_SENSOR_MISMATCH: u8 = 2;
+pub const OSCC_STEERING_DTC_SPOOF_MISMATCH: u8 = 3;
 
 pub struct OsccSteeringCommand {
     pub torque_request: f32,
@@ -33,10 +38,79 @@ impl<'a> From<&'a DataFrame> for OsccSteeringCommand {
     }
 }
 
+/// Encodes a command published by the host `oscc` crate
+impl<'a> From<&'a OsccSteeringCommand> for DataFrame {
+    fn from(command: &OsccSteeringCommand) -> Self {
+        let mut frame =
+            oscc_data_frame(OSCC_STEERING_COMMAND_CAN_ID, OSCC_STEERING_COMMAND_CAN_DLC);
+
+        {
+            let raw_torque_request = command.torque_request.to_bits();
+            let data = frame.data_as_mut();
+
+            data[2] = (raw_torque_request & 0xFF) as _;
+            data[3] = ((raw_torque_request >> 8) & 0xFF) as _;
+            data[4] = ((raw_torque_request >> 16) & 0xFF) as _;
+            data[5] = ((raw_torque_request >> 24) & 0xFF) as _;
+        }
+
+        frame
+    }
+}
+
+/// Steering wheel angle command, OxCC closes the loop on the
+/// OBD steering wheel angle
+pub struct OxccSteeringAngleCommand {
+    /// Same sign convention as the OBD steering wheel angle \[degrees\]
+    pub angle_request: f32,
+}
+
+impl<'a> From<&'a DataFrame> for OxccSteeringAngleCommand {
+    fn from(f: &DataFrame) -> Self {
+        assert_eq!(
+            u32::from(f.id()),
+            u32::from(OXCC_STEERING_ANGLE_COMMAND_CAN_ID)
+        );
+        let data = f.data();
+
+        let raw_angle_request: u32 = u32::from(data[2])
+            | (u32::from(data[3]) << 8)
+            | (u32::from(data[4]) << 16)
+            | (u32::from(data[5]) << 24);
+
+        OxccSteeringAngleCommand {
+            angle_request: f32::from_bits(raw_angle_request),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct OsccSteeringReport {
     pub enabled: bool,
     pub operator_override: bool,
     pub dtcs: u8,
+    pub slew_rate_limited: bool,
+    /// See `override_rearm::RearmState`
+    pub override_rearm_state: u8,
+    /// Whether the steering wheel angle loop is in control
+    pub angle_control: bool,
+}
+
+/// Decodes a report published by OxCC, used by the host `oscc` crate
+impl<'a> From<&'a DataFrame> for OsccSteeringReport {
+    fn from(f: &DataFrame) -> Self {
+        assert_eq!(u32::from(f.id()), u32::from(OSCC_STEERING_REPORT_CAN_ID));
+        let data = f.data();
+
+        OsccSteeringReport {
+            enabled: data[2] != 0,
+            operator_override: data[3] != 0,
+            dtcs: data[4],
+            slew_rate_limited: data[5] != 0,
+            override_rearm_state: data[6],
+            angle_control: data[7] != 0,
+        }
+    }
 }
 
 pub trait SteeringReportSupplier {
@@ -60,6 +134,9 @@ impl OsccSteeringReport {
             enabled: false,
             operator_override: false,
             dtcs: 0,
+            slew_rate_limited: false,
+            override_rearm_state: 0,
+            angle_control: false,
         }
     }
 }
@@ -0,0 +1,36 @@
+//! OxCC system state CAN protocol
+
+use nucleo_f767zi::hal::can::{BaseID, CanError, DataFrame, ID};
+
+pub const OXCC_SYSTEM_STATE_REPORT_CAN_ID: u16 = 0xA0;
+pub const OXCC_SYSTEM_CLEAR_FAULTS_CAN_ID: u16 = 0xA1;
+
+pub const OXCC_SYSTEM_STATE_REPORT_CAN_DLC: u8 = 8;
+
+pub struct OxccSystemStateReport {
+    pub state: u8,
+    pub control_allowed: bool,
+    /// ID of the active `VehicleProfile`
+    pub vehicle_profile: u8,
+}
+
+pub trait SystemStateReportPublisher {
+    fn publish_system_state_report(
+        &mut self,
+        system_state_report: &OxccSystemStateReport,
+    ) -> Result<(), CanError>;
+}
+
+pub fn default_system_state_report_data_frame() -> DataFrame {
+    DataFrame::new(ID::BaseID(BaseID::new(OXCC_SYSTEM_STATE_REPORT_CAN_ID)))
+}
+
+impl OxccSystemStateReport {
+    pub fn new() -> Self {
+        OxccSystemStateReport {
+            state: 0,
+            control_allowed: false,
+            vehicle_profile: 0,
+        }
+    }
+}
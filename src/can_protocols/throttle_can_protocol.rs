@@ -1,17 +1,23 @@
 //! Throttle CAN protocol
 
 use nucleo_f767zi::hal::can::{BaseID, CanError, DataFrame, ID};
+use oscc_magic_byte::oscc_data_frame;
 
 pub const OSCC_THROTTLE_ENABLE_CAN_ID: u16 = 0x90;
 pub const OSCC_THROTTLE_DISABLE_CAN_ID: u16 = 0x91;
 pub const OSCC_THROTTLE_COMMAND_CAN_ID: u16 = 0x92;
 pub const OSCC_THROTTLE_REPORT_CAN_ID: u16 = 0x93;
 
+pub const OSCC_THROTTLE_COMMAND_CAN_DLC: u8 = 8;
 pub const OSCC_THROTTLE_REPORT_CAN_DLC: u8 = 8;
 
 // TODO - enum
 pub const OSCC_THROTTLE_DTC_INVALID_SENSOR_VAL: u8 = 0;
 pub const OSCC_THROTTLE_DTC_OPERATOR_OVERRIDE: u8 = 1;
+pub const OSCC_THROTTLE_DTC_SENSOR_MISMATCH: u8 = 2;
+pub const OSCC_THROTTLE_DTC_SPOOF_MISMATCH: u8 = 3;
+/// Reported while throttle control is enabled and held by the brake interlock
+pub const OSCC_THROTTLE_DTC_BRAKE_INTERLOCK: u8 = 4;
 
 pub struct OsccThrottleCommand {
     pub torque_request: f32,
@@ -33,10 +39,50 @@ impl<'a> From<&'a DataFrame> for OsccThrottleCommand {
     }
 }
 
+/// Encodes a command published by the host `oscc` crate
+impl<'a> From<&'a OsccThrottleCommand> for DataFrame {
+    fn from(command: &OsccThrottleCommand) -> Self {
+        let mut frame =
+            oscc_data_frame(OSCC_THROTTLE_COMMAND_CAN_ID, OSCC_THROTTLE_COMMAND_CAN_DLC);
+
+        {
+            let raw_torque_request = command.torque_request.to_bits();
+            let data = frame.data_as_mut();
+
+            data[2] = (raw_torque_request & 0xFF) as _;
+            data[3] = ((raw_torque_request >> 8) & 0xFF) as _;
+            data[4] = ((raw_torque_request >> 16) & 0xFF) as _;
+            data[5] = ((raw_torque_request >> 24) & 0xFF) as _;
+        }
+
+        frame
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct OsccThrottleReport {
     pub enabled: bool,
     pub operator_override: bool,
     pub dtcs: u8,
+    pub slew_rate_limited: bool,
+    /// See `override_rearm::RearmState`
+    pub override_rearm_state: u8,
+}
+
+/// Decodes a report published by OxCC, used by the host `oscc` crate
+impl<'a> From<&'a DataFrame> for OsccThrottleReport {
+    fn from(f: &DataFrame) -> Self {
+        assert_eq!(u32::from(f.id()), u32::from(OSCC_THROTTLE_REPORT_CAN_ID));
+        let data = f.data();
+
+        OsccThrottleReport {
+            enabled: data[2] != 0,
+            operator_override: data[3] != 0,
+            dtcs: data[4],
+            slew_rate_limited: data[5] != 0,
+            override_rearm_state: data[6],
+        }
+    }
 }
 
 pub trait ThrottleReportSupplier {
@@ -60,6 +106,8 @@ impl OsccThrottleReport {
             enabled: false,
             operator_override: false,
             dtcs: 0,
+            slew_rate_limited: false,
+            override_rearm_state: 0,
         }
     }
 }
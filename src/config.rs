@@ -5,9 +5,14 @@ use fault_can_protocol::*;
 use nucleo_f767zi::hal::can::{
     CanBitTiming, CanConfig, CanFilterConfig, FilterMode, FilterScale, RxFifo,
 };
+use override_can_protocol::*;
+use param_can_protocol::*;
+use self_test_can_protocol::*;
+use speed_control_can_protocol::*;
 use steering_can_protocol::*;
+use system_state_can_protocol::*;
 use throttle_can_protocol::*;
-use vehicle::*;
+use vehicle;
 
 /// Control CAN interface configuration
 pub const CONTROL_CAN_CONFIG: CanConfig = CanConfig {
@@ -54,7 +59,7 @@ pub const OBD_CAN_CONFIG: CanConfig = CanConfig {
 /// we can use ID list mode instead of masking.
 /// Only the specific message IDs are allowed through the filter.
 /// Filter 0 is the highest priority filter, followed by filter 1, etc.
-pub fn gather_control_can_filters() -> [CanFilterConfig; 3] {
+pub fn gather_control_can_filters() -> [CanFilterConfig; 5] {
     // Filter 0, bound to FIFO_0
     //  - disable control IDs for throttle, brake, steering
     //  - fault report ID
@@ -71,6 +76,7 @@ pub fn gather_control_can_filters() -> [CanFilterConfig; 3] {
 
     // Filter 1, bound to FIFO_1
     // - control command IDs for brake, throttle, and steering
+    // - system clear faults ID
     let mut f1 = CanFilterConfig::default();
     f1.filter_number = 1;
     f1.enabled = true;
@@ -80,10 +86,11 @@ pub fn gather_control_can_filters() -> [CanFilterConfig; 3] {
     f1.filter_mask_id_low = u32::from(OSCC_BRAKE_COMMAND_CAN_ID << 5);
     f1.filter_id_low = u32::from(OSCC_THROTTLE_COMMAND_CAN_ID << 5);
     f1.filter_mask_id_high = u32::from(OSCC_STEERING_COMMAND_CAN_ID << 5);
-    f1.filter_id_high = 0;
+    f1.filter_id_high = u32::from(OXCC_SYSTEM_CLEAR_FAULTS_CAN_ID << 5);
 
     // filter 2, bound to FIFO_1
     // - enable control IDs for brake, throttle, and steering
+    // - self test loopback ID
     let mut f2 = CanFilterConfig::default();
     f2.filter_number = 2;
     f2.enabled = true;
@@ -93,9 +100,37 @@ pub fn gather_control_can_filters() -> [CanFilterConfig; 3] {
     f2.filter_mask_id_low = u32::from(OSCC_BRAKE_ENABLE_CAN_ID << 5);
     f2.filter_id_low = u32::from(OSCC_THROTTLE_ENABLE_CAN_ID << 5);
     f2.filter_mask_id_high = u32::from(OSCC_STEERING_ENABLE_CAN_ID << 5);
-    f2.filter_id_high = 0;
+    f2.filter_id_high = u32::from(OXCC_SELF_TEST_LOOPBACK_CAN_ID << 5);
 
-    [f0, f1, f2]
+    // filter 3, bound to FIFO_0
+    // - operator override acknowledge ID
+    // - steering angle command ID
+    // - speed control command ID
+    let mut f3 = CanFilterConfig::default();
+    f3.filter_number = 3;
+    f3.enabled = true;
+    f3.mode = FilterMode::IdList;
+    f3.fifo_assignment = RxFifo::Fifo0;
+    f3.scale = FilterScale::Fs16Bit;
+    f3.filter_mask_id_low = u32::from(OXCC_OVERRIDE_ACKNOWLEDGE_CAN_ID << 5);
+    f3.filter_id_low = u32::from(OXCC_SPEED_CONTROL_COMMAND_CAN_ID << 5);
+    f3.filter_mask_id_high = u32::from(OXCC_STEERING_ANGLE_COMMAND_CAN_ID << 5);
+    f3.filter_id_high = u32::from(OXCC_STEERING_ANGLE_COMMAND_CAN_ID << 5);
+
+    // filter 4, bound to FIFO_0
+    // - parameter request ID
+    let mut f4 = CanFilterConfig::default();
+    f4.filter_number = 4;
+    f4.enabled = true;
+    f4.mode = FilterMode::IdList;
+    f4.fifo_assignment = RxFifo::Fifo0;
+    f4.scale = FilterScale::Fs16Bit;
+    f4.filter_mask_id_low = u32::from(OXCC_PARAM_REQUEST_CAN_ID << 5);
+    f4.filter_id_low = u32::from(OXCC_PARAM_REQUEST_CAN_ID << 5);
+    f4.filter_mask_id_high = u32::from(OXCC_PARAM_REQUEST_CAN_ID << 5);
+    f4.filter_id_high = u32::from(OXCC_PARAM_REQUEST_CAN_ID << 5);
+
+    [f0, f1, f2, f3, f4]
 }
 
 /// Gather the vehicle OBD CAN filter configurations
@@ -111,6 +146,8 @@ pub fn gather_control_can_filters() -> [CanFilterConfig; 3] {
 /// Only the specific message IDs are allowed through the filter.
 /// Filter 0 is the highest priority filter, followed by filter 1, etc.
 pub fn gather_obd_can_filters() -> [CanFilterConfig; 1] {
+    let profile = vehicle::profile();
+
     // filter 14, bound to FIFO_0
     // - the 4 OBD IDs
     let mut f3 = CanFilterConfig::default();
@@ -120,12 +157,11 @@ pub fn gather_obd_can_filters() -> [CanFilterConfig; 1] {
     f3.mode = FilterMode::IdList;
     f3.fifo_assignment = RxFifo::Fifo0;
     f3.scale = FilterScale::Fs16Bit;
-    f3.filter_mask_id_low = u32::from(KIA_SOUL_OBD_STEERING_WHEEL_ANGLE_CAN_ID << 5);
-    f3.filter_id_low = u32::from(KIA_SOUL_OBD_WHEEL_SPEED_CAN_ID << 5);
-    f3.filter_mask_id_high = u32::from(KIA_SOUL_OBD_BRAKE_PRESSURE_CAN_ID << 5);
-    #[cfg(feature = "kia-soul-ev")]
-    {
-        f3.filter_id_high = u32::from(KIA_SOUL_OBD_THROTTLE_PRESSURE_CAN_ID << 5);
+    f3.filter_mask_id_low = u32::from(profile.obd_steering_wheel_angle_can_id << 5);
+    f3.filter_id_low = u32::from(profile.obd_wheel_speed_can_id << 5);
+    f3.filter_mask_id_high = u32::from(profile.obd_brake_pressure_can_id << 5);
+    if let Some(throttle_pressure_can_id) = profile.obd_throttle_pressure_can_id {
+        f3.filter_id_high = u32::from(throttle_pressure_can_id << 5);
     }
 
     [f3]
@@ -0,0 +1,186 @@
+//! Interrupt driven debug console
+//!
+//! `DebugConsole` waits on the UART for every byte, at 115200 baud a
+//! single line stalls the control loop for several milliseconds. The
+//! console output is instead queued in a RAM ring buffer which the USART3
+//! interrupt drains whenever the transmit data register is empty, the
+//! main loop only copies bytes into the buffer.
+//!
+//! The `log` messages, the `debug_shell` output and the `telemetry`
+//! frames share the buffer, output that does not fit is dropped.
+//! Only the main loop queues output.
+//!
+//! The same interrupt moves every received byte into a second ring
+//! buffer, so input typed or pasted while the main loop is busy is kept
+//! until `read` is called. Input that does not fit is dropped.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::{cmp, ptr};
+use cortex_m;
+use cortex_m::interrupt::Nr;
+use cortex_m::peripheral::NVIC;
+use nucleo_f767zi::debug_console::DebugConsole;
+use nucleo_f767zi::hal::stm32f7x7::{self, Interrupt};
+
+/// Must be a power of two
+const BUFFER_CAPACITY: usize = 2048;
+
+/// Must be a power of two
+const RX_BUFFER_CAPACITY: usize = 256;
+
+/// USART_CR1 receive data register not empty and overrun interrupt enable
+const USART_CR1_RXNEIE: u32 = 1 << 5;
+
+/// USART_CR1 transmit data register empty interrupt enable
+const USART_CR1_TXEIE: u32 = 1 << 7;
+
+/// USART_ICR overrun error clear flag
+const USART_ICR_ORECF: u32 = 1 << 3;
+
+static mut BUFFER: [u8; BUFFER_CAPACITY] = [0; BUFFER_CAPACITY];
+
+/// Free running write position, only changed by the main loop
+static HEAD: AtomicUsize = AtomicUsize::new(0);
+
+/// Free running read position, only changed by the interrupt
+static TAIL: AtomicUsize = AtomicUsize::new(0);
+
+static mut RX_BUFFER: [u8; RX_BUFFER_CAPACITY] = [0; RX_BUFFER_CAPACITY];
+
+/// Free running write position of the received bytes, only changed by
+/// the interrupt
+static RX_HEAD: AtomicUsize = AtomicUsize::new(0);
+
+/// Free running read position of the received bytes, only changed by
+/// the main loop
+static RX_TAIL: AtomicUsize = AtomicUsize::new(0);
+
+/// Takes over the configured debug console, enables the receive
+/// interrupt and unmasks the USART3 interrupt
+///
+/// Output queued before is transmitted from here on.
+pub fn start(_debug_console: DebugConsole) {
+    let usart = unsafe { &*stm32f7x7::USART3::ptr() };
+    usart
+        .cr1
+        .modify(|r, w| unsafe { w.bits(r.bits() | USART_CR1_RXNEIE) });
+
+    let nr = Interrupt::USART3.nr();
+    unsafe {
+        (*NVIC::ptr()).iser[usize::from(nr / 32)].write(1 << (nr % 32));
+    }
+}
+
+/// Queues as many of the bytes as fit, returns the number queued
+pub fn write(bytes: &[u8]) -> usize {
+    let count = cmp::min(bytes.len(), free_space());
+    push(&bytes[..count]);
+    count
+}
+
+/// Queues the bytes only if all of them fit, so a log line or a
+/// telemetry frame is never cut short
+pub fn write_all(bytes: &[u8]) -> bool {
+    if bytes.len() > free_space() {
+        return false;
+    }
+
+    push(bytes);
+    true
+}
+
+/// Takes the oldest received byte, if any
+pub fn read() -> Option<u8> {
+    let tail = RX_TAIL.load(Ordering::Relaxed);
+    if tail == RX_HEAD.load(Ordering::Acquire) {
+        return None;
+    }
+
+    let byte = unsafe { RX_BUFFER[tail & (RX_BUFFER_CAPACITY - 1)] };
+    RX_TAIL.store(tail.wrapping_add(1), Ordering::Release);
+
+    Some(byte)
+}
+
+/// Called from the USART3 interrupt handler
+pub fn on_interrupt() {
+    receive();
+
+    let usart = unsafe { &*stm32f7x7::USART3::ptr() };
+    let head = HEAD.load(Ordering::Acquire);
+    let mut tail = TAIL.load(Ordering::Relaxed);
+
+    while tail != head && usart.isr.read().txe().bit_is_set() {
+        unsafe {
+            ptr::write_volatile(
+                &usart.tdr as *const _ as *mut u8,
+                BUFFER[tail & (BUFFER_CAPACITY - 1)],
+            );
+        }
+        tail = tail.wrapping_add(1);
+    }
+
+    TAIL.store(tail, Ordering::Release);
+
+    // nothing left to send, the next write enables the interrupt again
+    if tail == head {
+        usart
+            .cr1
+            .modify(|r, w| unsafe { w.bits(r.bits() & !USART_CR1_TXEIE) });
+    }
+}
+
+/// Moves the received byte into the receive buffer
+fn receive() {
+    let usart = unsafe { &*stm32f7x7::USART3::ptr() };
+    let isr = usart.isr.read();
+
+    // an overrun stops the receiver and keeps the interrupt pending
+    // until it is cleared, the lost bytes are of no use to a line anyway
+    if isr.ore().bit_is_set() {
+        usart.icr.write(|w| unsafe { w.bits(USART_ICR_ORECF) });
+    }
+
+    if isr.rxne().bit_is_set() {
+        // reading the data register clears the flag, also when dropped
+        let byte = usart.rdr.read().bits() as u8;
+        let head = RX_HEAD.load(Ordering::Relaxed);
+
+        if head.wrapping_sub(RX_TAIL.load(Ordering::Acquire)) < RX_BUFFER_CAPACITY {
+            unsafe {
+                RX_BUFFER[head & (RX_BUFFER_CAPACITY - 1)] = byte;
+            }
+            RX_HEAD.store(head.wrapping_add(1), Ordering::Release);
+        }
+    }
+}
+
+fn free_space() -> usize {
+    let used = HEAD
+        .load(Ordering::Relaxed)
+        .wrapping_sub(TAIL.load(Ordering::Acquire));
+
+    BUFFER_CAPACITY - used
+}
+
+fn push(bytes: &[u8]) {
+    if bytes.is_empty() {
+        return;
+    }
+
+    let head = HEAD.load(Ordering::Relaxed);
+    for (offset, &byte) in bytes.iter().enumerate() {
+        unsafe {
+            BUFFER[head.wrapping_add(offset) & (BUFFER_CAPACITY - 1)] = byte;
+        }
+    }
+    HEAD.store(head.wrapping_add(bytes.len()), Ordering::Release);
+
+    // the interrupt clears the enable bit with a read-modify-write of its own
+    let usart = unsafe { &*stm32f7x7::USART3::ptr() };
+    cortex_m::interrupt::free(|_| {
+        usart
+            .cr1
+            .modify(|r, w| unsafe { w.bits(r.bits() | USART_CR1_TXEIE) })
+    });
+}
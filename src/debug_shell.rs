@@ -0,0 +1,342 @@
+//! Line based shell on the debug console
+//!
+//! The USART3 interrupt of the `console` buffers the received bytes,
+//! the shell reads them on each main loop iteration and collects a line
+//! which is parsed into a `ShellCommand` for the main loop to execute.
+//!
+//! Shell output is queued on the interrupt driven `console` along with
+//! the `log` messages and the `telemetry` frames, so neither direction
+//! waits on the UART and the control loop timing is kept. Output that
+//! does not fit in the console buffer is dropped.
+
+use can_gateway_module::{CanErrorState, CanStatistics};
+use console;
+use core::fmt::{self, Write};
+use core::str;
+use dual_signal::SensorReading;
+use log::Level;
+use nucleo_f767zi::hal::rcc::ResetConditions;
+
+const LINE_CAPACITY: usize = 64;
+
+/// Maximum number of words in a command line
+const MAX_WORDS: usize = 4;
+
+const PROMPT: &str = "> ";
+
+const ASCII_BACKSPACE: u8 = 0x08;
+const ASCII_DELETE: u8 = 0x7F;
+
+const HELP: &str = "\
+help                  show this help
+status                system state, module states and DTCs
+sensors               live dual signal sensor readings
+enable <module>       enable brake, throttle or steering, bench profile only
+disable [module]      disable a module, or all of them
+clear                 clear the faults
+resets                reset conditions of this boot
+param                 list the parameters
+param get <id>        read a parameter
+param set <id> <v>    write a parameter
+param save            save the parameters to flash
+param defaults        restore the vehicle profile defaults
+can                   CAN statistics
+log [level]           show the log level, or set error, warn, info or debug
+";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ControlModule {
+    Brake,
+    Throttle,
+    Steering,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ShellCommand {
+    Status,
+    Sensors,
+    Enable(ControlModule),
+    /// Disables a single module, or all of them
+    Disable(Option<ControlModule>),
+    ClearFaults,
+    ResetConditions,
+    ParamList,
+    ParamGet(u8),
+    ParamSet(u8, f32),
+    ParamSave,
+    ParamDefaults,
+    CanStatistics,
+    /// Shows the log level, or sets it
+    Log(Option<Level>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum ParseError {
+    Help,
+    UnknownCommand,
+    UnknownModule,
+    UnknownLevel,
+    InvalidNumber,
+}
+
+pub struct DebugShell {
+    line: [u8; LINE_CAPACITY],
+    line_len: usize,
+    line_overflow: bool,
+    prompt_pending: bool,
+}
+
+impl DebugShell {
+    pub fn new() -> Self {
+        DebugShell {
+            line: [0; LINE_CAPACITY],
+            line_len: 0,
+            line_overflow: false,
+            prompt_pending: true,
+        }
+    }
+
+    /// Handles the received bytes
+    ///
+    /// Returns a command once a complete line was parsed, the bytes
+    /// after it stay buffered for the next poll. The prompt is shown on
+    /// the next poll after the command output was queued.
+    pub fn poll(&mut self) -> Option<ShellCommand> {
+        if self.prompt_pending {
+            self.prompt_pending = false;
+            self.queue(PROMPT.as_bytes());
+        }
+
+        while let Some(byte) = console::read() {
+            if let Some(command) = self.process_byte(byte) {
+                return Some(command);
+            }
+        }
+
+        None
+    }
+
+    pub fn print_module_state(
+        &mut self,
+        name: &str,
+        enabled: bool,
+        operator_override: bool,
+        dtcs: u8,
+    ) {
+        let _ = writeln!(
+            self,
+            "{:<9} enabled {} override {} DTCs {:#010b}",
+            name, enabled, operator_override, dtcs
+        );
+    }
+
+    pub fn print_sensor_reading(&mut self, name: &str, reading: &SensorReading) {
+        let _ = writeln!(
+            self,
+            "{:<9} high {:>4} low {:>4} {}",
+            name,
+            reading.high,
+            reading.low,
+            if reading.plausible {
+                "plausible"
+            } else {
+                "IMPLAUSIBLE"
+            }
+        );
+    }
+
+    pub fn print_reset_conditions(&mut self, reset_conditions: &ResetConditions) {
+        let conditions = [
+            ("low-power", reset_conditions.low_power),
+            ("window watchdog", reset_conditions.window_watchdog),
+            (
+                "independent watchdog",
+                reset_conditions.independent_watchdog,
+            ),
+            ("software", reset_conditions.software),
+            ("POR/PDR", reset_conditions.por_pdr),
+            ("PIN", reset_conditions.pin),
+            ("BOR", reset_conditions.bor),
+        ];
+
+        for &(name, is_set) in &conditions {
+            let _ = writeln!(self, "{:<21} {}", name, is_set);
+        }
+    }
+
+    pub fn print_can_statistics(
+        &mut self,
+        statistics: &CanStatistics,
+        control_can: &CanErrorState,
+        obd_can: &CanErrorState,
+    ) {
+        let _ = writeln!(
+            self,
+            "control rx {} tx {} tx timeouts {} tx errors {}",
+            statistics.control_rx_frames,
+            statistics.control_tx_frames,
+            statistics.control_tx_timeouts,
+            statistics.control_tx_errors
+        );
+        let _ = writeln!(
+            self,
+            "obd rx {} republished {}",
+            statistics.obd_rx_frames, statistics.obd_republished_frames
+        );
+        self.print_can_error_state("control", control_can);
+        self.print_can_error_state("obd", obd_can);
+    }
+
+    fn print_can_error_state(&mut self, name: &str, error_state: &CanErrorState) {
+        let _ = writeln!(
+            self,
+            "{} TEC {} REC {} LEC {}{}{}",
+            name,
+            error_state.transmit_errors,
+            error_state.receive_errors,
+            error_state.last_error_code,
+            if error_state.error_passive {
+                " error-passive"
+            } else {
+                ""
+            },
+            if error_state.bus_off { " bus-off" } else { "" }
+        );
+    }
+
+    fn process_byte(&mut self, byte: u8) -> Option<ShellCommand> {
+        match byte {
+            b'\r' | b'\n' => {
+                // also swallows the second half of a CR LF
+                if self.line_len == 0 && !self.line_overflow {
+                    return None;
+                }
+
+                self.queue(b"\r\n");
+                self.prompt_pending = true;
+                self.submit_line()
+            }
+            ASCII_BACKSPACE | ASCII_DELETE => {
+                if self.line_len > 0 {
+                    self.line_len -= 1;
+                    self.queue(b"\x08 \x08");
+                }
+                None
+            }
+            0x20..=0x7E => {
+                if self.line_len < LINE_CAPACITY {
+                    self.line[self.line_len] = byte;
+                    self.line_len += 1;
+                    self.queue(&[byte]);
+                } else {
+                    self.line_overflow = true;
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn submit_line(&mut self) -> Option<ShellCommand> {
+        let line = self.line;
+        let line_len = self.line_len;
+        let line_overflow = self.line_overflow;
+
+        self.line_len = 0;
+        self.line_overflow = false;
+
+        if line_overflow {
+            let _ = writeln!(self, "line too long");
+            return None;
+        }
+
+        // only printable ASCII is stored
+        let result = str::from_utf8(&line[..line_len])
+            .map_err(|_| ParseError::UnknownCommand)
+            .and_then(parse);
+
+        match result {
+            Ok(command) => return Some(command),
+            Err(ParseError::Help) => self.queue(HELP.as_bytes()),
+            Err(ParseError::UnknownCommand) => {
+                let _ = writeln!(self, "unknown command, try help");
+            }
+            Err(ParseError::UnknownModule) => {
+                let _ = writeln!(self, "unknown module, one of brake, throttle, steering");
+            }
+            Err(ParseError::UnknownLevel) => {
+                let _ = writeln!(self, "unknown level, one of error, warn, info, debug");
+            }
+            Err(ParseError::InvalidNumber) => {
+                let _ = writeln!(self, "invalid number");
+            }
+        }
+
+        None
+    }
+
+    fn queue(&mut self, bytes: &[u8]) {
+        console::write(bytes);
+    }
+}
+
+/// Never fails, output that does not fit is dropped
+impl fmt::Write for DebugShell {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.queue(s.as_bytes());
+        Ok(())
+    }
+}
+
+fn parse(line: &str) -> Result<ShellCommand, ParseError> {
+    let mut words = [""; MAX_WORDS];
+    let mut word_count = 0;
+
+    for word in line.split_whitespace() {
+        if word_count == MAX_WORDS {
+            return Err(ParseError::UnknownCommand);
+        }
+        words[word_count] = word;
+        word_count += 1;
+    }
+
+    match &words[..word_count] {
+        ["help"] => Err(ParseError::Help),
+        ["status"] => Ok(ShellCommand::Status),
+        ["sensors"] => Ok(ShellCommand::Sensors),
+        ["enable", module] => parse_module(module).map(ShellCommand::Enable),
+        ["disable"] => Ok(ShellCommand::Disable(None)),
+        ["disable", module] => {
+            parse_module(module).map(|module| ShellCommand::Disable(Some(module)))
+        }
+        ["clear"] => Ok(ShellCommand::ClearFaults),
+        ["resets"] => Ok(ShellCommand::ResetConditions),
+        ["param"] => Ok(ShellCommand::ParamList),
+        ["param", "get", id] => parse_number(id).map(ShellCommand::ParamGet),
+        ["param", "set", id, value] => Ok(ShellCommand::ParamSet(
+            parse_number(id)?,
+            parse_number(value)?,
+        )),
+        ["param", "save"] => Ok(ShellCommand::ParamSave),
+        ["param", "defaults"] => Ok(ShellCommand::ParamDefaults),
+        ["can"] => Ok(ShellCommand::CanStatistics),
+        ["log"] => Ok(ShellCommand::Log(None)),
+        ["log", level] => Level::from_name(level)
+            .map(|level| ShellCommand::Log(Some(level)))
+            .ok_or(ParseError::UnknownLevel),
+        _ => Err(ParseError::UnknownCommand),
+    }
+}
+
+fn parse_module(word: &str) -> Result<ControlModule, ParseError> {
+    match word {
+        "brake" => Ok(ControlModule::Brake),
+        "throttle" => Ok(ControlModule::Throttle),
+        "steering" => Ok(ControlModule::Steering),
+        _ => Err(ParseError::UnknownModule),
+    }
+}
+
+fn parse_number<T: str::FromStr>(word: &str) -> Result<T, ParseError> {
+    word.parse().map_err(|_| ParseError::InvalidNumber)
+}
@@ -60,9 +60,96 @@ where
     pub fn low(&self) -> u16 {
         self.low
     }
+
+    pub fn reader(&self) -> &T {
+        &self.reader
+    }
+
+    /// The latest readings, checked against a plausibility model
+    pub fn reading(&self, plausibility: &SignalPlausibility) -> SensorReading {
+        SensorReading {
+            high: self.high,
+            low: self.low,
+            plausible: plausibility.is_plausible(self.high, self.low),
+        }
+    }
+}
+
+/// Readings of both channels of a dual signal sensor \[steps\]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SensorReading {
+    pub high: u16,
+    pub low: u16,
+    pub plausible: bool,
 }
 
 pub trait HighLowReader {
     fn read_high(&self) -> u16;
     fn read_low(&self) -> u16;
 }
+
+/// Expected relationship between the high and low channels
+/// of a dual signal sensor
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ChannelRelation {
+    /// `high` tracks `low` scaled by `ratio`, within `tolerance` \[steps\]
+    Ratio { ratio: f32, tolerance: u16 },
+    /// `high - low` stays within `[min, max]` \[steps\]
+    Offset { min: i16, max: i16 },
+    /// `high + low` stays within `[min, max]` \[steps\]
+    ///
+    /// Used by mirrored sensors, where one channel rises
+    /// as the other falls.
+    Sum { min: u16, max: u16 },
+}
+
+/// Plausibility model of a dual signal sensor
+///
+/// All values are in ADC steps.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SignalPlausibility {
+    pub relation: ChannelRelation,
+    pub high_min: u16,
+    pub high_max: u16,
+    pub low_min: u16,
+    pub low_max: u16,
+    /// Readings at or above this value on either channel indicate
+    /// the channel is stuck high (shorted to the sensor supply)
+    pub stuck_high_threshold: u16,
+}
+
+impl SignalPlausibility {
+    pub fn is_stuck_high(&self, high: u16, low: u16) -> bool {
+        (high >= self.stuck_high_threshold) || (low >= self.stuck_high_threshold)
+    }
+
+    pub fn is_out_of_range(&self, high: u16, low: u16) -> bool {
+        (high < self.high_min)
+            || (high > self.high_max)
+            || (low < self.low_min)
+            || (low > self.low_max)
+    }
+
+    pub fn is_mismatched(&self, high: u16, low: u16) -> bool {
+        match self.relation {
+            ChannelRelation::Ratio { ratio, tolerance } => {
+                let error = f32::from(high) - (ratio * f32::from(low));
+                (error > f32::from(tolerance)) || (error < -f32::from(tolerance))
+            }
+            ChannelRelation::Offset { min, max } => {
+                let offset = i32::from(high) - i32::from(low);
+                (offset < i32::from(min)) || (offset > i32::from(max))
+            }
+            ChannelRelation::Sum { min, max } => {
+                let sum = u32::from(high) + u32::from(low);
+                (sum < u32::from(min)) || (sum > u32::from(max))
+            }
+        }
+    }
+
+    pub fn is_plausible(&self, high: u16, low: u16) -> bool {
+        !self.is_stuck_high(high, low)
+            && !self.is_out_of_range(high, low)
+            && !self.is_mismatched(high, low)
+    }
+}
@@ -1,8 +1,10 @@
 //! Fault condition
 
-use dual_signal::{DualSignal, HighLowReader};
+use board::{dac_steps_to_adc_steps, SPOOF_READBACK_TOLERANCE};
+use dual_signal::{DualSignal, HighLowReader, SignalPlausibility};
 use embedded_hal::timer::CountDown;
 use nucleo_f767zi::hal::timer::OnePulse;
+use num;
 
 pub struct FaultCondition<TIMER> {
     monitoring_active: bool,
@@ -54,4 +56,32 @@ where
 
         self.condition_exceeded_duration(condition_active)
     }
+
+    pub fn check_signal_implausible<T: HighLowReader>(
+        &mut self,
+        signal: &DualSignal<T>,
+        plausibility: &SignalPlausibility,
+    ) -> bool {
+        let condition_active = !plausibility.is_plausible(signal.high(), signal.low());
+
+        self.condition_exceeded_duration(condition_active)
+    }
+
+    /// Checks the spoof outputs (in DAC steps) against their readback
+    /// on the signal channels
+    pub fn check_spoof_readback<T: HighLowReader>(
+        &mut self,
+        signal: &DualSignal<T>,
+        spoof_high: u16,
+        spoof_low: u16,
+    ) -> bool {
+        let high_error = i32::from(signal.high()) - i32::from(dac_steps_to_adc_steps(spoof_high));
+        let low_error = i32::from(signal.low()) - i32::from(dac_steps_to_adc_steps(spoof_low));
+
+        let tolerance = i32::from(SPOOF_READBACK_TOLERANCE);
+        let condition_active =
+            (num::abs(high_error) > tolerance) || (num::abs(low_error) > tolerance);
+
+        self.condition_exceeded_duration(condition_active)
+    }
 }
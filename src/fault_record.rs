@@ -0,0 +1,249 @@
+//! Persistent fault record
+//!
+//! The record lives in the 4 KB backup SRAM, which keeps its contents
+//! across system and watchdog resets (and power loss when VBAT is supplied).
+//! It holds the last module DTCs, the panic/HardFault reason and the
+//! last reset cause, along with a lockout flag which keeps control
+//! disabled after an unexpected reset until the faults are cleared.
+//!
+//! The record is accessed through volatile reads/writes so it can also
+//! be updated from the panic handler and HardFault exception.
+//!
+//! The record is checked with a CRC computed by the CRC peripheral, as the
+//! parameter records are. The panic handler and the HardFault exception
+//! never return, so a CRC computation they interrupt is of no further use.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::{mem, ptr};
+use fault_can_protocol::*;
+use nucleo_f767zi::hal::rcc::ResetConditions;
+use nucleo_f767zi::hal::stm32f7x7;
+use params;
+
+const BACKUP_SRAM_BASE: usize = 0x4002_4000;
+
+const FAULT_RECORD_MAGIC: u32 = 0xFA17_0CC0;
+
+// reset cause bits
+pub const RESET_CAUSE_LOW_POWER: u8 = 0;
+pub const RESET_CAUSE_WINDOW_WATCHDOG: u8 = 1;
+pub const RESET_CAUSE_INDEPENDENT_WATCHDOG: u8 = 2;
+pub const RESET_CAUSE_SOFTWARE: u8 = 3;
+pub const RESET_CAUSE_POR_PDR: u8 = 4;
+pub const RESET_CAUSE_PIN: u8 = 5;
+pub const RESET_CAUSE_BOR: u8 = 6;
+
+/// Set by the HardFault exception before it escalates to a panic
+static HARD_FAULT_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// Set by the first panic, the panic handler ends in an abort trap
+/// which escalates to a HardFault and a second panic
+static PANIC_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FaultReason {
+    None,
+    Panic,
+    HardFault,
+}
+
+impl From<FaultReason> for u8 {
+    fn from(r: FaultReason) -> u8 {
+        match r {
+            FaultReason::None => 0,
+            FaultReason::Panic => 1,
+            FaultReason::HardFault => 2,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct FaultRecord {
+    magic: u32,
+    pub brake_dtcs: u8,
+    pub throttle_dtcs: u8,
+    pub steering_dtcs: u8,
+    pub reset_cause: u8,
+    pub fault_reason: u8,
+    /// Non-zero while control is locked out
+    pub lockout: u8,
+    /// Always zero, keeps the padding out of the CRC
+    reserved: [u8; 2],
+    /// Source line of the last panic, 0 if unknown
+    pub panic_line: u32,
+    checksum: u32,
+}
+
+const FAULT_RECORD_WORDS: usize = mem::size_of::<FaultRecord>() / mem::size_of::<u32>();
+
+impl FaultRecord {
+    fn new() -> Self {
+        FaultRecord {
+            magic: FAULT_RECORD_MAGIC,
+            brake_dtcs: 0,
+            throttle_dtcs: 0,
+            steering_dtcs: 0,
+            reset_cause: 0,
+            fault_reason: FaultReason::None.into(),
+            lockout: 0,
+            reserved: [0; 2],
+            panic_line: 0,
+            checksum: 0,
+        }
+    }
+
+    fn words(&self) -> &[u32; FAULT_RECORD_WORDS] {
+        unsafe { &*(self as *const FaultRecord as *const [u32; FAULT_RECORD_WORDS]) }
+    }
+
+    /// CRC of every word but the checksum
+    fn compute_checksum(&self) -> u32 {
+        let words = self.words();
+        params::crc32(&words[..FAULT_RECORD_WORDS - 1])
+    }
+
+    fn is_valid(&self) -> bool {
+        (self.magic == FAULT_RECORD_MAGIC)
+            && (self.reserved == [0; 2])
+            && (self.checksum == self.compute_checksum())
+    }
+}
+
+/// Enables the backup SRAM clock and write access
+///
+/// Safe to call repeatedly, the backup domain write protection is
+/// re-enabled by every reset.
+fn enable_backup_sram() {
+    let rcc = unsafe { &*stm32f7x7::RCC::ptr() };
+    let pwr = unsafe { &*stm32f7x7::PWR::ptr() };
+
+    rcc.apb1enr.modify(|_, w| w.pwren().set_bit());
+    pwr.cr1.modify(|_, w| w.dbp().set_bit());
+    rcc.ahb1enr.modify(|_, w| w.bkpsramen().set_bit());
+}
+
+/// Reads the record, an invalid or uninitialized record reads as empty
+pub fn read() -> FaultRecord {
+    enable_backup_sram();
+
+    let record = unsafe { ptr::read_volatile(BACKUP_SRAM_BASE as *const FaultRecord) };
+
+    if record.is_valid() {
+        record
+    } else {
+        FaultRecord::new()
+    }
+}
+
+fn write(record: &FaultRecord) {
+    let mut record = *record;
+    record.magic = FAULT_RECORD_MAGIC;
+    record.checksum = record.compute_checksum();
+
+    enable_backup_sram();
+
+    unsafe { ptr::write_volatile(BACKUP_SRAM_BASE as *mut FaultRecord, record) };
+}
+
+/// Records the reset cause, returns whether control is locked out
+///
+/// Watchdog, software and low-power resets are unexpected and
+/// lock out control until `clear` is called.
+pub fn record_reset(reset_conditions: &ResetConditions) -> bool {
+    let mut record = read();
+
+    let causes = [
+        (reset_conditions.low_power, RESET_CAUSE_LOW_POWER),
+        (
+            reset_conditions.window_watchdog,
+            RESET_CAUSE_WINDOW_WATCHDOG,
+        ),
+        (
+            reset_conditions.independent_watchdog,
+            RESET_CAUSE_INDEPENDENT_WATCHDOG,
+        ),
+        (reset_conditions.software, RESET_CAUSE_SOFTWARE),
+        (reset_conditions.por_pdr, RESET_CAUSE_POR_PDR),
+        (reset_conditions.pin, RESET_CAUSE_PIN),
+        (reset_conditions.bor, RESET_CAUSE_BOR),
+    ];
+
+    record.reset_cause = 0;
+    for (asserted, cause) in causes.iter() {
+        if *asserted {
+            record.reset_cause |= 1 << cause;
+        }
+    }
+
+    if reset_conditions.low_power
+        || reset_conditions.window_watchdog
+        || reset_conditions.independent_watchdog
+        || reset_conditions.software
+    {
+        record.lockout = 1;
+    }
+
+    write(&record);
+
+    record.lockout != 0
+}
+
+/// Records the DTCs of a module fault report
+pub fn record_fault_report(fault_report: &OsccFaultReport) {
+    let mut record = read();
+
+    match fault_report.fault_origin_id {
+        FAULT_ORIGIN_BRAKE => record.brake_dtcs = fault_report.dtcs,
+        FAULT_ORIGIN_THROTTLE => record.throttle_dtcs = fault_report.dtcs,
+        FAULT_ORIGIN_STEERING => record.steering_dtcs = fault_report.dtcs,
+        _ => (),
+    }
+
+    write(&record);
+}
+
+/// Called from the HardFault exception before panicking
+///
+/// A HardFault raised by the abort trap of the panic handler leaves
+/// the panic already recorded untouched.
+pub fn record_hard_fault() {
+    if PANIC_IN_PROGRESS.load(Ordering::SeqCst) {
+        return;
+    }
+
+    HARD_FAULT_IN_PROGRESS.store(true, Ordering::SeqCst);
+
+    let mut record = read();
+    record.fault_reason = FaultReason::HardFault.into();
+    record.lockout = 1;
+    write(&record);
+}
+
+/// Called from the panic handler, only the first panic is recorded
+pub fn record_panic(line: u32) {
+    if PANIC_IN_PROGRESS.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let mut record = read();
+
+    if !HARD_FAULT_IN_PROGRESS.load(Ordering::SeqCst) {
+        record.fault_reason = FaultReason::Panic.into();
+    }
+    record.panic_line = line;
+    record.lockout = 1;
+
+    write(&record);
+}
+
+/// Clears the recorded faults and releases the lockout,
+/// the last reset cause is kept
+pub fn clear() {
+    let reset_cause = read().reset_cause;
+
+    let mut record = FaultRecord::new();
+    record.reset_cause = reset_cause;
+
+    write(&record);
+}
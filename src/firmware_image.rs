@@ -0,0 +1,108 @@
+//! Firmware image integrity
+//!
+//! The `.image_info` flash section holds the length and CRC32 of the
+//! firmware image, patched into the ELF after linking by
+//! `scripts/patch-image-crc`.
+//! The image starts at the beginning of flash, the image info block
+//! itself is excluded from the checksum.
+//!
+//! The CRC peripheral computes the CRC-32/MPEG-2 checksum
+//! (polynomial 0x04C11DB7, initial value 0xFFFFFFFF, no reflection,
+//! no final XOR), one little-endian 32-bit word at a time.
+
+use core::{mem, ptr};
+use nucleo_f767zi::hal::stm32f7x7::{CRC, RCC};
+
+/// Start of the flash memory, see `memory.x`
+const FLASH_BASE: usize = 0x0800_0000;
+
+const IMAGE_INFO_MAGIC: u32 = 0x1A6E_0CC0;
+
+#[repr(C)]
+pub struct ImageInfo {
+    magic: u32,
+    /// Length of the image in bytes, 0 until patched
+    length: u32,
+    crc: u32,
+}
+
+#[link_section = ".image_info"]
+#[no_mangle]
+#[used]
+pub static IMAGE_INFO: ImageInfo = ImageInfo {
+    magic: IMAGE_INFO_MAGIC,
+    length: 0,
+    crc: 0,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ImageCheck {
+    Valid,
+    /// The image info has not been patched after linking
+    Unpatched,
+    Mismatch {
+        expected: u32,
+        computed: u32,
+    },
+}
+
+/// Volatile read, the patched values are unknown to the compiler
+fn image_info() -> (u32, u32, u32) {
+    unsafe {
+        (
+            ptr::read_volatile(&IMAGE_INFO.magic),
+            ptr::read_volatile(&IMAGE_INFO.length),
+            ptr::read_volatile(&IMAGE_INFO.crc),
+        )
+    }
+}
+
+/// The CRC patched into the image, 0 if unpatched
+pub fn image_crc() -> u32 {
+    image_info().2
+}
+
+/// Firmware version from the crate manifest
+pub fn version() -> (u8, u8) {
+    (
+        env!("CARGO_PKG_VERSION_MAJOR").parse().unwrap_or(0),
+        env!("CARGO_PKG_VERSION_MINOR").parse().unwrap_or(0),
+    )
+}
+
+/// Computes the image CRC with the CRC peripheral and compares it
+/// to the patched value
+pub fn check_image() -> ImageCheck {
+    let (magic, length, expected) = image_info();
+
+    if (magic != IMAGE_INFO_MAGIC) || (length == 0) {
+        return ImageCheck::Unpatched;
+    }
+
+    let info_start = &IMAGE_INFO as *const ImageInfo as usize;
+    let info_end = info_start + mem::size_of::<ImageInfo>();
+    let image_end = FLASH_BASE + length as usize;
+
+    let rcc = unsafe { &*RCC::ptr() };
+    let crc = unsafe { &*CRC::ptr() };
+
+    rcc.ahb1enr.modify(|_, w| w.crcen().set_bit());
+    crc.cr.write(|w| w.reset().set_bit());
+
+    let mut address = FLASH_BASE;
+    while address < image_end {
+        if (address < info_start) || (address >= info_end) {
+            let word = unsafe { ptr::read_volatile(address as *const u32) };
+            crc.dr.write(|w| unsafe { w.bits(word) });
+        }
+        address += mem::size_of::<u32>();
+    }
+
+    let computed = crc.dr.read().bits();
+
+    if computed == expected {
+        ImageCheck::Valid
+    } else {
+        ImageCheck::Mismatch { expected, computed }
+    }
+}
@@ -0,0 +1,78 @@
+//! Brake/throttle interlock
+//!
+//! Sits above the control modules and keeps OxCC from applying
+//! throttle while the brake is being commanded or applied by
+//! the driver.
+
+use brake_module::BrakeModule;
+use oxcc_error::OxccError;
+use params::{self, Param};
+use throttle_module::ThrottleModule;
+use vehicle;
+
+/// Threshold of `BrakeModule::measured_pedal_position`, in the units of
+/// the brake pedal sensor
+#[cfg(not(feature = "kia-soul-petrol"))]
+const BRAKE_PEDAL_THRESHOLD: Param = Param::ThrottleInterlockBrakePedalThreshold;
+#[cfg(feature = "kia-soul-petrol")]
+const BRAKE_PEDAL_THRESHOLD: Param = Param::ThrottleInterlockBrakePressureThreshold;
+
+/// What happens to the throttle while the interlock is active
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InterlockAction {
+    /// Hold the throttle spoof output at the minimum command
+    ZeroThrottle,
+    /// Disable throttle control and refuse enable requests
+    DisableThrottle,
+}
+
+/// The rule that activated the interlock
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InterlockRule {
+    /// Brake command above `Param::ThrottleInterlockBrakeCommandThreshold`
+    BrakeCommand,
+    /// Measured brake pedal above `Param::ThrottleInterlockBrakePedalThreshold`,
+    /// or `Param::ThrottleInterlockBrakePressureThreshold` on the Kia Soul Petrol
+    BrakePedal,
+}
+
+pub struct BrakeThrottleInterlock {
+    active_rule: Option<InterlockRule>,
+}
+
+impl BrakeThrottleInterlock {
+    pub const fn new() -> Self {
+        BrakeThrottleInterlock { active_rule: None }
+    }
+
+    /// Applies the interlock from the brake command and the brake pedal
+    /// sensor readings of the previous `BrakeModule::check_for_faults`
+    pub fn check(
+        &mut self,
+        brake: &BrakeModule,
+        throttle: &mut ThrottleModule,
+    ) -> Result<(), OxccError> {
+        let brake_command_threshold = params::get(Param::ThrottleInterlockBrakeCommandThreshold);
+        let brake_pedal_threshold = params::get(BRAKE_PEDAL_THRESHOLD);
+
+        let rule = if brake.brake_command() > brake_command_threshold {
+            Some(InterlockRule::BrakeCommand)
+        } else if brake.measured_pedal_position() as f32 > brake_pedal_threshold {
+            Some(InterlockRule::BrakePedal)
+        } else {
+            None
+        };
+
+        if rule != self.active_rule {
+            if let Some(rule) = rule {
+                info!("Throttle interlocked by {:?}", rule);
+            } else {
+                info!("Throttle interlock released");
+            }
+
+            self.active_rule = rule;
+        }
+
+        throttle.apply_brake_interlock(rule.map(|_| vehicle::profile().throttle_interlock_action))
+    }
+}
@@ -0,0 +1,177 @@
+//! Leveled logging on the debug console
+//!
+//! The `error!`, `warn!`, `info!` and `debug!` macros format a message
+//! into a line with a timestamp, the level and the module it came from,
+//! e.g. `[  12.345] warn  throttle_module: Operator override`, which is
+//! queued on the interrupt driven `console`. Logging never waits on the
+//! UART, a line that does not fit in the console buffer is dropped and
+//! counted.
+//!
+//! Messages above `MAX_LEVEL` are compiled out, see the `log-level-*`
+//! features. The runtime level filters the remaining ones, it is
+//! `Level::Info` after reset and set with the debug shell `log` command.
+
+use console;
+use core::cmp;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use sys_tick;
+
+/// Logs a message tagged with the calling module if `$level` is enabled
+#[allow(unused_macros)]
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {
+        if ::log::enabled($level) {
+            ::log::record($level, module_path!(), format_args!($($arg)+));
+        }
+    };
+}
+
+#[allow(unused_macros)]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        log!(::log::Level::Error, $($arg)+)
+    };
+}
+
+#[allow(unused_macros)]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        log!(::log::Level::Warn, $($arg)+)
+    };
+}
+
+#[allow(unused_macros)]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        log!(::log::Level::Info, $($arg)+)
+    };
+}
+
+#[allow(unused_macros)]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        log!(::log::Level::Debug, $($arg)+)
+    };
+}
+
+/// Longest line, including the prefix and the newline, longer
+/// messages are truncated
+const LINE_CAPACITY: usize = 160;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "error" => Some(Level::Error),
+            "warn" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            _ => None,
+        }
+    }
+
+    fn from_usize(value: usize) -> Level {
+        match value {
+            0 => Level::Error,
+            1 => Level::Warn,
+            2 => Level::Info,
+            _ => Level::Debug,
+        }
+    }
+}
+
+/// Most verbose level built into the image
+#[cfg(feature = "log-level-error")]
+pub const MAX_LEVEL: Level = Level::Error;
+#[cfg(all(feature = "log-level-warn", not(feature = "log-level-error")))]
+pub const MAX_LEVEL: Level = Level::Warn;
+#[cfg(all(
+    feature = "log-level-info",
+    not(any(feature = "log-level-error", feature = "log-level-warn"))
+))]
+pub const MAX_LEVEL: Level = Level::Info;
+#[cfg(not(any(
+    feature = "log-level-error",
+    feature = "log-level-warn",
+    feature = "log-level-info"
+)))]
+pub const MAX_LEVEL: Level = Level::Debug;
+
+static LEVEL: AtomicUsize = AtomicUsize::new(Level::Info as usize);
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Runtime level, never above `MAX_LEVEL`
+pub fn level() -> Level {
+    cmp::min(Level::from_usize(LEVEL.load(Ordering::Relaxed)), MAX_LEVEL)
+}
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+/// Number of lines dropped because the console buffer was full
+pub fn dropped() -> usize {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+#[inline]
+pub fn enabled(level: Level) -> bool {
+    level <= MAX_LEVEL && (level as usize) <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Formats and queues a line, used by the logging macros
+pub fn record(level: Level, module_path: &str, args: fmt::Arguments) {
+    let tag = module_path.rsplit("::").next().unwrap_or(module_path);
+    let millis = sys_tick::millis();
+
+    let mut line = Line {
+        buffer: [0; LINE_CAPACITY],
+        len: 0,
+    };
+    let _ = write!(
+        line,
+        "[{:>4}.{:03}] {:<5} {}: ",
+        millis / 1000,
+        millis % 1000,
+        level.name(),
+        tag
+    );
+    let _ = line.write_fmt(args);
+    line.buffer[line.len] = b'\n';
+
+    if !console::write_all(&line.buffer[..=line.len]) {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Line buffer which truncates, one byte is kept for the newline
+struct Line {
+    buffer: [u8; LINE_CAPACITY],
+    len: usize,
+}
+
+impl fmt::Write for Line {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let count = cmp::min(s.len(), LINE_CAPACITY - 1 - self.len);
+        self.buffer[self.len..self.len + count].copy_from_slice(&s.as_bytes()[..count]);
+        self.len += count;
+        Ok(())
+    }
+}
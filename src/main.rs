@@ -19,36 +19,81 @@ extern crate panic_semihosting;
 #[macro_use]
 extern crate typenum;
 
+// declared first, the other modules use its macros
+#[macro_use]
+mod log;
+
 mod board;
+#[macro_use]
+mod calibration;
 mod can_gateway_module;
 mod config;
+mod console;
 mod dac_mcp4922;
+mod debug_shell;
 mod dtc;
 mod dual_signal;
 mod fault_condition;
+mod fault_record;
+mod firmware_image;
+mod interlock;
+mod override_rearm;
 mod oxcc_error;
 #[cfg(feature = "panic-abort")]
 mod panic_abort;
+mod params;
+mod pedal_selection;
+mod pid;
 mod ranges;
+mod self_test;
+mod slew_rate_limiter;
+#[cfg(feature = "kia-soul-petrol")]
+mod solenoid_pwm;
+mod speed_control;
 mod steering_module;
+mod supervisor;
+mod sys_tick;
+mod system_state;
+mod telemetry;
+mod telemetry_protocol;
 mod throttle_module;
 mod types;
+mod wwdg;
 
 #[path = "can_protocols/brake_can_protocol.rs"]
 mod brake_can_protocol;
 #[path = "can_protocols/fault_can_protocol.rs"]
 mod fault_can_protocol;
+#[path = "can_protocols/fault_record_can_protocol.rs"]
+mod fault_record_can_protocol;
+#[path = "can_protocols/identity_can_protocol.rs"]
+mod identity_can_protocol;
+#[path = "can_protocols/obd_can_protocol.rs"]
+mod obd_can_protocol;
 #[path = "can_protocols/oscc_magic_byte.rs"]
 mod oscc_magic_byte;
+#[path = "can_protocols/override_can_protocol.rs"]
+mod override_can_protocol;
+#[path = "can_protocols/param_can_protocol.rs"]
+mod param_can_protocol;
+#[path = "can_protocols/self_test_can_protocol.rs"]
+mod self_test_can_protocol;
+#[path = "can_protocols/speed_control_can_protocol.rs"]
+mod speed_control_can_protocol;
 #[path = "can_protocols/steering_can_protocol.rs"]
 mod steering_can_protocol;
+#[path = "can_protocols/system_state_can_protocol.rs"]
+mod system_state_can_protocol;
 #[path = "can_protocols/throttle_can_protocol.rs"]
 mod throttle_can_protocol;
 
-#[cfg(feature = "kia-niro")]
+#[cfg(not(feature = "kia-soul-petrol"))]
+#[path = "vehicles/bench.rs"]
+mod bench;
+#[cfg(not(feature = "kia-soul-petrol"))]
 #[path = "vehicles/kial_niro.rs"]
 mod kial_niro;
-#[cfg(feature = "kia-soul-ev")]
+#[cfg(not(feature = "kia-soul-petrol"))]
 #[path = "vehicles/kial_soul_ev.rs"]
 mod kial_soul_ev;
 #[cfg(feature = "kia-soul-petrol")]
@@ -56,101 +101,141 @@ mod kial_soul_ev;
 mod kial_soul_petrol;
 mod vehicle;
 
-#[cfg(any(feature = "kia-soul-ev", feature = "kia-niro"))]
+#[cfg(feature = "kia-soul-petrol")]
+#[path = "brake/kia_soul_petrol/accumulator.rs"]
+mod accumulator;
+#[cfg(not(feature = "kia-soul-petrol"))]
 #[path = "brake/kia_soul_ev_niro/brake_module.rs"]
 mod brake_module;
 #[cfg(feature = "kia-soul-petrol")]
 #[path = "brake/kia_soul_petrol/brake_module.rs"]
 mod brake_module;
+#[cfg(feature = "kia-soul-petrol")]
+#[path = "brake/kia_soul_petrol/pressure_controller.rs"]
+mod pressure_controller;
+#[cfg(feature = "kia-soul-petrol")]
+#[path = "brake/kia_soul_petrol/pressure_sensor_check.rs"]
+mod pressure_sensor_check;
 
-use board::FullBoard;
-use brake_can_protocol::BrakeReportPublisher;
+use board::{Board, FullBoard};
+use brake_can_protocol::*;
 use brake_module::{BrakeModule, UnpreparedBrakeModule};
 use can_gateway_module::CanGatewayModule;
 use core::fmt::Write;
-use fault_can_protocol::FaultReportPublisher;
-use nucleo_f767zi::debug_console::DebugConsole;
+use debug_shell::{ControlModule, DebugShell, ShellCommand};
+use dtc::DtcBitfield;
+use fault_can_protocol::*;
+use fault_record_can_protocol::FaultRecordReportPublisher;
+use identity_can_protocol::*;
+use interlock::BrakeThrottleInterlock;
 use nucleo_f767zi::hal::can::CanError;
-use nucleo_f767zi::hal::can::RxFifo;
+use nucleo_f767zi::hal::can::{CanFrame, RxFifo};
+use nucleo_f767zi::hal::stm32f7x7::interrupt;
 use nucleo_f767zi::led::{Color, Leds};
+use oscc_magic_byte::*;
 use oxcc_error::OxccError;
+use param_can_protocol::*;
+use params::Param;
 use rt::{entry, exception, ExceptionFrame};
-use steering_can_protocol::SteeringReportPublisher;
+use self_test_can_protocol::SelfTestReportPublisher;
+use speed_control::SpeedController;
+use steering_can_protocol::*;
 use steering_module::{SteeringModule, UnpreparedSteeringModule};
-use throttle_can_protocol::ThrottleReportPublisher;
+use supervisor::{Activity, Supervisor};
+use system_state::{SystemEvent, SystemState, SystemStateMachine};
+use system_state_can_protocol::*;
+use telemetry::Telemetry;
+use telemetry_protocol::*;
+use throttle_can_protocol::*;
 use throttle_module::{ThrottleModule, UnpreparedThrottleModule};
 
-const DEBUG_WRITE_FAILURE: &str = "Failed to write to debug_console";
-
 /// A local container for passing around the control modules
 struct ControlModules {
     pub brake: BrakeModule,
     pub throttle: ThrottleModule,
     pub steering: SteeringModule,
+    pub speed_control: SpeedController,
 }
 
 #[entry]
 fn main() -> ! {
+    // the board configuration uses some of the parameters and the
+    // vehicle profile, and a full parameter sector can only be compacted
+    // before the watchdogs start
+    params::load();
+
     // once the organization is cleaned up, the entire board doesn't need to be
     // mutable let Board {mut leds, mut delay, ..} = Board::new();
     let (
         mut board,
-        brake_dac,
+        brake_actuator,
         brake_pins,
-        brake_pedal_position_sensor,
+        brake_sensor,
         brake_grounded_fault_timer,
         brake_override_fault_timer,
+        brake_mismatch_fault_timer,
+        brake_readback_fault_timer,
         accelerator_position_sensor,
         throttle_dac,
         throttle_pins,
         throttle_grounded_fault_timer,
         throttle_override_fault_timer,
+        throttle_mismatch_fault_timer,
+        throttle_readback_fault_timer,
         torque_sensor,
         steering_dac,
         steering_pins,
         steering_grounded_fault_timer,
-        mut debug_console,
+        steering_mismatch_fault_timer,
+        steering_readback_fault_timer,
+        debug_console,
         can_publish_timer,
         control_can,
         obd_can,
     ) = FullBoard::new().split_components();
 
+    // the debug console output is sent and its input buffered by the USART3 interrupt
+    console::start(debug_console);
+
     // turn on the blue LED
     board.leds[Color::Blue].on();
 
-    // show startup message and reset warnings if debugging
+    // show startup message if debugging
     #[cfg(debug_assertions)]
     {
-        writeln!(debug_console, "OxCC is running").unwrap();
+        info!("OxCC is running");
+    }
 
-        // TODO - some of these are worthy of disabling controls?
-        if board.reset_conditions.low_power {
-            writeln!(debug_console, "WARNING: low-power reset detected")
-                .expect(DEBUG_WRITE_FAILURE);
-        }
-        if board.reset_conditions.window_watchdog || board.reset_conditions.independent_watchdog {
-            writeln!(debug_console, "WARNING: watchdog reset detected").expect(DEBUG_WRITE_FAILURE);
-        }
-        if board.reset_conditions.software {
-            writeln!(debug_console, "WARNING: software reset detected").expect(DEBUG_WRITE_FAILURE);
-        }
-        if board.reset_conditions.por_pdr {
-            writeln!(debug_console, "WARNING: POR/PDR reset detected").expect(DEBUG_WRITE_FAILURE);
-        }
-        if board.reset_conditions.pin {
-            writeln!(debug_console, "WARNING: PIN reset detected").expect(DEBUG_WRITE_FAILURE);
-        }
-        if board.reset_conditions.bor {
-            writeln!(debug_console, "WARNING: BOR reset detected").expect(DEBUG_WRITE_FAILURE);
-        }
+    info!("Vehicle profile: {}", vehicle::profile().name);
+
+    // show reset warnings
+    if board.reset_conditions.low_power {
+        warn!("low-power reset detected");
+    }
+    if board.reset_conditions.window_watchdog || board.reset_conditions.independent_watchdog {
+        warn!("watchdog reset detected");
+    }
+    if board.reset_conditions.software {
+        warn!("software reset detected");
+    }
+    if board.reset_conditions.por_pdr {
+        warn!("POR/PDR reset detected");
+    }
+    if board.reset_conditions.pin {
+        warn!("PIN reset detected");
+    }
+    if board.reset_conditions.bor {
+        warn!("BOR reset detected");
     }
 
     let unprepared_brake_module = UnpreparedBrakeModule::new(
-        brake_dac,
+        brake_actuator,
         brake_pins,
-        brake_pedal_position_sensor,
+        brake_sensor,
         brake_grounded_fault_timer,
         brake_override_fault_timer,
+        brake_mismatch_fault_timer,
+        brake_readback_fault_timer,
     );
     let unprepared_throttle_module = UnpreparedThrottleModule::new(
         accelerator_position_sensor,
@@ -158,45 +243,164 @@ fn main() -> ! {
         throttle_pins,
         throttle_grounded_fault_timer,
         throttle_override_fault_timer,
+        throttle_mismatch_fault_timer,
+        throttle_readback_fault_timer,
     );
     let unprepared_steering_module = UnpreparedSteeringModule::new(
         torque_sensor,
         steering_dac,
         steering_pins,
         steering_grounded_fault_timer,
+        steering_mismatch_fault_timer,
+        steering_readback_fault_timer,
     );
     let mut can_gateway = CanGatewayModule::new(can_publish_timer, control_can, obd_can);
+    let mut interlock = BrakeThrottleInterlock::new();
+    let mut system_state = SystemStateMachine::new();
+    let mut debug_shell = DebugShell::new();
+    let mut telemetry = Telemetry::new();
 
     let mut modules = ControlModules {
         brake: unprepared_brake_module.prepare_module(),
         throttle: unprepared_throttle_module.prepare_module(),
         steering: unprepared_steering_module.prepare_module(),
+        speed_control: SpeedController::new(),
     };
 
+    // watchdog, software and low-power resets are not expected during
+    // normal operation, the persistent fault record keeps control locked
+    // out until the faults are cleared
+    let locked_out = fault_record::record_reset(&board.reset_conditions);
+    info!("{:?}", fault_record::read());
+    update_system_state(
+        SystemEvent::BootComplete {
+            unexpected_reset: locked_out,
+        },
+        &mut system_state,
+        &mut modules,
+        &mut board.leds,
+    );
+
     // send reports immediately
-    if let Err(e) = publish_reports(&mut modules, &mut can_gateway) {
+    if let Err(e) = publish_reports(&mut modules, &mut can_gateway, &system_state) {
         handle_error(
             e,
             &mut modules,
             &mut can_gateway,
-            &mut debug_console,
+            &mut system_state,
             &mut board.leds,
         );
     }
 
+    // start supervising the main loop activities, the watchdogs are
+    // only serviced by the supervisor from here on
+    board.wdg.refresh();
+    board.wwdg.start();
+    let mut supervisor = Supervisor::new();
+
     loop {
-        // refresh the independent watchdog
-        board.wdg.refresh();
+        // pace the loop, keeping the WWDG refreshes inside its window
+        supervisor.wait_for_next_iteration();
+
+        // service the watchdogs while all activities are healthy
+        if let Some(activity) = supervisor.service_watchdogs(&mut board.wdg, &mut board.wwdg) {
+            board.leds[Color::Red].on();
+            error!("supervised activity {:?} missed its deadline", activity);
+
+            // stop controlling while waiting for the watchdog reset
+            update_system_state(
+                SystemEvent::Error,
+                &mut system_state,
+                &mut modules,
+                &mut board.leds,
+            );
+        }
+
+        // run the self test, control can only be enabled once it passed
+        if system_state.state() == SystemState::SelfTest {
+            let self_test_result = self_test::run(
+                &mut modules.brake,
+                &mut modules.throttle,
+                &mut modules.steering,
+                &mut can_gateway,
+            );
+
+            let self_test_event = if self_test_result.passed() {
+                SystemEvent::SelfTestPassed
+            } else {
+                error!("Self test failed: {:#018b}", self_test_result.failures);
+                SystemEvent::SelfTestFailed
+            };
+            update_system_state(
+                self_test_event,
+                &mut system_state,
+                &mut modules,
+                &mut board.leds,
+            );
+
+            if let Err(e) = can_gateway.publish_self_test_report(&self_test_result) {
+                if e != CanError::Timeout {
+                    handle_error(
+                        OxccError::from(e),
+                        &mut modules,
+                        &mut can_gateway,
+                        &mut system_state,
+                        &mut board.leds,
+                    );
+                }
+            }
+        }
 
         // check the control CAN FIFOs for any frames to be processed
-        if let Err(e) =
-            process_control_can_frames(&mut modules, &mut can_gateway, &mut debug_console)
-        {
+        if let Err(e) = process_control_can_frames(
+            &mut modules,
+            &mut can_gateway,
+            &mut system_state,
+            &mut board.leds,
+        ) {
+            handle_error(
+                e,
+                &mut modules,
+                &mut can_gateway,
+                &mut system_state,
+                &mut board.leds,
+            );
+        }
+        supervisor.check_in(Activity::ControlCanRx);
+
+        // replace the brake and throttle commands while speed control is active
+        if let Err(e) = modules.speed_control.update(
+            &mut modules.brake,
+            &mut modules.throttle,
+            can_gateway.vehicle_speed(),
+        ) {
+            handle_error(
+                e,
+                &mut modules,
+                &mut can_gateway,
+                &mut system_state,
+                &mut board.leds,
+            );
+        }
+
+        // apply the brake/throttle interlock on top of the latest commands
+        if let Err(e) = interlock.check(&modules.brake, &mut modules.throttle) {
             handle_error(
                 e,
                 &mut modules,
                 &mut can_gateway,
-                &mut debug_console,
+                &mut system_state,
+                &mut board.leds,
+            );
+        }
+
+        // step the slew rate limited commands towards their targets
+        if let Err(e) = update_commands(&mut modules) {
+            handle_error(
+                e,
+                &mut modules,
+                &mut can_gateway,
+                &mut system_state,
                 &mut board.leds,
             );
         }
@@ -205,17 +409,51 @@ fn main() -> ! {
         // NOTE
         // ignoring transmit timeouts until a proper error handling strategy is
         // implemented
-        if let Err(e) = check_for_faults(&mut modules, &mut can_gateway, &mut debug_console) {
+        if let Err(e) = check_for_faults(
+            &mut modules,
+            &mut can_gateway,
+            &mut system_state,
+            &mut board.leds,
+        ) {
             if e != OxccError::Can(CanError::Timeout) {
                 handle_error(
                     e,
                     &mut modules,
                     &mut can_gateway,
-                    &mut debug_console,
+                    &mut system_state,
                     &mut board.leds,
                 );
             }
         }
+        supervisor.check_in(Activity::FaultChecks);
+
+        // track the control and control CAN bus state
+        let control_event = if modules.brake.is_enabled()
+            || modules.throttle.is_enabled()
+            || modules.steering.is_enabled()
+        {
+            SystemEvent::ControlEnabled
+        } else {
+            SystemEvent::ControlDisabled
+        };
+        update_system_state(
+            control_event,
+            &mut system_state,
+            &mut modules,
+            &mut board.leds,
+        );
+
+        let control_can_event = if can_gateway.control_can_healthy() {
+            SystemEvent::ControlCanRecovered
+        } else {
+            SystemEvent::ControlCanDegraded
+        };
+        update_system_state(
+            control_can_event,
+            &mut system_state,
+            &mut modules,
+            &mut board.leds,
+        );
 
         // republish OBD frames to control CAN bus
         if let Err(e) = can_gateway.republish_obd_frames_to_control_can_bus() {
@@ -223,7 +461,27 @@ fn main() -> ! {
                 e,
                 &mut modules,
                 &mut can_gateway,
-                &mut debug_console,
+                &mut system_state,
+                &mut board.leds,
+            );
+        }
+        supervisor.check_in(Activity::ObdRepublish);
+
+        // share the latest OBD vehicle speed with the modules that need it
+        modules
+            .steering
+            .update_vehicle_speed(can_gateway.vehicle_speed());
+
+        // close the steering angle loop on the latest OBD steering wheel angle
+        if let Err(e) = modules
+            .steering
+            .update_steering_wheel_angle(can_gateway.steering_wheel_angle())
+        {
+            handle_error(
+                e,
+                &mut modules,
+                &mut can_gateway,
+                &mut system_state,
                 &mut board.leds,
             );
         }
@@ -232,12 +490,38 @@ fn main() -> ! {
         if can_gateway.wait_for_publish() {
             board.leds[Color::Green].toggle();
 
-            if let Err(e) = publish_reports(&mut modules, &mut can_gateway) {
+            if let Err(e) = publish_reports(&mut modules, &mut can_gateway, &system_state) {
+                handle_error(
+                    e,
+                    &mut modules,
+                    &mut can_gateway,
+                    &mut system_state,
+                    &mut board.leds,
+                );
+            }
+            supervisor.check_in(Activity::ReportPublish);
+        }
+
+        // queue the telemetry records on the debug console
+        if telemetry.is_enabled() {
+            publish_telemetry(&mut telemetry, &mut modules, &system_state);
+        }
+
+        // handle debug shell input, never waits on the UART
+        if let Some(command) = debug_shell.poll() {
+            if let Err(e) = process_shell_command(
+                command,
+                &mut debug_shell,
+                &mut modules,
+                &can_gateway,
+                &mut system_state,
+                &mut board,
+            ) {
                 handle_error(
                     e,
                     &mut modules,
                     &mut can_gateway,
-                    &mut debug_console,
+                    &mut system_state,
                     &mut board.leds,
                 );
             }
@@ -255,61 +539,433 @@ fn main() -> ! {
 fn process_control_can_frames(
     modules: &mut ControlModules,
     can_gateway: &mut CanGatewayModule,
-    debug_console: &mut DebugConsole,
+    system_state: &mut SystemStateMachine,
+    leds: &mut Leds,
 ) -> Result<(), OxccError> {
-    // poll both control CAN FIFOs
+    // drain both control CAN FIFOs, the hardware only holds three
+    // frames in each
     for fifo in &[RxFifo::Fifo0, RxFifo::Fifo1] {
-        match can_gateway.control_can().receive(fifo) {
-            Ok(rx_frame) => {
-                modules.brake.process_rx_frame(&rx_frame, debug_console)?;
-                modules
-                    .throttle
-                    .process_rx_frame(&rx_frame, debug_console)?;
-                modules
-                    .steering
-                    .process_rx_frame(&rx_frame, debug_console)?;
+        loop {
+            let rx_frame = match can_gateway.receive_control_can_frame(fifo) {
+                Ok(rx_frame) => rx_frame,
+                Err(CanError::BufferExhausted) => break,
+                Err(e) => return Err(OxccError::from(e)),
+            };
+
+            process_control_can_frame(&rx_frame, modules, can_gateway, system_state, leds)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn process_control_can_frame(
+    rx_frame: &CanFrame,
+    modules: &mut ControlModules,
+    can_gateway: &mut CanGatewayModule,
+    system_state: &mut SystemStateMachine,
+    leds: &mut Leds,
+) -> Result<(), OxccError> {
+    if is_oscc_frame_with_id(rx_frame, OXCC_SYSTEM_CLEAR_FAULTS_CAN_ID) {
+        clear_faults(system_state, modules, leds);
+    } else if is_oscc_frame_with_id(rx_frame, OXCC_PARAM_REQUEST_CAN_ID) {
+        if let CanFrame::DataFrame(ref frame) = rx_frame {
+            if frame.data().len() == OXCC_PARAM_REQUEST_CAN_DLC as usize {
+                let param_response = params::process_request(
+                    &OxccParamRequest::from(frame),
+                    is_control_enabled(modules),
+                );
+
+                if let Err(e) = can_gateway.publish_param_response(&param_response) {
+                    if e != CanError::Timeout {
+                        return Err(OxccError::from(e));
+                    }
+                }
+            } else {
+                warn!("Param request dropped, DLC {}", frame.data().len());
             }
-            Err(e) => {
-                // report all but BufferExhausted (no data)
-                if e != CanError::BufferExhausted {
-                    return Err(OxccError::from(e));
+        }
+    } else if is_pedal_command(rx_frame) && modules.speed_control.is_engaged() {
+        // speed control owns the brake and throttle commands
+    } else {
+        modules.brake.process_rx_frame(rx_frame)?;
+        modules.throttle.process_rx_frame(rx_frame)?;
+        modules.steering.process_rx_frame(rx_frame)?;
+        modules.speed_control.process_rx_frame(rx_frame);
+    }
+
+    Ok(())
+}
+
+/// Executes a debug shell command, the output goes to the shell
+///
+/// Control modules can only be enabled from the shell with the bench
+/// profile active, disabling them is always allowed.
+fn process_shell_command(
+    command: ShellCommand,
+    debug_shell: &mut DebugShell,
+    modules: &mut ControlModules,
+    can_gateway: &CanGatewayModule,
+    system_state: &mut SystemStateMachine,
+    board: &mut Board,
+) -> Result<(), OxccError> {
+    // the shell output never fails, it is dropped when the buffer is full
+    match command {
+        ShellCommand::Status => {
+            let profile = vehicle::profile();
+            let _ = writeln!(
+                debug_shell,
+                "state {:?} control allowed {} profile {} ({})",
+                system_state.state(),
+                system_state.control_allowed(),
+                profile.name,
+                profile.id
+            );
+
+            let report = modules.brake.supply_brake_report();
+            debug_shell.print_module_state(
+                "brake",
+                report.enabled,
+                report.operator_override,
+                report.dtcs,
+            );
+            let report = modules.throttle.supply_throttle_report();
+            debug_shell.print_module_state(
+                "throttle",
+                report.enabled,
+                report.operator_override,
+                report.dtcs,
+            );
+            let report = modules.steering.supply_steering_report();
+            debug_shell.print_module_state(
+                "steering",
+                report.enabled,
+                report.operator_override,
+                report.dtcs,
+            );
+
+            let _ = writeln!(debug_shell, "{:?}", fault_record::read());
+        }
+        ShellCommand::Sensors => {
+            let reading = modules.brake.read_sensor();
+            debug_shell.print_sensor_reading("brake", &reading);
+            let reading = modules.throttle.read_sensor();
+            debug_shell.print_sensor_reading("throttle", &reading);
+            let reading = modules.steering.read_sensor();
+            debug_shell.print_sensor_reading("steering", &reading);
+        }
+        ShellCommand::Enable(module) => {
+            if !vehicle::is_bench() {
+                let _ = writeln!(debug_shell, "enable is only allowed with the bench profile");
+            } else {
+                match module {
+                    ControlModule::Brake => modules.brake.enable_control()?,
+                    ControlModule::Throttle => modules.throttle.enable_control()?,
+                    ControlModule::Steering => modules.steering.enable_control()?,
                 }
             }
         }
+        ShellCommand::Disable(module) => match module {
+            Some(ControlModule::Brake) => modules.brake.disable_control()?,
+            Some(ControlModule::Throttle) => modules.throttle.disable_control()?,
+            Some(ControlModule::Steering) => modules.steering.disable_control()?,
+            None => disable_all_controls(modules),
+        },
+        ShellCommand::ClearFaults => {
+            clear_faults(system_state, modules, &mut board.leds);
+            let _ = writeln!(debug_shell, "state {:?}", system_state.state());
+        }
+        ShellCommand::ResetConditions => {
+            debug_shell.print_reset_conditions(&board.reset_conditions)
+        }
+        ShellCommand::ParamList => {
+            let profile = vehicle::profile();
+            let mut id = 0;
+            while let Some(param) = Param::from_id(id) {
+                let limits = param.limits(profile);
+                let _ = writeln!(
+                    debug_shell,
+                    "{} {:?} {} [{} {}] default {}",
+                    id,
+                    param,
+                    params::get(param),
+                    limits.min,
+                    limits.max,
+                    param.default_value(profile)
+                );
+                id += 1;
+            }
+        }
+        ShellCommand::ParamGet(param_id) => {
+            process_shell_param_request(
+                OXCC_PARAM_OPERATION_READ,
+                param_id,
+                0.0,
+                debug_shell,
+                modules,
+            );
+        }
+        ShellCommand::ParamSet(param_id, value) => {
+            process_shell_param_request(
+                OXCC_PARAM_OPERATION_WRITE,
+                param_id,
+                value,
+                debug_shell,
+                modules,
+            );
+        }
+        ShellCommand::ParamSave => {
+            process_shell_param_request(OXCC_PARAM_OPERATION_SAVE, 0, 0.0, debug_shell, modules);
+        }
+        ShellCommand::ParamDefaults => {
+            process_shell_param_request(
+                OXCC_PARAM_OPERATION_RESTORE_DEFAULTS,
+                0,
+                0.0,
+                debug_shell,
+                modules,
+            );
+        }
+        ShellCommand::CanStatistics => {
+            debug_shell.print_can_statistics(
+                &can_gateway.statistics(),
+                &can_gateway.control_can_error_state(),
+                &can_gateway.obd_can_error_state(),
+            );
+        }
+        ShellCommand::Log(level) => {
+            if let Some(level) = level {
+                log::set_level(level);
+            }
+            let _ = writeln!(
+                debug_shell,
+                "log level {} max {} dropped {}",
+                log::level().name(),
+                log::MAX_LEVEL.name(),
+                log::dropped()
+            );
+        }
     }
 
     Ok(())
 }
 
+/// Tracks the system and module states for the telemetry events,
+/// and publishes a snapshot once per telemetry period
+fn publish_telemetry(
+    telemetry: &mut Telemetry,
+    modules: &mut ControlModules,
+    system_state: &SystemStateMachine,
+) {
+    telemetry.track_system_state(system_state.state().into());
+    {
+        let report = modules.brake.supply_brake_report();
+        telemetry.track_module(
+            TELEMETRY_MODULE_BRAKE,
+            report.enabled,
+            report.operator_override,
+            report.dtcs,
+        );
+    }
+    {
+        let report = modules.throttle.supply_throttle_report();
+        telemetry.track_module(
+            TELEMETRY_MODULE_THROTTLE,
+            report.enabled,
+            report.operator_override,
+            report.dtcs,
+        );
+    }
+    {
+        let report = modules.steering.supply_steering_report();
+        telemetry.track_module(
+            TELEMETRY_MODULE_STEERING,
+            report.enabled,
+            report.operator_override,
+            report.dtcs,
+        );
+    }
+
+    if telemetry.snapshot_due() {
+        telemetry.publish_system(system_state.state().into(), system_state.control_allowed());
+
+        let reading = modules.brake.read_sensor();
+        let spoof = modules.brake.commanded_spoof();
+        telemetry.publish_module(TELEMETRY_MODULE_BRAKE, &reading, spoof);
+
+        let reading = modules.throttle.read_sensor();
+        let spoof = modules.throttle.commanded_spoof();
+        telemetry.publish_module(TELEMETRY_MODULE_THROTTLE, &reading, spoof);
+
+        let reading = modules.steering.read_sensor();
+        let spoof = modules.steering.commanded_spoof();
+        telemetry.publish_module(TELEMETRY_MODULE_STEERING, &reading, spoof);
+    }
+}
+
+/// Handles a parameter request from the debug shell the same way
+/// as one received on the control CAN bus
+fn process_shell_param_request(
+    operation: u8,
+    param_id: u8,
+    value: f32,
+    debug_shell: &mut DebugShell,
+    modules: &ControlModules,
+) {
+    let param_response = params::process_request(
+        &OxccParamRequest {
+            operation,
+            param_id,
+            value,
+        },
+        is_control_enabled(modules),
+    );
+
+    let _ = writeln!(
+        debug_shell,
+        "{:?} status {} value {}",
+        Param::from_id(param_response.param_id),
+        param_response.status,
+        param_response.value
+    );
+}
+
+fn is_control_enabled(modules: &ControlModules) -> bool {
+    modules.brake.is_enabled() || modules.throttle.is_enabled() || modules.steering.is_enabled()
+}
+
+/// Clears the persistent fault record when faulted, and any
+/// recoverable system state
+fn clear_faults(
+    system_state: &mut SystemStateMachine,
+    modules: &mut ControlModules,
+    leds: &mut Leds,
+) {
+    if system_state.state() == SystemState::Faulted {
+        fault_record::clear();
+    }
+    update_system_state(SystemEvent::ClearFaults, system_state, modules, leds);
+}
+
+fn is_oscc_frame_with_id(can_frame: &CanFrame, can_id: u16) -> bool {
+    if let CanFrame::DataFrame(ref frame) = can_frame {
+        let id: u32 = frame.id().into();
+        let data = frame.data();
+
+        (id == can_id.into())
+            && (data.len() >= 2)
+            && (data[0] == OSCC_MAGIC_BYTE_0)
+            && (data[1] == OSCC_MAGIC_BYTE_1)
+    } else {
+        false
+    }
+}
+
+fn is_pedal_command(can_frame: &CanFrame) -> bool {
+    is_oscc_frame_with_id(can_frame, OSCC_BRAKE_COMMAND_CAN_ID)
+        || is_oscc_frame_with_id(can_frame, OSCC_THROTTLE_COMMAND_CAN_ID)
+}
+
+/// Operator overrides are expected during normal operation and only
+/// disable the module, any other DTC is a system fault
+fn is_system_fault(fault_report: &OsccFaultReport) -> bool {
+    let mut dtcs = fault_report.dtcs;
+
+    match fault_report.fault_origin_id {
+        FAULT_ORIGIN_BRAKE => dtcs.clear(OSCC_BRAKE_DTC_OPERATOR_OVERRIDE),
+        FAULT_ORIGIN_THROTTLE => dtcs.clear(OSCC_THROTTLE_DTC_OPERATOR_OVERRIDE),
+        FAULT_ORIGIN_STEERING => dtcs.clear(OSCC_STEERING_DTC_OPERATOR_OVERRIDE),
+        _ => (),
+    }
+
+    dtcs.are_any_set()
+}
+
+fn update_commands(modules: &mut ControlModules) -> Result<(), OxccError> {
+    modules.brake.update_command()?;
+    modules.throttle.update_command()?;
+    modules.steering.update_command()
+}
+
 fn check_for_faults(
     modules: &mut ControlModules,
     can_gateway: &mut CanGatewayModule,
-    debug_console: &mut DebugConsole,
+    system_state: &mut SystemStateMachine,
+    leds: &mut Leds,
 ) -> Result<(), OxccError> {
-    let maybe_fault = modules.brake.check_for_faults(debug_console)?;
+    let mut module_fault = false;
+
+    let maybe_fault = modules.brake.check_for_faults()?;
     if let Some(brake_fault) = maybe_fault {
+        if is_system_fault(brake_fault) {
+            fault_record::record_fault_report(brake_fault);
+            module_fault = true;
+        }
         can_gateway.publish_fault_report(brake_fault)?;
     }
 
-    let maybe_fault = modules.throttle.check_for_faults(debug_console)?;
+    let maybe_fault = modules.throttle.check_for_faults()?;
     if let Some(throttle_fault) = maybe_fault {
+        if is_system_fault(throttle_fault) {
+            fault_record::record_fault_report(throttle_fault);
+            module_fault = true;
+        }
         can_gateway.publish_fault_report(throttle_fault)?;
     }
 
-    let maybe_fault = modules.steering.check_for_faults(debug_console)?;
+    let maybe_fault = modules.steering.check_for_faults()?;
     if let Some(steering_fault) = maybe_fault {
+        if is_system_fault(steering_fault) {
+            fault_record::record_fault_report(steering_fault);
+            module_fault = true;
+        }
         can_gateway.publish_fault_report(steering_fault)?;
     }
 
+    if module_fault {
+        update_system_state(SystemEvent::ModuleFault, system_state, modules, leds);
+    }
+
     Ok(())
 }
 
+/// Applies a system event, disabling all controls when entering
+/// the `Degraded`, `SafeStop` or `Faulted` states
+fn update_system_state(
+    event: SystemEvent,
+    system_state: &mut SystemStateMachine,
+    modules: &mut ControlModules,
+    leds: &mut Leds,
+) {
+    if let Some(state) = system_state.handle(event) {
+        info!("System state: {:?}", state);
+
+        match state {
+            SystemState::SafeStop | SystemState::Faulted => {
+                leds[Color::Red].on();
+                disable_all_controls(modules);
+            }
+            SystemState::Degraded => disable_all_controls(modules),
+            SystemState::SelfTest => leds[Color::Red].off(),
+            _ => (),
+        }
+    }
+}
+
+fn disable_all_controls(modules: &mut ControlModules) {
+    let _ = modules.throttle.disable_control();
+    let _ = modules.steering.disable_control();
+    let _ = modules.brake.disable_control();
+    modules.speed_control.deactivate();
+}
+
 // NOTE
 // ignoring transmit timeouts until a proper error handling strategy is
 // implemented
 fn publish_reports(
     modules: &mut ControlModules,
     can_gateway: &mut CanGatewayModule,
+    system_state: &SystemStateMachine,
 ) -> Result<(), OxccError> {
     // attempt to publish them all, only report the last to fail
     let mut result = Ok(());
@@ -334,6 +990,35 @@ fn publish_reports(
         }
     }
 
+    let system_state_report = OxccSystemStateReport {
+        state: system_state.state().into(),
+        control_allowed: system_state.control_allowed(),
+        vehicle_profile: vehicle::profile().id,
+    };
+    if let Err(e) = can_gateway.publish_system_state_report(&system_state_report) {
+        if e != CanError::Timeout {
+            result = Err(OxccError::from(e));
+        }
+    }
+
+    if let Err(e) = can_gateway.publish_fault_record_report(&fault_record::read()) {
+        if e != CanError::Timeout {
+            result = Err(OxccError::from(e));
+        }
+    }
+
+    let (version_major, version_minor) = firmware_image::version();
+    let identity_report = OxccIdentityReport {
+        image_crc: firmware_image::image_crc(),
+        version_major,
+        version_minor,
+    };
+    if let Err(e) = can_gateway.publish_identity_report(&identity_report) {
+        if e != CanError::Timeout {
+            result = Err(OxccError::from(e));
+        }
+    }
+
     result
 }
 
@@ -342,27 +1027,38 @@ fn handle_error(
     error: OxccError,
     modules: &mut ControlModules,
     can_gateway: &mut CanGatewayModule,
-    debug_console: &mut DebugConsole,
+    system_state: &mut SystemStateMachine,
     leds: &mut Leds,
 ) {
     leds[Color::Red].on();
 
-    writeln!(debug_console, "ERROR: {:#?}", error).expect(DEBUG_WRITE_FAILURE);
+    error!("{:?}", error);
 
     // disable all controls
-    let _ = modules.throttle.disable_control(debug_console);
-    let _ = modules.steering.disable_control(debug_console);
-    let _ = modules.brake.disable_control(debug_console);
+    disable_all_controls(modules);
+
+    update_system_state(SystemEvent::Error, system_state, modules, leds);
 
     // publish reports
-    let _ = publish_reports(modules, can_gateway);
+    let _ = publish_reports(modules, can_gateway, system_state);
 }
 
 #[exception]
 fn HardFault(ef: &ExceptionFrame) -> ! {
+    fault_record::record_hard_fault();
     panic!("HardFault at {:#?}", ef);
 }
 
+#[exception]
+fn SysTick() {
+    sys_tick::tick();
+}
+
+#[interrupt]
+fn USART3() {
+    console::on_interrupt();
+}
+
 #[exception]
 fn DefaultHandler(irqn: i16) {
     panic!("Unhandled exception (IRQn = {})", irqn);
@@ -0,0 +1,107 @@
+//! Operator override re-arm policy
+//!
+//! After an operator override, a module can only be enabled again
+//! once it is re-armed according to its policy.
+
+use sys_tick;
+
+/// Operator override re-arm policies
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RearmPolicy {
+    /// Re-arm as soon as the input is back below the override threshold
+    Immediate,
+    /// Re-arm once the input stayed below the override threshold
+    /// for the given duration. \[ms\]
+    QuietTime(u32),
+    /// Re-arm on an acknowledgement received while the input is below
+    /// the override threshold
+    Acknowledge,
+}
+
+/// Re-arm states, reported in the module report frames
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RearmState {
+    Armed,
+    /// Input still above the override threshold
+    Overridden,
+    /// Waiting for the quiet time to elapse
+    QuietTime,
+    /// Waiting for an acknowledgement
+    AwaitingAcknowledge,
+}
+
+impl From<RearmState> for u8 {
+    fn from(s: RearmState) -> u8 {
+        match s {
+            RearmState::Armed => 0,
+            RearmState::Overridden => 1,
+            RearmState::QuietTime => 2,
+            RearmState::AwaitingAcknowledge => 3,
+        }
+    }
+}
+
+pub struct OverrideRearm {
+    policy: RearmPolicy,
+    state: RearmState,
+    quiet_since: u32,
+}
+
+impl OverrideRearm {
+    pub const fn new(policy: RearmPolicy) -> Self {
+        OverrideRearm {
+            policy,
+            state: RearmState::Armed,
+            quiet_since: 0,
+        }
+    }
+
+    pub fn state(&self) -> RearmState {
+        self.state
+    }
+
+    fn is_armed(&self) -> bool {
+        self.state == RearmState::Armed
+    }
+
+    pub fn override_detected(&mut self) {
+        self.state = RearmState::Overridden;
+    }
+
+    /// Applies the policy, returns whether the module is armed
+    pub fn update(&mut self, below_threshold: bool) -> bool {
+        if self.state == RearmState::Armed {
+            return true;
+        }
+
+        if !below_threshold {
+            self.state = RearmState::Overridden;
+            return false;
+        }
+
+        self.state = match (self.policy, self.state) {
+            (RearmPolicy::Immediate, _) => RearmState::Armed,
+            (RearmPolicy::QuietTime(duration), RearmState::QuietTime) => {
+                if sys_tick::millis_since(self.quiet_since) >= duration {
+                    RearmState::Armed
+                } else {
+                    RearmState::QuietTime
+                }
+            }
+            (RearmPolicy::QuietTime(_), _) => {
+                self.quiet_since = sys_tick::millis();
+                RearmState::QuietTime
+            }
+            (RearmPolicy::Acknowledge, _) => RearmState::AwaitingAcknowledge,
+        };
+
+        self.is_armed()
+    }
+
+    /// Acknowledgement, only effective while awaiting it
+    pub fn acknowledge(&mut self) {
+        if self.state == RearmState::AwaitingAcknowledge {
+            self.state = RearmState::Armed;
+        }
+    }
+}
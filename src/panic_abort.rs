@@ -3,22 +3,23 @@
 //! The implementation performs the following (in order):
 //! - disable safety/control related GPIO pins
 //! - enable red LED and brake lights as a fault indicator
+//! - record the panic in the persistent fault record
 //! - output the PanicInfo to Serial3
 //! - `intrinsics::abort`
 //!
+//! The abort trap escalates to the HardFault exception, which panics
+//! again. The fault record keeps the reason and line of the first panic.
+//!
 //! **NOTE**
 //! The watchdog is enable by default, so you might not
 //! even notice the fault.
-//! It might be worth exposing the `hard_fault_indicator()`
-//! function so we can call it during initialization if
-//! the `ResetConditions` indicate a watchdog reset. That
-//! would persist the indication across panicing/faults.
-//! We could also disable the watchdog (and other interrupts)
-//! completely to stay in the abort.
+//! The fault record survives the watchdog reset, control stays
+//! locked out until the faults are cleared, see `fault_record`.
 
 use core::panic::PanicInfo;
 use core::{intrinsics, ptr};
 use cortex_m::interrupt::CriticalSection;
+use fault_record;
 
 use nucleo_f767zi::hal::stm32f7x7;
 use nucleo_f767zi::hal::time::*;
@@ -42,6 +43,7 @@ fn panic(info: &PanicInfo) -> ! {
     cortex_m::interrupt::free(|cs| {
         disable_controls_gpio(cs);
         hard_fault_indicator(cs);
+        fault_record::record_panic(info.location().map_or(0, |l| l.line()));
         serial3_panicinfo_dump(cs, info);
     });
 
@@ -62,8 +64,45 @@ fn disable_controls_gpio(_cs: &CriticalSection) {
     gpiod.odr.modify(|_, w| w.odr11().clear_bit());
 
     // disable brake controls spoof-enable pin on PD12
+    // (master cylinder solenoid on the Kia Soul Petrol)
     gpiod.moder.modify(|_, w| w.moder12().output());
     gpiod.odr.modify(|_, w| w.odr12().clear_bit());
+
+    #[cfg(feature = "kia-soul-petrol")]
+    disable_brake_hydraulics_gpio();
+}
+
+/// Disable the Kia Soul Petrol brake solenoid PWM outputs on PE9, PE11,
+/// PE13 and PE14 by taking the pins away from TIM1, and the accumulator
+/// pump relay on PD14
+#[cfg(feature = "kia-soul-petrol")]
+fn disable_brake_hydraulics_gpio() {
+    let gpiod = unsafe { &*stm32f7x7::GPIOD::ptr() };
+    let gpioe = unsafe { &*stm32f7x7::GPIOE::ptr() };
+
+    gpiod.moder.modify(|_, w| w.moder14().output());
+    gpiod.odr.modify(|_, w| w.odr14().clear_bit());
+
+    gpioe.odr.modify(|_, w| {
+        w.odr9()
+            .clear_bit()
+            .odr11()
+            .clear_bit()
+            .odr13()
+            .clear_bit()
+            .odr14()
+            .clear_bit()
+    });
+    gpioe.moder.modify(|_, w| {
+        w.moder9()
+            .output()
+            .moder11()
+            .output()
+            .moder13()
+            .output()
+            .moder14()
+            .output()
+    });
 }
 
 /// Enable the hard fault indication
@@ -0,0 +1,568 @@
+//! Runtime tunable parameters
+//!
+//! A small set of the vehicle profile thresholds and the telemetry period
+//! can be changed without reflashing, through `OxccParamRequest` frames.
+//! The compiled constants are the defaults, and every value is held within
+//! the hard limits of the vehicle profile.
+//!
+//! The parameters are saved to the last flash sector (sector 11, 256 KB,
+//! single bank mode), which `memory.x` keeps out of the image.
+//! Each save appends a record holding all the values, a layout version
+//! and a CRC. The newest valid record with the current version is loaded
+//! on boot, anything else falls back to the defaults.
+//!
+//! Every record also holds the ID of the vehicle profile its values
+//! belong to, loading a record selects that profile. Selecting another
+//! profile saves a record with the defaults of that profile, it becomes
+//! active on the next reset. Saving the current values before that reset
+//! keeps the active profile.
+//!
+//! Erasing the sector stalls the flash for seconds, so it only happens on
+//! boot, before the watchdogs are started, once the sector is full.
+//! Changes are only accepted while all control modules are disabled.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::{mem, ptr};
+use cortex_m::asm;
+use nucleo_f767zi::hal::stm32f7x7::{flash, CRC, FLASH, RCC};
+use param_can_protocol::*;
+use steering_module::FILTER_ALPHA;
+use telemetry::{TELEMETRY_PERIOD, TELEMETRY_PERIOD_LIMITS};
+use vehicle::{self, VehicleProfile};
+
+/// Parameter flash sector, see `memory.x`
+const PARAM_SECTOR: u32 = 11;
+const PARAM_SECTOR_BASE: usize = 0x081C_0000;
+const PARAM_SECTOR_SIZE: usize = 256 * 1024;
+
+const PARAM_RECORD_MAGIC: u32 = 0x9A4A_0CC0;
+
+/// Layout version of the saved records, records of another
+/// version are ignored
+const PARAM_RECORD_VERSION: u16 = 3;
+
+const PARAM_COUNT: usize = 8;
+
+const FLASH_KEY1: u32 = 0x4567_0123;
+const FLASH_KEY2: u32 = 0xCDEF_89AB;
+const FLASH_CR_PG: u32 = 1 << 0;
+const FLASH_CR_SER: u32 = 1 << 1;
+const FLASH_CR_SNB_SHIFT: u32 = 3;
+const FLASH_CR_PSIZE_X32: u32 = 0b10 << 8;
+const FLASH_CR_STRT: u32 = 1 << 16;
+const FLASH_CR_LOCK: u32 = 1 << 31;
+const FLASH_SR_BSY: u32 = 1 << 16;
+
+/// OPERR, WRPERR, PGAERR, PGPERR and ERSERR
+const FLASH_SR_ERRORS: u32 = (1 << 1) | (1 << 4) | (1 << 5) | (1 << 6) | (1 << 7);
+
+/// Erased flash word
+const ERASED: u32 = 0xFFFF_FFFF;
+
+/// Current values as `f32` bits, set by `load`
+static VALUES: [AtomicUsize; PARAM_COUNT] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+/// Next free record slot in the parameter sector, `PARAM_SLOT_COUNT`
+/// once the sector is full
+static NEXT_SLOT: AtomicUsize = AtomicUsize::new(0);
+
+/// Hard limits of a parameter value
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ParamLimits {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl ParamLimits {
+    pub fn contains(&self, value: f32) -> bool {
+        (value >= self.min) && (value <= self.max)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Param {
+    /// Fault hysteresis, applied on the next reset \[ms\]
+    FaultHysteresis,
+    /// Accelerator override threshold \[steps\]
+    AcceleratorOverrideThreshold,
+    /// Steering torque difference override threshold \[steps\]
+    TorqueDifferenceOverrideThreshold,
+    /// Steering torque difference filter `FILTER_ALPHA`
+    SteeringFilterAlpha,
+    /// Brake pedal override threshold, \[steps\] or \[decibars\]
+    BrakeOverrideThreshold,
+    /// Throttle interlock brake command threshold
+    ThrottleInterlockBrakeCommandThreshold,
+    /// Throttle interlock brake pedal position threshold \[steps\]
+    #[cfg(not(feature = "kia-soul-petrol"))]
+    ThrottleInterlockBrakePedalThreshold,
+    /// Throttle interlock brake pedal pressure threshold, takes the ID of
+    /// `ThrottleInterlockBrakePedalThreshold` in the Kia Soul Petrol image
+    /// \[decibars\]
+    #[cfg(feature = "kia-soul-petrol")]
+    ThrottleInterlockBrakePressureThreshold,
+    /// Telemetry record period, 0 turns the stream off \[ms\]
+    TelemetryPeriod,
+}
+
+const PARAMS: [Param; PARAM_COUNT] = [
+    Param::FaultHysteresis,
+    Param::AcceleratorOverrideThreshold,
+    Param::TorqueDifferenceOverrideThreshold,
+    Param::SteeringFilterAlpha,
+    Param::BrakeOverrideThreshold,
+    Param::ThrottleInterlockBrakeCommandThreshold,
+    #[cfg(not(feature = "kia-soul-petrol"))]
+    Param::ThrottleInterlockBrakePedalThreshold,
+    #[cfg(feature = "kia-soul-petrol")]
+    Param::ThrottleInterlockBrakePressureThreshold,
+    Param::TelemetryPeriod,
+];
+
+impl Param {
+    /// Looks up a parameter by its CAN ID, the index in `PARAMS`
+    pub fn from_id(id: u8) -> Option<Param> {
+        PARAMS.get(usize::from(id)).cloned()
+    }
+
+    /// The compiled constant of the vehicle profile
+    pub fn default_value(self, profile: &VehicleProfile) -> f32 {
+        match self {
+            Param::FaultHysteresis => profile.fault_hysteresis as f32,
+            Param::AcceleratorOverrideThreshold => profile.accelerator_override_threshold,
+            Param::TorqueDifferenceOverrideThreshold => {
+                profile.torque_difference_override_threshold
+            }
+            Param::SteeringFilterAlpha => FILTER_ALPHA,
+            Param::BrakeOverrideThreshold => profile.brake_override_threshold,
+            Param::ThrottleInterlockBrakeCommandThreshold => {
+                profile.throttle_interlock_brake_command_threshold
+            }
+            #[cfg(not(feature = "kia-soul-petrol"))]
+            Param::ThrottleInterlockBrakePedalThreshold => {
+                profile.throttle_interlock_brake_pedal_threshold
+            }
+            #[cfg(feature = "kia-soul-petrol")]
+            Param::ThrottleInterlockBrakePressureThreshold => {
+                profile.throttle_interlock_brake_pressure_threshold
+            }
+            Param::TelemetryPeriod => TELEMETRY_PERIOD,
+        }
+    }
+
+    /// The hard limits from the vehicle profile
+    pub fn limits(self, profile: &VehicleProfile) -> ParamLimits {
+        match self {
+            Param::FaultHysteresis => profile.fault_hysteresis_limits,
+            Param::AcceleratorOverrideThreshold => profile.accelerator_override_threshold_limits,
+            Param::TorqueDifferenceOverrideThreshold => {
+                profile.torque_difference_override_threshold_limits
+            }
+            Param::SteeringFilterAlpha => profile.steering_filter_alpha_limits,
+            Param::BrakeOverrideThreshold => profile.brake_override_threshold_limits,
+            Param::ThrottleInterlockBrakeCommandThreshold => {
+                profile.throttle_interlock_brake_command_threshold_limits
+            }
+            #[cfg(not(feature = "kia-soul-petrol"))]
+            Param::ThrottleInterlockBrakePedalThreshold => {
+                profile.throttle_interlock_brake_pedal_threshold_limits
+            }
+            #[cfg(feature = "kia-soul-petrol")]
+            Param::ThrottleInterlockBrakePressureThreshold => {
+                profile.throttle_interlock_brake_pressure_threshold_limits
+            }
+            Param::TelemetryPeriod => TELEMETRY_PERIOD_LIMITS,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ParamError {
+    UnknownParam,
+    UnknownOperation,
+    UnknownVehicleProfile,
+    OutOfLimits,
+    /// Changes are refused while any control module is enabled
+    ControlEnabled,
+    /// No free record slot left until the next boot
+    StoreFull,
+    /// Flash status register error bits
+    Flash(u32),
+}
+
+impl From<ParamError> for u8 {
+    fn from(e: ParamError) -> u8 {
+        match e {
+            ParamError::UnknownParam => OXCC_PARAM_STATUS_UNKNOWN_PARAM,
+            ParamError::UnknownOperation => OXCC_PARAM_STATUS_UNKNOWN_OPERATION,
+            ParamError::UnknownVehicleProfile => OXCC_PARAM_STATUS_UNKNOWN_VEHICLE_PROFILE,
+            ParamError::OutOfLimits => OXCC_PARAM_STATUS_OUT_OF_LIMITS,
+            ParamError::ControlEnabled => OXCC_PARAM_STATUS_CONTROL_ENABLED,
+            ParamError::StoreFull => OXCC_PARAM_STATUS_STORE_FULL,
+            ParamError::Flash(_) => OXCC_PARAM_STATUS_FLASH_ERROR,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ParamRecord {
+    magic: u32,
+    version: u16,
+    count: u8,
+    /// `VehicleProfile::id` the values belong to
+    vehicle_profile: u8,
+    /// `f32` bits, in `PARAMS` order
+    values: [u32; PARAM_COUNT],
+    crc: u32,
+}
+
+const PARAM_RECORD_WORDS: usize = mem::size_of::<ParamRecord>() / mem::size_of::<u32>();
+const PARAM_SLOT_COUNT: usize = PARAM_SECTOR_SIZE / mem::size_of::<ParamRecord>();
+
+impl ParamRecord {
+    fn words(&self) -> &[u32; PARAM_RECORD_WORDS] {
+        unsafe { &*(self as *const ParamRecord as *const [u32; PARAM_RECORD_WORDS]) }
+    }
+
+    fn compute_crc(&self) -> u32 {
+        let words = self.words();
+        crc32(&words[..PARAM_RECORD_WORDS - 1])
+    }
+
+    fn is_valid(&self) -> bool {
+        (self.magic == PARAM_RECORD_MAGIC)
+            && (self.version == PARAM_RECORD_VERSION)
+            && (usize::from(self.count) == PARAM_COUNT)
+            && (self.crc == self.compute_crc())
+    }
+}
+
+/// Current value of a parameter
+pub fn get(param: Param) -> f32 {
+    f32::from_bits(VALUES[param as usize].load(Ordering::Relaxed) as u32)
+}
+
+/// Sets the current value of a parameter, `save` persists it
+pub fn set(param: Param, value: f32) -> Result<(), ParamError> {
+    if !param.limits(vehicle::profile()).contains(value) {
+        return Err(ParamError::OutOfLimits);
+    }
+
+    VALUES[param as usize].store(value.to_bits() as usize, Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// Sets all parameters back to the compiled constants of the active
+/// vehicle profile, `save` persists them
+pub fn restore_defaults() {
+    let profile = vehicle::profile();
+
+    for param in PARAMS.iter() {
+        VALUES[*param as usize].store(
+            param.default_value(profile).to_bits() as usize,
+            Ordering::Relaxed,
+        );
+    }
+}
+
+/// Loads the newest valid record and selects its vehicle profile,
+/// or the defaults of the default profile
+///
+/// Must be called before the board is configured and the watchdogs are
+/// started, a full parameter sector is compacted here, which erases it.
+pub fn load() {
+    restore_defaults();
+
+    let mut newest = None;
+    let mut next_slot = PARAM_SLOT_COUNT;
+
+    for slot in 0..PARAM_SLOT_COUNT {
+        let record = read_slot(slot);
+
+        if record.magic == ERASED {
+            next_slot = slot;
+            break;
+        }
+
+        if record.is_valid() {
+            newest = Some(record);
+        }
+    }
+
+    if let Some(record) = newest {
+        // the values of a profile missing from this image are not
+        // meant for the default one
+        if vehicle::select(record.vehicle_profile) {
+            restore_defaults();
+
+            for (param, bits) in PARAMS.iter().zip(record.values.iter()) {
+                // a value outside of the current limits keeps its default
+                let _ = set(*param, f32::from_bits(*bits));
+            }
+        }
+    }
+
+    NEXT_SLOT.store(next_slot, Ordering::Relaxed);
+
+    if next_slot == PARAM_SLOT_COUNT {
+        if erase_sector().is_ok() {
+            NEXT_SLOT.store(0, Ordering::Relaxed);
+
+            if newest.is_some() {
+                let _ = save();
+            }
+        }
+    }
+}
+
+/// Appends a record of the current values to the parameter sector
+pub fn save() -> Result<(), ParamError> {
+    let mut values = [0; PARAM_COUNT];
+    for (param, bits) in PARAMS.iter().zip(values.iter_mut()) {
+        *bits = get(*param).to_bits();
+    }
+
+    append_record(vehicle::profile(), values)
+}
+
+/// Appends a record of the vehicle profile with its default values to
+/// the parameter sector, the profile becomes active on the next reset
+pub fn save_vehicle_profile(profile: &VehicleProfile) -> Result<(), ParamError> {
+    let mut values = [0; PARAM_COUNT];
+    for (param, bits) in PARAMS.iter().zip(values.iter_mut()) {
+        *bits = param.default_value(profile).to_bits();
+    }
+
+    append_record(profile, values)
+}
+
+fn append_record(profile: &VehicleProfile, values: [u32; PARAM_COUNT]) -> Result<(), ParamError> {
+    let slot = NEXT_SLOT.load(Ordering::Relaxed);
+
+    if slot >= PARAM_SLOT_COUNT {
+        return Err(ParamError::StoreFull);
+    }
+
+    let mut record = ParamRecord {
+        magic: PARAM_RECORD_MAGIC,
+        version: PARAM_RECORD_VERSION,
+        count: PARAM_COUNT as u8,
+        vehicle_profile: profile.id,
+        values,
+        crc: 0,
+    };
+    record.crc = record.compute_crc();
+
+    // the slot is used up even if programming fails part way
+    NEXT_SLOT.store(slot + 1, Ordering::Relaxed);
+
+    program_slot(slot, &record)
+}
+
+/// Handles a parameter request, `control_enabled` refuses changes
+pub fn process_request(request: &OxccParamRequest, control_enabled: bool) -> OxccParamResponse {
+    if request.operation == OXCC_PARAM_OPERATION_SELECT_VEHICLE_PROFILE {
+        return process_vehicle_profile_request(request, control_enabled);
+    }
+
+    let param = Param::from_id(request.param_id);
+
+    let result = match (request.operation, param) {
+        (OXCC_PARAM_OPERATION_READ, Some(_)) => Ok(()),
+        (OXCC_PARAM_OPERATION_WRITE, Some(_))
+        | (OXCC_PARAM_OPERATION_SAVE, _)
+        | (OXCC_PARAM_OPERATION_RESTORE_DEFAULTS, _)
+            if control_enabled =>
+        {
+            Err(ParamError::ControlEnabled)
+        }
+        (OXCC_PARAM_OPERATION_WRITE, Some(param)) => set(param, request.value),
+        (OXCC_PARAM_OPERATION_SAVE, _) => save(),
+        (OXCC_PARAM_OPERATION_RESTORE_DEFAULTS, _) => {
+            restore_defaults();
+            Ok(())
+        }
+        (OXCC_PARAM_OPERATION_READ, None) | (OXCC_PARAM_OPERATION_WRITE, None) => {
+            Err(ParamError::UnknownParam)
+        }
+        _ => Err(ParamError::UnknownOperation),
+    };
+
+    match result {
+        Ok(()) if request.operation != OXCC_PARAM_OPERATION_READ => {
+            info!(
+                "Parameter operation {} on {:?} done",
+                request.operation, param
+            );
+        }
+        Err(e) => {
+            warn!(
+                "Parameter operation {} on {:?} failed: {:?}",
+                request.operation, param, e
+            );
+        }
+        _ => (),
+    }
+
+    OxccParamResponse {
+        operation: request.operation,
+        param_id: request.param_id,
+        status: match result {
+            Ok(()) => OXCC_PARAM_STATUS_OK,
+            Err(e) => e.into(),
+        },
+        value: param.map_or(0.0, get),
+    }
+}
+
+/// Handles a vehicle profile selection, the parameter ID of the
+/// request is the vehicle profile ID
+fn process_vehicle_profile_request(
+    request: &OxccParamRequest,
+    control_enabled: bool,
+) -> OxccParamResponse {
+    let profile = vehicle::find(request.param_id);
+
+    let result = match profile {
+        _ if control_enabled => Err(ParamError::ControlEnabled),
+        Some(profile) => save_vehicle_profile(profile),
+        None => Err(ParamError::UnknownVehicleProfile),
+    };
+
+    match (result, profile) {
+        (Ok(()), Some(profile)) => {
+            info!(
+                "Vehicle profile {} selected, active on the next reset",
+                profile.name
+            );
+        }
+        (Err(e), _) => {
+            warn!(
+                "Vehicle profile {} selection failed: {:?}",
+                request.param_id, e
+            );
+        }
+        _ => (),
+    }
+
+    OxccParamResponse {
+        operation: request.operation,
+        param_id: request.param_id,
+        status: match result {
+            Ok(()) => OXCC_PARAM_STATUS_OK,
+            Err(e) => e.into(),
+        },
+        value: 0.0,
+    }
+}
+
+fn read_slot(slot: usize) -> ParamRecord {
+    let address = PARAM_SECTOR_BASE + (slot * mem::size_of::<ParamRecord>());
+
+    unsafe { ptr::read_volatile(address as *const ParamRecord) }
+}
+
+fn unlock_flash(flash: &flash::RegisterBlock) {
+    if (flash.cr.read().bits() & FLASH_CR_LOCK) != 0 {
+        flash.keyr.write(|w| unsafe { w.bits(FLASH_KEY1) });
+        flash.keyr.write(|w| unsafe { w.bits(FLASH_KEY2) });
+    }
+}
+
+/// Waits for the flash operation to finish, then clears and
+/// returns the error bits
+fn wait_for_flash(flash: &flash::RegisterBlock) -> u32 {
+    while (flash.sr.read().bits() & FLASH_SR_BSY) != 0 {}
+
+    let errors = flash.sr.read().bits() & FLASH_SR_ERRORS;
+    flash.sr.write(|w| unsafe { w.bits(errors) });
+
+    errors
+}
+
+fn erase_sector() -> Result<(), ParamError> {
+    let flash = unsafe { &*FLASH::ptr() };
+
+    unlock_flash(flash);
+    wait_for_flash(flash);
+
+    flash.cr.write(|w| unsafe {
+        w.bits(FLASH_CR_PSIZE_X32 | FLASH_CR_SER | (PARAM_SECTOR << FLASH_CR_SNB_SHIFT))
+    });
+    flash
+        .cr
+        .modify(|r, w| unsafe { w.bits(r.bits() | FLASH_CR_STRT) });
+    asm::dsb();
+
+    let errors = wait_for_flash(flash);
+    flash.cr.write(|w| unsafe { w.bits(FLASH_CR_LOCK) });
+
+    if errors == 0 {
+        Ok(())
+    } else {
+        Err(ParamError::Flash(errors))
+    }
+}
+
+fn program_slot(slot: usize, record: &ParamRecord) -> Result<(), ParamError> {
+    let flash = unsafe { &*FLASH::ptr() };
+    let address = PARAM_SECTOR_BASE + (slot * mem::size_of::<ParamRecord>());
+
+    unlock_flash(flash);
+    wait_for_flash(flash);
+
+    flash
+        .cr
+        .write(|w| unsafe { w.bits(FLASH_CR_PSIZE_X32 | FLASH_CR_PG) });
+
+    let mut errors = 0;
+    for (index, word) in record.words().iter().enumerate() {
+        unsafe {
+            ptr::write_volatile(
+                (address + (index * mem::size_of::<u32>())) as *mut u32,
+                *word,
+            )
+        };
+        asm::dsb();
+
+        errors |= wait_for_flash(flash);
+        if errors != 0 {
+            break;
+        }
+    }
+
+    flash.cr.write(|w| unsafe { w.bits(FLASH_CR_LOCK) });
+
+    if errors == 0 {
+        Ok(())
+    } else {
+        Err(ParamError::Flash(errors))
+    }
+}
+
+/// CRC-32/MPEG-2 of the words with the CRC peripheral, as in
+/// `firmware_image::check_image`, also used by the `fault_record`
+pub fn crc32(words: &[u32]) -> u32 {
+    let rcc = unsafe { &*RCC::ptr() };
+    let crc = unsafe { &*CRC::ptr() };
+
+    rcc.ahb1enr.modify(|_, w| w.crcen().set_bit());
+    crc.cr.write(|w| w.reset().set_bit());
+
+    for word in words {
+        crc.dr.write(|w| unsafe { w.bits(*word) });
+    }
+
+    crc.dr.read().bits()
+}
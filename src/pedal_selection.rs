@@ -0,0 +1,38 @@
+//! Speed control pedal selection
+//!
+//! A single controller output selects the pedal, positive outputs press
+//! the throttle and negative outputs press the brake. A pressed pedal
+//! stays selected until the output crosses its release threshold, the
+//! band between the thresholds keeps the pedals from chattering.
+
+/// The pedal commanded by the speed controller
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Pedal {
+    Released,
+    Throttle,
+    Brake,
+}
+
+/// Pedal selection thresholds on the controller output
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PedalThresholds {
+    /// Output above which the throttle is pressed.
+    pub throttle_engage: f32,
+    /// Output below which a pressed throttle is released.
+    pub throttle_release: f32,
+    /// Output below which the brake is pressed.
+    pub brake_engage: f32,
+    /// Output above which a pressed brake is released.
+    pub brake_release: f32,
+}
+
+/// Selects the pedal for `output`, given the currently selected `pedal`
+pub fn select_pedal(pedal: Pedal, output: f32, thresholds: &PedalThresholds) -> Pedal {
+    match pedal {
+        Pedal::Throttle if output > thresholds.throttle_release => Pedal::Throttle,
+        Pedal::Brake if output < thresholds.brake_release => Pedal::Brake,
+        _ if output >= thresholds.throttle_engage => Pedal::Throttle,
+        _ if output <= thresholds.brake_engage => Pedal::Brake,
+        _ => Pedal::Released,
+    }
+}
@@ -0,0 +1,106 @@
+//! PID controller
+//!
+//! Fixed-rate PID controller, the caller provides the time step of
+//! every update.
+//!
+//! - the integral is clamped to the windup guard, and is not integrated
+//!   further while the output is saturated in the direction of the error
+//! - the output is clamped to the output limits
+//! - the derivative term acts on the measurement instead of the error,
+//!   a setpoint step does not kick the output
+//! - `initialize` seeds the state from the output currently applied,
+//!   so taking over control is bumpless
+
+use num;
+
+/// PID controller gains
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PidGains {
+    pub proportional: f32,
+    pub integral: f32,
+    pub derivative: f32,
+}
+
+pub struct Pid {
+    gains: PidGains,
+    windup_guard: f32,
+    output_min: f32,
+    output_max: f32,
+    integral: f32,
+    previous_measurement: Option<f32>,
+}
+
+impl Pid {
+    pub const fn new(gains: PidGains, windup_guard: f32, output_min: f32, output_max: f32) -> Self {
+        Pid {
+            gains,
+            windup_guard,
+            output_min,
+            output_max,
+            integral: 0.0,
+            previous_measurement: None,
+        }
+    }
+
+    /// Clears the integral and derivative state
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.previous_measurement = None;
+    }
+
+    /// Bumpless transfer, seeds the integral such that the next update
+    /// continues from `output` if the setpoint and measurement are unchanged
+    pub fn initialize(&mut self, setpoint: f32, measurement: f32, output: f32) {
+        let proportional = self.gains.proportional * (setpoint - measurement);
+
+        self.integral = if self.gains.integral != 0.0 {
+            num::clamp(
+                (output - proportional) / self.gains.integral,
+                -self.windup_guard,
+                self.windup_guard,
+            )
+        } else {
+            0.0
+        };
+        self.previous_measurement = Some(measurement);
+    }
+
+    /// Returns the clamped output for a time step of `dt` \[seconds\]
+    ///
+    /// A non-positive `dt` leaves the integral and derivative
+    /// terms unchanged.
+    pub fn update(&mut self, setpoint: f32, measurement: f32, dt: f32) -> f32 {
+        let error = setpoint - measurement;
+        let proportional = self.gains.proportional * error;
+
+        let derivative = match self.previous_measurement {
+            Some(previous_measurement) if dt > 0.0 => {
+                -self.gains.derivative * (measurement - previous_measurement) / dt
+            }
+            _ => 0.0,
+        };
+        self.previous_measurement = Some(measurement);
+
+        if dt > 0.0 {
+            let integral = num::clamp(
+                self.integral + (error * dt),
+                -self.windup_guard,
+                self.windup_guard,
+            );
+
+            let unclamped = proportional + (self.gains.integral * integral) + derivative;
+            let saturated_high = (unclamped > self.output_max) && (error > 0.0);
+            let saturated_low = (unclamped < self.output_min) && (error < 0.0);
+
+            if !saturated_high && !saturated_low {
+                self.integral = integral;
+            }
+        }
+
+        num::clamp(
+            proportional + (self.gains.integral * self.integral) + derivative,
+            self.output_min,
+            self.output_max,
+        )
+    }
+}
@@ -3,10 +3,7 @@
 use core::marker::PhantomData;
 use num;
 
-use typenum::{Cmp, Same, Unsigned, B1};
-// Although this says private, it's needed to write generic inequality
-// constraints for typenums
-use typenum::private::IsLessOrEqualPrivate;
+use typenum::Unsigned;
 
 /// Indicates that a type-level number may be converted to a runtime-level
 /// number of the type T
@@ -46,40 +43,3 @@ impl<T: PartialOrd, L: ReifyTo<T>, U: ReifyTo<T>> Bounded<T, L, U> {
         &self.val
     }
 }
-
-pub fn coerce<T, Lower1, Upper1, Lower2, Upper2>(
-    b: Bounded<T, Lower1, Upper1>,
-) -> Bounded<T, Lower2, Upper2>
-where
-    T: PartialOrd,
-    Lower1: ReifyTo<T>,
-    Upper1: ReifyTo<T>,
-    Lower2: ReifyTo<T>,
-    Upper2: ReifyTo<T>,
-
-    // Lower2 <= Upper2
-    Lower2: Cmp<Upper2>,
-    Lower2: IsLessOrEqualPrivate<Upper2, <Lower2 as Cmp<Upper2>>::Output>,
-    <Lower2 as IsLessOrEqualPrivate<Upper2, <Lower2 as Cmp<Upper2>>::Output>>::Output: Same<B1>,
-
-    // Lower1 <= Lower2
-    Lower2: Cmp<Lower1>,
-    Lower2: IsLessOrEqualPrivate<Lower1, <Lower2 as Cmp<Lower1>>::Output>,
-    <Lower2 as IsLessOrEqualPrivate<Lower1, <Lower2 as Cmp<Lower1>>::Output>>::Output: Same<B1>,
-
-    // Lower2 <= Upper1
-    Lower2: Cmp<Upper1>,
-    Lower2: IsLessOrEqualPrivate<Upper1, <Lower2 as Cmp<Upper1>>::Output>,
-    <Lower2 as IsLessOrEqualPrivate<Upper1, <Lower2 as Cmp<Upper1>>::Output>>::Output: Same<B1>,
-
-    // Upper1 <= Upper2
-    Upper1: Cmp<Upper2>,
-    Upper1: IsLessOrEqualPrivate<Upper2, <Upper1 as Cmp<Upper2>>::Output>,
-    <Upper1 as IsLessOrEqualPrivate<Upper2, <Upper1 as Cmp<Upper2>>::Output>>::Output: Same<B1>,
-{
-    Bounded {
-        val: b.val,
-        _lower_inclusive: PhantomData,
-        _upper_inclusive: PhantomData,
-    }
-}
@@ -0,0 +1,103 @@
+//! Power-on self test
+//!
+//! Runs while the system is in the `SelfTest` state, control can only
+//! be enabled once it passed.
+//!
+//! The following is checked:
+//! - idle sensor readings are plausible
+//! - each MCP4922 DAC accepts SPI writes
+//! - control CAN frames are received back in silent loopback mode
+//! - the system clock is driven by the locked PLL
+//! - the firmware image CRC, unpatched images are only accepted
+//!   in debug builds
+
+use brake_module::BrakeModule;
+use can_gateway_module::CanGatewayModule;
+use dtc::DtcBitfield;
+use firmware_image::{self, ImageCheck};
+use nucleo_f767zi::hal::stm32f7x7::RCC;
+use steering_module::SteeringModule;
+use throttle_module::ThrottleModule;
+
+// self test failure bits
+pub const SELF_TEST_BRAKE_SENSOR: u8 = 0;
+pub const SELF_TEST_THROTTLE_SENSOR: u8 = 1;
+pub const SELF_TEST_STEERING_SENSOR: u8 = 2;
+pub const SELF_TEST_BRAKE_DAC: u8 = 3;
+pub const SELF_TEST_THROTTLE_DAC: u8 = 4;
+pub const SELF_TEST_STEERING_DAC: u8 = 5;
+pub const SELF_TEST_CONTROL_CAN_LOOPBACK: u8 = 6;
+pub const SELF_TEST_CLOCKS: u8 = 7;
+pub const SELF_TEST_IMAGE_CRC: u8 = 8;
+
+/// RCC_CFGR system clock switch status, PLL used as system clock
+const RCC_CFGR_SWS_PLL: u8 = 0b10;
+
+#[derive(Copy, Clone, Debug)]
+pub struct SelfTestResult {
+    pub completed: bool,
+    /// Bitfield of the failed checks
+    pub failures: u16,
+}
+
+impl SelfTestResult {
+    pub const fn new() -> Self {
+        SelfTestResult {
+            completed: false,
+            failures: 0,
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.completed && !self.failures.are_any_set()
+    }
+}
+
+/// Runs all of the checks, control must be disabled
+pub fn run(
+    brake: &mut BrakeModule,
+    throttle: &mut ThrottleModule,
+    steering: &mut SteeringModule,
+    can_gateway: &mut CanGatewayModule,
+) -> SelfTestResult {
+    let mut result = SelfTestResult::new();
+
+    let checks = [
+        (brake.self_test_sensor(), SELF_TEST_BRAKE_SENSOR),
+        (throttle.self_test_sensor(), SELF_TEST_THROTTLE_SENSOR),
+        (steering.self_test_sensor(), SELF_TEST_STEERING_SENSOR),
+        (brake.self_test_dac(), SELF_TEST_BRAKE_DAC),
+        (throttle.self_test_dac(), SELF_TEST_THROTTLE_DAC),
+        (steering.self_test_dac(), SELF_TEST_STEERING_DAC),
+        (
+            can_gateway.control_can_loopback_test(),
+            SELF_TEST_CONTROL_CAN_LOOPBACK,
+        ),
+        (clocks_configured(), SELF_TEST_CLOCKS),
+        (image_valid(), SELF_TEST_IMAGE_CRC),
+    ];
+
+    for (passed, check) in checks.iter() {
+        if !passed {
+            result.failures.set(*check);
+        }
+    }
+
+    result.completed = true;
+
+    result
+}
+
+fn image_valid() -> bool {
+    match firmware_image::check_image() {
+        ImageCheck::Valid => true,
+        ImageCheck::Unpatched => cfg!(debug_assertions),
+        ImageCheck::Mismatch { .. } => false,
+    }
+}
+
+fn clocks_configured() -> bool {
+    let rcc = unsafe { &*RCC::ptr() };
+
+    rcc.cr.read().pllrdy().bit_is_set() && (rcc.cfgr.read().sws().bits() == RCC_CFGR_SWS_PLL)
+}
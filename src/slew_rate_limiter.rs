@@ -0,0 +1,79 @@
+//! Command slew rate limiter
+//!
+//! Only changes which increase the actuation are rate limited,
+//! moving the output towards zero is applied at once.
+
+use sys_tick;
+
+pub struct SlewRateLimiter {
+    /// Maximum rate of change \[units/second\]
+    max_rate: f32,
+    target: f32,
+    output: f32,
+    last_update: u32,
+    active: bool,
+}
+
+impl SlewRateLimiter {
+    pub const fn new(max_rate: f32, initial_output: f32) -> Self {
+        SlewRateLimiter {
+            max_rate,
+            target: initial_output,
+            output: initial_output,
+            last_update: 0,
+            active: false,
+        }
+    }
+
+    /// Restarts the limiter from `output`
+    pub fn reset(&mut self, output: f32) {
+        self.target = output;
+        self.output = output;
+        self.last_update = sys_tick::millis();
+        self.active = false;
+    }
+
+    /// Sets a new target and moves the output towards it
+    pub fn limit(&mut self, target: f32) -> f32 {
+        self.target = target;
+        self.update()
+    }
+
+    /// Moves the output towards the last target by no more than the
+    /// maximum rate allows for the time elapsed since the last update
+    ///
+    /// Must be called periodically, the output only reaches the target
+    /// through these updates.
+    pub fn update(&mut self) -> f32 {
+        let elapsed_ms = sys_tick::millis_since(self.last_update);
+        self.last_update = self.last_update.wrapping_add(elapsed_ms);
+
+        // release towards zero without delay
+        if self.output > 0.0 {
+            self.output = self.output.min(self.target.max(0.0));
+        } else if self.output < 0.0 {
+            self.output = self.output.max(self.target.min(0.0));
+        }
+
+        let max_step = self.max_rate * elapsed_ms as f32 / 1000.0;
+        let step = self.target - self.output;
+
+        if step > max_step {
+            self.output += max_step;
+            self.active = true;
+        } else if step < -max_step {
+            self.output -= max_step;
+            self.active = true;
+        } else {
+            self.output = self.target;
+            self.active = false;
+        }
+
+        self.output
+    }
+
+    /// Whether the output is still rate limited short of the target
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
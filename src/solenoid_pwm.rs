@@ -0,0 +1,102 @@
+//! Brake solenoid PWM
+//!
+//! TIM1 drives the Kia Soul Petrol brake solenoids, the accumulator
+//! (actuation) solenoids on CH1/CH2 and the release solenoids on CH3/CH4.
+//!
+//! The counter period is 256 steps so the duty cycles keep the 0-255 scale
+//! of the original OSCC Arduino `analogWrite()`, at the same ~3.921 kHz
+//! PWM frequency.
+
+use nucleo_f767zi::hal::stm32f7x7::{RCC, TIM1};
+use types::BrakeSolenoidPins;
+
+/// PWM frequency \[Hz\]
+const PWM_FREQUENCY: u32 = 3_921;
+
+/// Counter auto-reload value, 256 steps per period
+const PWM_PERIOD: u32 = 255;
+
+const CR1_CEN: u32 = 1 << 0;
+const CR1_ARPE: u32 = 1 << 7;
+const EGR_UG: u32 = 1 << 0;
+const BDTR_MOE: u32 = 1 << 15;
+
+/// PWM mode 1 with preload, for both channels of a CCMRx register
+const CCMR_PWM_MODE_1: u32 = (0b110 << 4) | (1 << 3) | (0b110 << 12) | (1 << 11);
+
+/// CC1E..CC4E, active high outputs
+const CCER_OUTPUTS_ENABLED: u32 = (1 << 0) | (1 << 4) | (1 << 8) | (1 << 12);
+
+pub struct SolenoidPwm {
+    tim1: TIM1,
+    _pins: BrakeSolenoidPins,
+}
+
+impl SolenoidPwm {
+    /// Configures and starts TIM1 with all solenoids off
+    ///
+    /// `timer_clock` is the TIM1 kernel clock. \[Hz\]
+    pub fn new(tim1: TIM1, pins: BrakeSolenoidPins, timer_clock: u32) -> Self {
+        let rcc = unsafe { &*RCC::ptr() };
+        rcc.apb2enr.modify(|_, w| w.tim1en().set_bit());
+
+        let prescaler = (timer_clock / (PWM_FREQUENCY * (PWM_PERIOD + 1))) - 1;
+
+        tim1.cr1.write(|w| unsafe { w.bits(0) });
+        tim1.psc.write(|w| unsafe { w.bits(prescaler) });
+        tim1.arr.write(|w| unsafe { w.bits(PWM_PERIOD) });
+        tim1.ccr1.write(|w| unsafe { w.bits(0) });
+        tim1.ccr2.write(|w| unsafe { w.bits(0) });
+        tim1.ccr3.write(|w| unsafe { w.bits(0) });
+        tim1.ccr4.write(|w| unsafe { w.bits(0) });
+        tim1.ccmr1_output
+            .write(|w| unsafe { w.bits(CCMR_PWM_MODE_1) });
+        tim1.ccmr2_output
+            .write(|w| unsafe { w.bits(CCMR_PWM_MODE_1) });
+        tim1.ccer.write(|w| unsafe { w.bits(CCER_OUTPUTS_ENABLED) });
+
+        // the advanced-control timer outputs also need the main output enable
+        tim1.bdtr.write(|w| unsafe { w.bits(BDTR_MOE) });
+
+        // load the preloaded registers and start counting
+        tim1.egr.write(|w| unsafe { w.bits(EGR_UG) });
+        tim1.cr1.write(|w| unsafe { w.bits(CR1_ARPE | CR1_CEN) });
+
+        SolenoidPwm { tim1, _pins: pins }
+    }
+
+    /// Sets the accumulator solenoids duty cycle \[0-255\]
+    pub fn set_accumulator_duty(&mut self, duty: u8) {
+        self.tim1.ccr1.write(|w| unsafe { w.bits(u32::from(duty)) });
+        self.tim1.ccr2.write(|w| unsafe { w.bits(u32::from(duty)) });
+    }
+
+    /// Sets the release solenoids duty cycle \[0-255\]
+    pub fn set_release_duty(&mut self, duty: u8) {
+        self.tim1.ccr3.write(|w| unsafe { w.bits(u32::from(duty)) });
+        self.tim1.ccr4.write(|w| unsafe { w.bits(u32::from(duty)) });
+    }
+
+    /// Turns all solenoids off
+    pub fn disable(&mut self) {
+        self.set_accumulator_duty(0);
+        self.set_release_duty(0);
+    }
+
+    /// Whether the timer is running with the outputs enabled and the
+    /// compare registers holding the given duty cycles
+    pub fn verify(&self, accumulator_duty: u8, release_duty: u8) -> bool {
+        let running = (self.tim1.cr1.read().bits() & CR1_CEN) != 0;
+        let outputs_enabled = ((self.tim1.bdtr.read().bits() & BDTR_MOE) != 0)
+            && (self.tim1.ccer.read().bits() == CCER_OUTPUTS_ENABLED);
+        let accumulator = u32::from(accumulator_duty);
+        let release = u32::from(release_duty);
+
+        running
+            && outputs_enabled
+            && (self.tim1.ccr1.read().bits() == accumulator)
+            && (self.tim1.ccr2.read().bits() == accumulator)
+            && (self.tim1.ccr3.read().bits() == release)
+            && (self.tim1.ccr4.read().bits() == release)
+    }
+}
@@ -0,0 +1,218 @@
+//! Speed control
+//!
+//! Sits above the brake and throttle modules, like the interlock, and
+//! arbitrates between their commands to reach the target vehicle speed
+//! of the latest `OxccSpeedControlCommand`, using the OBD vehicle speed.
+//!
+//! The target speed is approached along a reference speed ramped by the
+//! commanded acceleration limit. A single PID output selects the pedal,
+//! see `pedal_selection`, the pedal that is not selected is always
+//! commanded released.
+//!
+//! Speed control only runs while both the brake and throttle modules
+//! are enabled and the vehicle speed is known, the brake and throttle
+//! commands it produces replace those received over CAN, which are
+//! dropped as long as a speed target is set.
+
+use brake_module::BrakeModule;
+use nucleo_f767zi::hal::can::CanFrame;
+use num;
+use oscc_magic_byte::*;
+use oxcc_error::OxccError;
+use pedal_selection::{select_pedal, Pedal};
+use pid::Pid;
+use speed_control_can_protocol::*;
+use sys_tick;
+use throttle_module::ThrottleModule;
+use vehicle::{self, *};
+
+/// Period of the speed control loop \[ms\]
+const SPEED_CONTROL_PERIOD: u32 = 20;
+
+/// Converts an acceleration \[m/s^2\] to a speed change rate \[km/h/s\]
+const KPH_PER_SECOND_PER_METER_PER_SECOND_SQUARED: f32 = 3.6;
+
+/// Latest target from an `OxccSpeedControlCommand`
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct SpeedTarget {
+    /// \[km/h\]
+    speed: f32,
+    /// \[m/s^2\]
+    acceleration_limit: f32,
+}
+
+pub struct SpeedController {
+    target: Option<SpeedTarget>,
+    active: bool,
+    /// Ramped speed setpoint \[km/h\]
+    reference_speed: f32,
+    pedal: Pedal,
+    controller: Pid,
+    last_update: Option<u32>,
+}
+
+impl SpeedController {
+    pub fn new() -> Self {
+        let profile = vehicle::profile();
+
+        SpeedController {
+            target: None,
+            active: false,
+            reference_speed: 0.0,
+            pedal: Pedal::Released,
+            controller: Pid::new(
+                profile.speed_control_pid_gains,
+                profile.speed_control_pid_windup_guard,
+                -1.0,
+                1.0,
+            ),
+            last_update: None,
+        }
+    }
+
+    /// Drops the speed target, a new command is needed to resume
+    ///
+    /// The brake and throttle keep their last commands, this is
+    /// used alongside disabling them.
+    pub fn deactivate(&mut self) {
+        self.target = None;
+
+        if self.active {
+            info!("Speed control deactivated");
+            self.active = false;
+        }
+    }
+
+    /// Whether a speed target is set, the brake and throttle commands
+    /// received over CAN are dropped until it is released
+    pub fn is_engaged(&self) -> bool {
+        self.target.is_some()
+    }
+
+    pub fn process_rx_frame(&mut self, can_frame: &CanFrame) {
+        if let CanFrame::DataFrame(ref frame) = can_frame {
+            let id: u32 = frame.id().into();
+            let data = frame.data();
+
+            if (id == OXCC_SPEED_CONTROL_COMMAND_CAN_ID.into())
+                && (data.len() == OXCC_SPEED_CONTROL_COMMAND_CAN_DLC as usize)
+                && (data[0] == OSCC_MAGIC_BYTE_0)
+                && (data[1] == OSCC_MAGIC_BYTE_1)
+            {
+                let command = OxccSpeedControlCommand::from(frame);
+                let profile = vehicle::profile();
+
+                self.target = if command.enable {
+                    Some(SpeedTarget {
+                        speed: num::clamp(
+                            command.target_speed,
+                            0.0,
+                            profile.maximum_speed_control_target_speed,
+                        ),
+                        acceleration_limit: num::clamp(
+                            command.acceleration_limit,
+                            0.0,
+                            profile.maximum_speed_control_acceleration_limit,
+                        ),
+                    })
+                } else {
+                    None
+                };
+            }
+        }
+    }
+
+    /// Steps the speed control loop
+    ///
+    /// `vehicle_speed` is the latest OBD vehicle speed \[km/h\], `None`
+    /// if it is unknown or stale.
+    pub fn update(
+        &mut self,
+        brake: &mut BrakeModule,
+        throttle: &mut ThrottleModule,
+        vehicle_speed: Option<f32>,
+    ) -> Result<(), OxccError> {
+        let target = match self.target {
+            Some(target) => target,
+            None => {
+                if self.active {
+                    info!("Speed control released");
+                    self.active = false;
+                    return release_pedals(brake, throttle);
+                }
+
+                return Ok(());
+            }
+        };
+
+        if !brake.is_enabled() || !throttle.is_enabled() {
+            warn!("Speed control stopped, brake and throttle must be enabled");
+            self.target = None;
+            self.active = false;
+            return Ok(());
+        }
+
+        let vehicle_speed = match vehicle_speed {
+            Some(vehicle_speed) => vehicle_speed,
+            None => {
+                warn!("Speed control stopped, vehicle speed unknown");
+                self.target = None;
+                self.active = false;
+                return release_pedals(brake, throttle);
+            }
+        };
+
+        if !self.active {
+            info!("Speed control activated");
+            self.active = true;
+            self.reference_speed = vehicle_speed;
+            self.pedal = Pedal::Released;
+            self.controller.reset();
+            self.last_update = None;
+        }
+
+        if let Some(last_update) = self.last_update {
+            if sys_tick::millis_since(last_update) < SPEED_CONTROL_PERIOD {
+                return Ok(());
+            }
+        }
+
+        let now = sys_tick::millis();
+        let dt = self.last_update.map_or(0.0, |last_update| {
+            now.wrapping_sub(last_update) as f32 / 1000.0
+        });
+        self.last_update = Some(now);
+
+        let max_step = target.acceleration_limit * KPH_PER_SECOND_PER_METER_PER_SECOND_SQUARED * dt;
+        self.reference_speed +=
+            num::clamp(target.speed - self.reference_speed, -max_step, max_step);
+
+        let output = self
+            .controller
+            .update(self.reference_speed, vehicle_speed, dt);
+
+        self.pedal = select_pedal(
+            self.pedal,
+            output,
+            &vehicle::profile().speed_control_pedal_thresholds,
+        );
+
+        // the pedal being released is always commanded first
+        match self.pedal {
+            Pedal::Released => release_pedals(brake, throttle),
+            Pedal::Throttle => {
+                brake.command_position(MINIMUM_BRAKE_COMMAND)?;
+                throttle.command_position(output)
+            }
+            Pedal::Brake => {
+                throttle.command_position(MINIMUM_THROTTLE_COMMAND)?;
+                brake.command_position(-output)
+            }
+        }
+    }
+}
+
+fn release_pedals(brake: &mut BrakeModule, throttle: &mut ThrottleModule) -> Result<(), OxccError> {
+    throttle.command_position(MINIMUM_THROTTLE_COMMAND)?;
+    brake.command_position(MINIMUM_BRAKE_COMMAND)
+}
@@ -1,24 +1,42 @@
 //! Steering module
 
-use board::TorqueSensor;
-use core::fmt::Write;
+use board::{TorqueSensor, SPOOF_READBACK_VERIFICATION};
 use dac_mcp4922::DacOutput;
 use dtc::DtcBitfield;
-use dual_signal::DualSignal;
+use dual_signal::{DualSignal, SensorReading};
 use fault_can_protocol::*;
 use fault_condition::FaultCondition;
-use nucleo_f767zi::debug_console::DebugConsole;
 use nucleo_f767zi::hal::can::CanFrame;
 use nucleo_f767zi::hal::prelude::*;
 use num;
 use oscc_magic_byte::*;
+use override_can_protocol::*;
+use override_rearm::OverrideRearm;
 use oxcc_error::OxccError;
-use ranges;
+use params::{self, Param};
+use pid::Pid;
+use slew_rate_limiter::SlewRateLimiter;
 use steering_can_protocol::*;
+use sys_tick;
+use system_state;
 use types::*;
-use vehicle::*;
-
-const FILTER_ALPHA: f32 = 0.01_f32;
+use vehicle;
+
+/// Default of `Param::SteeringFilterAlpha`
+pub const FILTER_ALPHA: f32 = 0.01_f32;
+
+/// Period of the steering wheel angle control loop \[ms\]
+const ANGLE_CONTROL_PERIOD: u32 = 10;
+
+/// Source of the torque command
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum SteeringControlMode {
+    /// Torque requested by `OsccSteeringCommand`
+    Torque,
+    /// Torque produced by the steering wheel angle loop, holds
+    /// the target angle \[degrees\]
+    Angle(f32),
+}
 
 struct SteeringControlState<DTCS: DtcBitfield> {
     enabled: bool,
@@ -44,7 +62,18 @@ pub struct SteeringModule {
     steering_torque: DualSignal<TorqueSensor>,
     control_state: SteeringControlState<u8>,
     grounded_fault_state: FaultCondition<SteeringGroundedFaultTimer>,
+    override_rearm: OverrideRearm,
+    sensor_mismatch_fault_state: FaultCondition<SteeringMismatchFaultTimer>,
+    readback_fault_state: FaultCondition<SteeringReadbackFaultTimer>,
+    commanded_spoof: Option<(u16, u16)>,
+    command_slew_rate_limiter: SlewRateLimiter,
     filtered_diff: u16,
+    vehicle_speed: Option<f32>,
+    control_mode: SteeringControlMode,
+    angle_controller: Pid,
+    steering_wheel_angle: Option<f32>,
+    last_angle_update: Option<u32>,
+    applied_torque: f32,
     steering_report: OsccSteeringReport,
     fault_report: OsccFaultReport,
     steering_dac: SteeringDac,
@@ -61,13 +90,36 @@ impl UnpreparedSteeringModule {
         steering_dac: SteeringDac,
         steering_pins: SteeringPins,
         grounded_fault_timer: SteeringGroundedFaultTimer,
+        mismatch_fault_timer: SteeringMismatchFaultTimer,
+        readback_fault_timer: SteeringReadbackFaultTimer,
     ) -> Self {
+        let profile = vehicle::profile();
+
         UnpreparedSteeringModule {
             steering_module: SteeringModule {
                 steering_torque: DualSignal::new(0, 0, torque_sensor),
                 control_state: SteeringControlState::new(u8::default()),
                 grounded_fault_state: FaultCondition::new(grounded_fault_timer),
+                override_rearm: OverrideRearm::new(profile.steering_override_rearm_policy),
+                sensor_mismatch_fault_state: FaultCondition::new(mismatch_fault_timer),
+                readback_fault_state: FaultCondition::new(readback_fault_timer),
+                commanded_spoof: None,
+                command_slew_rate_limiter: SlewRateLimiter::new(
+                    profile.steering_command_slew_rate_limit,
+                    0.0,
+                ),
                 filtered_diff: 0,
+                vehicle_speed: None,
+                control_mode: SteeringControlMode::Torque,
+                angle_controller: Pid::new(
+                    profile.steering_angle_pid_gains,
+                    profile.steering_angle_pid_windup_guard,
+                    profile.minimum_torque_command,
+                    profile.maximum_torque_command,
+                ),
+                steering_wheel_angle: None,
+                last_angle_update: None,
+                applied_torque: 0.0,
                 steering_report: OsccSteeringReport::new(),
                 fault_report: OsccFaultReport {
                     fault_origin_id: FAULT_ORIGIN_STEERING,
@@ -87,7 +139,7 @@ impl UnpreparedSteeringModule {
 }
 
 impl SteeringModule {
-    pub fn disable_control(&mut self, debug_console: &mut DebugConsole) -> Result<(), OxccError> {
+    pub fn disable_control(&mut self) -> Result<(), OxccError> {
         if self.control_state.enabled {
             self.steering_torque.prevent_signal_discontinuity();
 
@@ -99,7 +151,10 @@ impl SteeringModule {
             // even if we've encountered an error, we can still disable
             self.steering_pins.spoof_enable.set_low();
             self.control_state.enabled = false;
-            writeln!(debug_console, "Steering control disabled");
+            self.commanded_spoof = None;
+            self.control_mode = SteeringControlMode::Torque;
+            self.applied_torque = 0.0;
+            info!("Steering control disabled");
 
             return if let Err(e) = result {
                 Err(OxccError::from(e))
@@ -111,7 +166,12 @@ impl SteeringModule {
         Ok(())
     }
 
-    pub fn enable_control(&mut self, debug_console: &mut DebugConsole) -> Result<(), OxccError> {
+    pub fn enable_control(&mut self) -> Result<(), OxccError> {
+        if !system_state::control_allowed() {
+            warn!("Steering enable refused, control not allowed in this system state");
+            return Ok(());
+        }
+
         if !self.control_state.enabled && !self.control_state.operator_override {
             self.steering_torque.prevent_signal_discontinuity();
 
@@ -125,7 +185,11 @@ impl SteeringModule {
             } else {
                 self.steering_pins.spoof_enable.set_high();
                 self.control_state.enabled = true;
-                writeln!(debug_console, "Steering control enabled");
+                self.commanded_spoof = None;
+                self.command_slew_rate_limiter.reset(0.0);
+                self.control_mode = SteeringControlMode::Torque;
+                self.applied_torque = 0.0;
+                info!("Steering control enabled");
                 Ok(())
             };
         }
@@ -139,20 +203,22 @@ impl SteeringModule {
         spoof_command_low: u16,
     ) -> Result<(), OxccError> {
         if self.control_state.enabled {
+            let profile = vehicle::profile();
+            let spoof_high = profile.steering_spoof_high_signal.clamp(spoof_command_high);
+            let spoof_low = profile.steering_spoof_low_signal.clamp(spoof_command_low);
+            let commanded_spoof = (spoof_high, spoof_low);
+
             // TODO - revisit this, enforce high->A, low->B
-            self.steering_dac.output_ab(
-                ranges::coerce(SteeringSpoofHighSignal::clamp(spoof_command_high)),
-                ranges::coerce(SteeringSpoofLowSignal::clamp(spoof_command_low)),
-            )?;
+            self.steering_dac
+                .output_ab(DacOutput::clamp(spoof_high), DacOutput::clamp(spoof_low))?;
+
+            self.commanded_spoof = Some(commanded_spoof);
         }
 
         Ok(())
     }
 
-    pub fn check_for_faults(
-        &mut self,
-        debug_console: &mut DebugConsole,
-    ) -> Result<Option<&OsccFaultReport>, OxccError> {
+    pub fn check_for_faults(&mut self) -> Result<Option<&OsccFaultReport>, OxccError> {
         if !self.control_state.enabled && !self.control_state.dtcs.are_any_set() {
             // Assumes this module already went through the proper transition into a faulted
             // and disabled state, and we do not want to double-report a possible duplicate
@@ -162,6 +228,8 @@ impl SteeringModule {
 
         self.steering_torque.update();
 
+        let override_threshold = params::get(Param::TorqueDifferenceOverrideThreshold) as u16;
+
         let unfiltered_diff = self.steering_torque.diff();
 
         if self.filtered_diff == 0 {
@@ -171,7 +239,7 @@ impl SteeringModule {
         // TODO - revist this
         // OSCC goes back and forth with u16 and f32 types
         self.filtered_diff = self.exponential_moving_average(
-            FILTER_ALPHA,
+            params::get(Param::SteeringFilterAlpha),
             f32::from(unfiltered_diff),
             f32::from(self.filtered_diff),
         ) as _;
@@ -180,9 +248,21 @@ impl SteeringModule {
             .grounded_fault_state
             .check_voltage_grounded(&self.steering_torque);
 
+        let inputs_implausible: bool = self.sensor_mismatch_fault_state.check_signal_implausible(
+            &self.steering_torque,
+            &vehicle::profile().torque_sensor_plausibility,
+        );
+
+        let outputs_mismatched: bool = match self.commanded_spoof {
+            Some((spoof_high, spoof_low)) if SPOOF_READBACK_VERIFICATION => self
+                .readback_fault_state
+                .check_spoof_readback(&self.steering_torque, spoof_high, spoof_low),
+            _ => self.readback_fault_state.condition_exceeded_duration(false),
+        };
+
         // sensor pins tied to ground - a value of zero indicates disconnection
         if inputs_grounded {
-            self.disable_control(debug_console)?;
+            self.disable_control()?;
 
             self.control_state
                 .dtcs
@@ -190,13 +270,36 @@ impl SteeringModule {
 
             self.update_fault_report();
 
-            writeln!(debug_console, "Bad value read from torque sensor");
+            error!("Bad value read from torque sensor");
+
+            Ok(Some(&self.fault_report))
+        } else if inputs_implausible {
+            self.disable_control()?;
+
+            self.control_state
+                .dtcs
+                .set(OSCC_STEERING_DTC_SENSOR_MISMATCH);
+
+            self.update_fault_report();
+
+            error!("Implausible value read from torque sensor");
 
             Ok(Some(&self.fault_report))
-        } else if (self.filtered_diff > TORQUE_DIFFERENCE_OVERRIDE_THRESHOLD)
-            && !self.control_state.operator_override
+        } else if outputs_mismatched {
+            self.disable_control()?;
+
+            self.control_state
+                .dtcs
+                .set(OSCC_STEERING_DTC_SPOOF_MISMATCH);
+
+            self.update_fault_report();
+
+            error!("Steering spoof output readback mismatch");
+
+            Ok(Some(&self.fault_report))
+        } else if (self.filtered_diff > override_threshold) && !self.control_state.operator_override
         {
-            self.disable_control(debug_console)?;
+            self.disable_control()?;
 
             self.control_state
                 .dtcs
@@ -205,14 +308,21 @@ impl SteeringModule {
             self.update_fault_report();
 
             self.control_state.operator_override = true;
+            self.override_rearm.override_detected();
 
-            writeln!(debug_console, "Steering operator override");
+            warn!("Steering operator override");
 
             Ok(Some(&self.fault_report))
-        } else {
+        } else if self
+            .override_rearm
+            .update(self.filtered_diff <= override_threshold)
+        {
             self.control_state.dtcs.clear_all();
             self.control_state.operator_override = false;
             Ok(None)
+        } else {
+            // overridden, waiting to be re-armed
+            Ok(None)
         }
     }
 
@@ -220,35 +330,165 @@ impl SteeringModule {
         self.fault_report.dtcs = self.control_state.dtcs;
     }
 
+    /// Updates the vehicle speed used to schedule the torque limit
+    pub fn update_vehicle_speed(&mut self, vehicle_speed: Option<f32>) {
+        self.vehicle_speed = vehicle_speed;
+    }
+
+    /// Updates the steering wheel angle and steps the angle control
+    /// loop while it is in control
+    ///
+    /// Control is disabled if the angle is unknown or stale while
+    /// the angle loop is in control.
+    pub fn update_steering_wheel_angle(
+        &mut self,
+        steering_wheel_angle: Option<f32>,
+    ) -> Result<(), OxccError> {
+        self.steering_wheel_angle = steering_wheel_angle;
+
+        let target_angle = match self.control_mode {
+            SteeringControlMode::Angle(target_angle) => target_angle,
+            SteeringControlMode::Torque => return Ok(()),
+        };
+
+        let angle = match steering_wheel_angle {
+            Some(angle) => angle,
+            None => {
+                warn!("Steering wheel angle is stale");
+                return self.disable_control();
+            }
+        };
+
+        if let Some(last_angle_update) = self.last_angle_update {
+            if sys_tick::millis_since(last_angle_update) < ANGLE_CONTROL_PERIOD {
+                return Ok(());
+            }
+        }
+
+        let now = sys_tick::millis();
+        let dt = self.last_angle_update.map_or(0.0, |last_angle_update| {
+            now.wrapping_sub(last_angle_update) as f32 / 1000.0
+        });
+        self.last_angle_update = Some(now);
+
+        let torque = self.angle_controller.update(target_angle, angle, dt);
+
+        self.apply_torque(torque)
+    }
+
+    /// Maximum torque command magnitude at the current vehicle speed
+    ///
+    /// Falls back to the most conservative limit if the vehicle speed
+    /// is unknown.
+    fn speed_scheduled_torque_limit(&self) -> f32 {
+        let profile = vehicle::profile();
+        let schedule = profile.steering_torque_speed_schedule;
+
+        let speed = match self.vehicle_speed {
+            Some(speed) => speed,
+            None => {
+                return schedule.iter().fold(
+                    profile.maximum_torque_command,
+                    |limit, &(_, torque)| {
+                        if torque < limit {
+                            torque
+                        } else {
+                            limit
+                        }
+                    },
+                );
+            }
+        };
+
+        if speed <= schedule[0].0 {
+            return schedule[0].1;
+        }
+
+        for window in schedule.windows(2) {
+            let (speed_a, torque_a) = window[0];
+            let (speed_b, torque_b) = window[1];
+
+            if speed <= speed_b {
+                return torque_a
+                    + ((speed - speed_a) / (speed_b - speed_a)) * (torque_b - torque_a);
+            }
+        }
+
+        schedule[schedule.len() - 1].1
+    }
+
     fn exponential_moving_average(&self, alpha: f32, input: f32, average: f32) -> f32 {
         (alpha * input) + ((1.0 - alpha) * average)
     }
 
+    /// Power-on self test of the sensor idle readings
+    pub fn self_test_sensor(&mut self) -> bool {
+        self.steering_torque.update();
+
+        vehicle::profile()
+            .torque_sensor_plausibility
+            .is_plausible(self.steering_torque.high(), self.steering_torque.low())
+    }
+
+    /// Commanded DAC outputs (high, low), `None` while not spoofing
+    pub fn commanded_spoof(&self) -> Option<(u16, u16)> {
+        self.commanded_spoof
+    }
+
+    /// Reads the torque sensor
+    pub fn read_sensor(&mut self) -> SensorReading {
+        self.steering_torque.update();
+        self.steering_torque
+            .reading(&vehicle::profile().torque_sensor_plausibility)
+    }
+
+    /// Power-on self test of the DAC, the sensor readings are passed through
+    /// as done when control is disabled
+    pub fn self_test_dac(&mut self) -> bool {
+        self.steering_dac
+            .output_ab(
+                DacOutput::clamp(self.steering_torque.low()),
+                DacOutput::clamp(self.steering_torque.high()),
+            )
+            .is_ok()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.control_state.enabled
+    }
+
     pub fn supply_steering_report(&mut self) -> &OsccSteeringReport {
         self.steering_report.enabled = self.control_state.enabled;
         self.steering_report.operator_override = self.control_state.operator_override;
+        self.steering_report.override_rearm_state = self.override_rearm.state().into();
         self.steering_report.dtcs = self.control_state.dtcs;
+        self.steering_report.slew_rate_limited = self.command_slew_rate_limiter.is_active();
+        self.steering_report.angle_control = self.control_mode != SteeringControlMode::Torque;
         &self.steering_report
     }
 
-    pub fn process_rx_frame(
-        &mut self,
-        can_frame: &CanFrame,
-        debug_console: &mut DebugConsole,
-    ) -> Result<(), OxccError> {
+    pub fn process_rx_frame(&mut self, can_frame: &CanFrame) -> Result<(), OxccError> {
         if let CanFrame::DataFrame(ref frame) = can_frame {
             let id: u32 = frame.id().into();
             let data = frame.data();
 
             if (data[0] == OSCC_MAGIC_BYTE_0) && (data[1] == OSCC_MAGIC_BYTE_1) {
                 if id == OSCC_STEERING_ENABLE_CAN_ID.into() {
-                    self.enable_control(debug_console)?;
+                    self.enable_control()?;
                 } else if id == OSCC_STEERING_DISABLE_CAN_ID.into() {
-                    self.disable_control(debug_console)?;
+                    self.disable_control()?;
                 } else if id == OSCC_STEERING_COMMAND_CAN_ID.into() {
                     self.process_steering_command(&OsccSteeringCommand::from(frame))?;
+                } else if id == OXCC_STEERING_ANGLE_COMMAND_CAN_ID.into() {
+                    self.process_steering_angle_command(&OxccSteeringAngleCommand::from(frame));
+                } else if id == OXCC_OVERRIDE_ACKNOWLEDGE_CAN_ID.into() {
+                    if (data.len() == OXCC_OVERRIDE_ACKNOWLEDGE_CAN_DLC as usize)
+                        && OxccOverrideAcknowledge::from(frame).acknowledges(FAULT_ORIGIN_STEERING)
+                    {
+                        self.override_rearm.acknowledge();
+                    }
                 } else if id == OSCC_FAULT_REPORT_CAN_ID.into() {
-                    self.process_fault_report(&OsccFaultReport::from(frame), debug_console)?;
+                    self.process_fault_report(&OsccFaultReport::from(frame))?;
                 }
             }
         }
@@ -256,41 +496,93 @@ impl SteeringModule {
         Ok(())
     }
 
-    fn process_fault_report(
-        &mut self,
-        fault_report: &OsccFaultReport,
-        debug_console: &mut DebugConsole,
-    ) -> Result<(), OxccError> {
-        writeln!(
-            debug_console,
+    fn process_fault_report(&mut self, fault_report: &OsccFaultReport) -> Result<(), OxccError> {
+        warn!(
             "Fault report received from: {} DTCs: {}",
             fault_report.fault_origin_id, fault_report.dtcs
         );
 
-        self.disable_control(debug_console)
+        self.disable_control()
     }
 
     fn process_steering_command(&mut self, command: &OsccSteeringCommand) -> Result<(), OxccError> {
+        self.control_mode = SteeringControlMode::Torque;
+
+        self.apply_torque(command.torque_request * vehicle::profile().maximum_torque_command)
+    }
+
+    /// Hands the torque command over to the angle loop, bumpless
+    /// from the torque currently applied
+    fn process_steering_angle_command(&mut self, command: &OxccSteeringAngleCommand) {
+        if !self.control_state.enabled {
+            return;
+        }
+
+        let maximum_angle = vehicle::profile().maximum_steering_angle_command;
+        let target_angle = num::clamp(command.angle_request, -maximum_angle, maximum_angle);
+
+        if let Some(angle) = self.steering_wheel_angle {
+            if self.control_mode == SteeringControlMode::Torque {
+                self.angle_controller
+                    .initialize(target_angle, angle, self.applied_torque);
+                self.last_angle_update = None;
+            }
+
+            self.control_mode = SteeringControlMode::Angle(target_angle);
+        } else {
+            warn!("Steering angle command ignored, steering wheel angle unknown");
+        }
+    }
+
+    /// Applies a torque command through the torque clamps, the speed
+    /// schedule and the slew rate limit
+    fn apply_torque(&mut self, torque: f32) -> Result<(), OxccError> {
+        let profile = vehicle::profile();
         let clamped_torque = num::clamp(
-            command.torque_request * MAXIMUM_TORQUE_COMMAND,
-            MINIMUM_TORQUE_COMMAND,
-            MAXIMUM_TORQUE_COMMAND,
+            torque,
+            profile.minimum_torque_command,
+            profile.maximum_torque_command,
         );
 
-        let spoof_voltage_low: f32 = num::clamp(
-            steering_torque_to_volts_low(clamped_torque),
-            STEERING_SPOOF_LOW_SIGNAL_VOLTAGE_MIN,
-            STEERING_SPOOF_LOW_SIGNAL_VOLTAGE_MAX,
-        );
+        let torque_limit = self.speed_scheduled_torque_limit();
 
-        let spoof_voltage_high: f32 = num::clamp(
-            steering_torque_to_volts_high(clamped_torque),
-            STEERING_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN,
-            STEERING_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX,
-        );
+        let clamped_torque = num::clamp(clamped_torque, -torque_limit, torque_limit);
+
+        let clamped_torque = self.command_slew_rate_limiter.limit(clamped_torque);
+
+        self.output_torque(clamped_torque)
+    }
+
+    /// Steps the slew rate limited torque towards the last command,
+    /// called every main loop iteration
+    pub fn update_command(&mut self) -> Result<(), OxccError> {
+        if !self.control_state.enabled {
+            return Ok(());
+        }
+
+        let was_limited = self.command_slew_rate_limiter.is_active();
+        let torque = self.command_slew_rate_limiter.update();
+
+        if was_limited {
+            self.output_torque(torque)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn output_torque(&mut self, clamped_torque: f32) -> Result<(), OxccError> {
+        let profile = vehicle::profile();
+
+        if self.control_state.enabled {
+            self.applied_torque = clamped_torque;
+        }
 
-        let spoof_value_low = (STEPS_PER_VOLT * spoof_voltage_low) as u16;
-        let spoof_value_high = (STEPS_PER_VOLT * spoof_voltage_high) as u16;
+        let spoof_value_low = profile
+            .steering_spoof_low_signal
+            .position_to_steps(clamped_torque);
+        let spoof_value_high = profile
+            .steering_spoof_high_signal
+            .position_to_steps(clamped_torque);
 
         self.update_steering(spoof_value_high, spoof_value_low)
     }
@@ -0,0 +1,128 @@
+//! Software task supervisor
+//!
+//! Each supervised activity of the main loop must check in within
+//! its deadline.
+//! The watchdogs are serviced once per main loop iteration, but only
+//! while every activity is healthy, otherwise the IWDG and WWDG are
+//! left to reset the system.
+//! The main loop is paced to `MAIN_LOOP_PERIOD`, which keeps the WWDG
+//! refreshes inside its window.
+//!
+//! The pacing makes an early refresh impossible, so the WWDG does not
+//! detect a loop running too fast, only one running too slow: the
+//! main loop period is bounded to the ~19 ms WWDG timeout. The early
+//! window only catches a refresh from outside the paced loop.
+
+use board::CAN_PUBLISH_HZ;
+use nucleo_f767zi::hal::iwdg::Iwdg;
+use nucleo_f767zi::hal::stm32f7x7::IWDG;
+use sys_tick;
+use wwdg::Wwdg;
+
+/// Main loop period, must stay inside the WWDG window. \[ms\]
+///
+/// Measured in SysTick milliseconds, an iteration starting late in a
+/// millisecond still waits for more than `MAIN_LOOP_PERIOD - 1` ms.
+const MAIN_LOOP_PERIOD: u32 = 2;
+
+/// Supervised activities
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Activity {
+    ControlCanRx,
+    FaultChecks,
+    ObdRepublish,
+    ReportPublish,
+}
+
+const ACTIVITIES: [Activity; 4] = [
+    Activity::ControlCanRx,
+    Activity::FaultChecks,
+    Activity::ObdRepublish,
+    Activity::ReportPublish,
+];
+
+impl Activity {
+    fn index(self) -> usize {
+        match self {
+            Activity::ControlCanRx => 0,
+            Activity::FaultChecks => 1,
+            Activity::ObdRepublish => 2,
+            Activity::ReportPublish => 3,
+        }
+    }
+
+    /// Maximum time between check-ins. \[ms\]
+    fn deadline(self) -> u32 {
+        match self {
+            Activity::ControlCanRx => 20,
+            Activity::FaultChecks => 20,
+            Activity::ObdRepublish => 20,
+            Activity::ReportPublish => 2 * (1000 / CAN_PUBLISH_HZ),
+        }
+    }
+}
+
+pub struct Supervisor {
+    check_ins: [u32; 4],
+    iteration_start: u32,
+    overdue: Option<Activity>,
+}
+
+impl Supervisor {
+    /// All activities start out as checked in
+    pub fn new() -> Self {
+        let now = sys_tick::millis();
+
+        Supervisor {
+            check_ins: [now; 4],
+            iteration_start: now,
+            overdue: None,
+        }
+    }
+
+    pub fn check_in(&mut self, activity: Activity) {
+        self.check_ins[activity.index()] = sys_tick::millis();
+    }
+
+    /// First activity which missed its deadline
+    pub fn overdue_activity(&self) -> Option<Activity> {
+        ACTIVITIES
+            .iter()
+            .find(|a| sys_tick::millis_since(self.check_ins[a.index()]) > a.deadline())
+            .cloned()
+    }
+
+    /// Waits until `MAIN_LOOP_PERIOD` has passed since the start of the
+    /// previous main loop iteration
+    ///
+    /// An iteration which overran its period starts right away.
+    pub fn wait_for_next_iteration(&mut self) {
+        while sys_tick::millis_since(self.iteration_start) < MAIN_LOOP_PERIOD {}
+
+        self.iteration_start = sys_tick::millis();
+    }
+
+    /// Services the watchdogs once per main loop iteration while every
+    /// activity is healthy
+    ///
+    /// Returns the overdue activity once, when it is first detected.
+    pub fn service_watchdogs(
+        &mut self,
+        iwdg: &mut Iwdg<IWDG>,
+        wwdg: &mut Wwdg,
+    ) -> Option<Activity> {
+        if self.overdue.is_some() {
+            return None;
+        }
+
+        if let Some(activity) = self.overdue_activity() {
+            self.overdue = Some(activity);
+            return self.overdue;
+        }
+
+        iwdg.refresh();
+        wwdg.refresh();
+
+        None
+    }
+}
@@ -0,0 +1,37 @@
+//! SysTick monotonic clock
+//!
+//! The SysTick exception fires at `SYS_TICK_HZ` and increments
+//! a millisecond counter which wraps around roughly every 49 days.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use cortex_m::peripheral::syst::SystClkSource;
+use cortex_m::peripheral::SYST;
+
+/// SysTick exception rate
+pub const SYS_TICK_HZ: u32 = 1000;
+
+static MILLIS: AtomicUsize = AtomicUsize::new(0);
+
+/// Configures and starts the SysTick exception
+pub fn start(mut syst: SYST, sysclk_hz: u32) {
+    syst.set_clock_source(SystClkSource::Core);
+    syst.set_reload((sysclk_hz / SYS_TICK_HZ) - 1);
+    syst.clear_current();
+    syst.enable_counter();
+    syst.enable_interrupt();
+}
+
+/// Called from the SysTick exception handler
+pub fn tick() {
+    MILLIS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Milliseconds since `start`
+pub fn millis() -> u32 {
+    MILLIS.load(Ordering::Relaxed) as u32
+}
+
+/// Milliseconds elapsed since the `earlier` timestamp
+pub fn millis_since(earlier: u32) -> u32 {
+    millis().wrapping_sub(earlier)
+}
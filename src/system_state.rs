@@ -0,0 +1,159 @@
+//! System safety state machine
+//!
+//! - `Boot` goes to `Faulted` after an unexpected reset, otherwise `SelfTest`
+//! - `Ready`, `Active` and `Degraded` go to `SafeStop` on a module fault or
+//!   runtime error, `SafeStop` goes to `Faulted` once all control is disabled
+//! - `Faulted` only leaves through a clear faults command, which re-runs the
+//!   self test
+//! - `Degraded` is entered while the control CAN bus is unhealthy
+//!
+//! Control can only be enabled in the `Ready` and `Active` states, the
+//! modules check `control_allowed` before enabling. Entering `Degraded`,
+//! `SafeStop` or `Faulted` disables all control.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// `SystemStateMachine::control_allowed` of the latest state
+static CONTROL_ALLOWED: AtomicBool = AtomicBool::new(false);
+
+/// Whether modules may accept enable requests in the current system state
+pub fn control_allowed() -> bool {
+    CONTROL_ALLOWED.load(Ordering::Relaxed)
+}
+
+/// System states
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SystemState {
+    Boot,
+    SelfTest,
+    Ready,
+    Active,
+    Degraded,
+    Faulted,
+    SafeStop,
+}
+
+/// Events driving the system state transitions
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SystemEvent {
+    /// Initialization completed, the reset cause is known
+    BootComplete {
+        unexpected_reset: bool,
+    },
+    SelfTestPassed,
+    SelfTestFailed,
+    /// At least one module has control enabled
+    ControlEnabled,
+    /// No module has control enabled
+    ControlDisabled,
+    /// A module detected a fault
+    ModuleFault,
+    /// Runtime error, see `handle_error`
+    Error,
+    ControlCanDegraded,
+    ControlCanRecovered,
+    /// Clear faults command received
+    ClearFaults,
+}
+
+pub struct SystemStateMachine {
+    state: SystemState,
+    control_enabled: bool,
+    control_can_degraded: bool,
+}
+
+impl SystemStateMachine {
+    pub const fn new() -> Self {
+        SystemStateMachine {
+            state: SystemState::Boot,
+            control_enabled: false,
+            control_can_degraded: false,
+        }
+    }
+
+    pub fn state(&self) -> SystemState {
+        self.state
+    }
+
+    /// Whether modules may accept enable requests
+    pub fn control_allowed(&self) -> bool {
+        (self.state == SystemState::Ready) || (self.state == SystemState::Active)
+    }
+
+    /// Handles an event, returns the new state on a transition
+    pub fn handle(&mut self, event: SystemEvent) -> Option<SystemState> {
+        match event {
+            SystemEvent::ControlEnabled => self.control_enabled = true,
+            SystemEvent::ControlDisabled => self.control_enabled = false,
+            SystemEvent::ControlCanDegraded => self.control_can_degraded = true,
+            SystemEvent::ControlCanRecovered => self.control_can_degraded = false,
+            _ => (),
+        }
+
+        let next_state = self.next_state(event);
+
+        if next_state != self.state {
+            self.state = next_state;
+            CONTROL_ALLOWED.store(self.control_allowed(), Ordering::Relaxed);
+            Some(next_state)
+        } else {
+            None
+        }
+    }
+
+    fn next_state(&self, event: SystemEvent) -> SystemState {
+        use self::SystemEvent::*;
+        use self::SystemState::*;
+
+        match (self.state, event) {
+            (Boot, BootComplete { unexpected_reset }) => {
+                if unexpected_reset {
+                    Faulted
+                } else {
+                    SelfTest
+                }
+            }
+            (Boot, _) => Boot,
+
+            (SelfTest, SelfTestPassed) => self.operational_state(),
+            (SelfTest, SelfTestFailed) => Faulted,
+            (SelfTest, _) => SelfTest,
+
+            (Faulted, ClearFaults) => SelfTest,
+            (Faulted, _) => Faulted,
+
+            (SafeStop, ControlDisabled) => Faulted,
+            (SafeStop, _) => SafeStop,
+
+            (_, ModuleFault) | (_, Error) => SafeStop,
+
+            (Ready, _) | (Active, _) | (Degraded, _) => self.operational_state(),
+        }
+    }
+
+    /// State while operating normally, based on the control CAN health
+    /// and whether any control is enabled
+    fn operational_state(&self) -> SystemState {
+        if self.control_can_degraded {
+            SystemState::Degraded
+        } else if self.control_enabled {
+            SystemState::Active
+        } else {
+            SystemState::Ready
+        }
+    }
+}
+
+impl From<SystemState> for u8 {
+    fn from(s: SystemState) -> u8 {
+        match s {
+            SystemState::Boot => 0,
+            SystemState::SelfTest => 1,
+            SystemState::Ready => 2,
+            SystemState::Active => 3,
+            SystemState::Degraded => 4,
+            SystemState::Faulted => 5,
+            SystemState::SafeStop => 6,
+        }
+    }
+}
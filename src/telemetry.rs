@@ -0,0 +1,169 @@
+//! Binary telemetry stream
+//!
+//! While `Param::TelemetryPeriod` is non-zero, `telemetry_protocol`
+//! records are streamed on the debug console: a system record and a
+//! record per control module every period, and an event record for each
+//! change of the system state, module enables, operator overrides and DTCs.
+//!
+//! The frames are queued on the `console` along with the log messages and
+//! the debug shell output, whole or not at all, so the stream never blocks
+//! the control loop. Records which do not fit are dropped and counted in
+//! the system record.
+//! Decode a capture with `host/oxcc-telemetry`.
+
+use console;
+use core::mem;
+use dual_signal::SensorReading;
+use params::{self, Param, ParamLimits};
+use sys_tick;
+use telemetry_protocol::*;
+use vehicle;
+
+/// Default of `Param::TelemetryPeriod`, the stream is off \[ms\]
+pub const TELEMETRY_PERIOD: f32 = 0.0;
+
+/// Hard limits of `Param::TelemetryPeriod` \[ms\]
+pub const TELEMETRY_PERIOD_LIMITS: ParamLimits = ParamLimits {
+    min: 0.0,
+    max: 1000.0,
+};
+
+const MODULE_COUNT: usize = 3;
+
+/// Module state as last seen
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct ModuleState {
+    enabled: bool,
+    operator_override: bool,
+    dtcs: u8,
+}
+
+pub struct Telemetry {
+    period_start: u32,
+    system_state: Option<u8>,
+    modules: [Option<ModuleState>; MODULE_COUNT],
+    dropped_records: u32,
+}
+
+impl Telemetry {
+    pub fn new() -> Self {
+        Telemetry {
+            period_start: sys_tick::millis(),
+            system_state: None,
+            modules: [None; MODULE_COUNT],
+            dropped_records: 0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        period() != 0
+    }
+
+    /// Whether the next snapshot is due, restarts the period
+    pub fn snapshot_due(&mut self) -> bool {
+        let period = period();
+
+        if (period == 0) || (sys_tick::millis_since(self.period_start) < period) {
+            return false;
+        }
+
+        self.period_start = sys_tick::millis();
+        true
+    }
+
+    /// Publishes an event when the system state changed
+    pub fn track_system_state(&mut self, state: u8) {
+        let previous = mem::replace(&mut self.system_state, Some(state));
+
+        if previous.map_or(false, |previous| previous != state) {
+            self.publish_event(TELEMETRY_EVENT_SYSTEM_STATE, 0, state);
+        }
+    }
+
+    /// Publishes events for the changes of a module
+    pub fn track_module(&mut self, module: u8, enabled: bool, operator_override: bool, dtcs: u8) {
+        let state = ModuleState {
+            enabled,
+            operator_override,
+            dtcs,
+        };
+
+        if let Some(previous) = mem::replace(&mut self.modules[usize::from(module)], Some(state)) {
+            if previous.enabled != enabled {
+                self.publish_event(TELEMETRY_EVENT_MODULE_ENABLED, module, enabled as _);
+            }
+            if previous.operator_override != operator_override {
+                self.publish_event(
+                    TELEMETRY_EVENT_OPERATOR_OVERRIDE,
+                    module,
+                    operator_override as _,
+                );
+            }
+            if previous.dtcs != dtcs {
+                self.publish_event(TELEMETRY_EVENT_DTCS, module, dtcs);
+            }
+        }
+    }
+
+    pub fn publish_system(&mut self, state: u8, control_allowed: bool) {
+        let record = TelemetryRecord::System(SystemTelemetry {
+            timestamp: sys_tick::millis(),
+            state,
+            control_allowed,
+            vehicle_profile: vehicle::profile().id,
+            dropped_records: self.dropped_records,
+        });
+        self.publish(&record);
+    }
+
+    /// Publishes a module snapshot, along with its state as last tracked
+    pub fn publish_module(
+        &mut self,
+        module: u8,
+        reading: &SensorReading,
+        spoof: Option<(u16, u16)>,
+    ) {
+        let state = self.modules[usize::from(module)].unwrap_or(ModuleState {
+            enabled: false,
+            operator_override: false,
+            dtcs: 0,
+        });
+
+        let record = TelemetryRecord::Module(ModuleTelemetry {
+            timestamp: sys_tick::millis(),
+            module,
+            enabled: state.enabled,
+            operator_override: state.operator_override,
+            dtcs: state.dtcs,
+            sensor_high: reading.high,
+            sensor_low: reading.low,
+            sensor_plausible: reading.plausible,
+            spoof,
+        });
+        self.publish(&record);
+    }
+
+    fn publish_event(&mut self, event: u8, source: u8, value: u8) {
+        let record = TelemetryRecord::Event(EventTelemetry {
+            timestamp: sys_tick::millis(),
+            event,
+            source,
+            value,
+        });
+        self.publish(&record);
+    }
+
+    fn publish(&mut self, record: &TelemetryRecord) {
+        let mut frame = [0; TELEMETRY_MAX_FRAME_LEN];
+        let frame_len = record.encode(&mut frame);
+
+        if !console::write_all(&frame[..frame_len]) {
+            self.dropped_records = self.dropped_records.saturating_add(1);
+        }
+    }
+}
+
+/// Current record period, 0 while the stream is off \[ms\]
+fn period() -> u32 {
+    params::get(Param::TelemetryPeriod) as u32
+}
@@ -0,0 +1,352 @@
+//! OxCC binary telemetry protocol
+//!
+//! Each record is a little-endian payload followed by its CRC, COBS
+//! encoded and enclosed in zero bytes, so a decoder can pick up the
+//! stream at any frame boundary and skip anything else written to the
+//! UART in between.
+//!
+//! | payload | CRC-16/CCITT-FALSE of the payload, little-endian |
+//!
+//! Every payload starts with the record type and a timestamp
+//! (`sys_tick::millis`), see the record structs for the rest.
+//!
+//! Shared with the host decoder in `host/oxcc-telemetry`,
+//! only depends on `core`.
+
+// the firmware only encodes
+#![allow(dead_code)]
+
+/// `SystemTelemetry`
+pub const TELEMETRY_RECORD_SYSTEM: u8 = 0;
+/// `ModuleTelemetry`
+pub const TELEMETRY_RECORD_MODULE: u8 = 1;
+/// `EventTelemetry`
+pub const TELEMETRY_RECORD_EVENT: u8 = 2;
+
+pub const TELEMETRY_MODULE_BRAKE: u8 = 0;
+pub const TELEMETRY_MODULE_THROTTLE: u8 = 1;
+pub const TELEMETRY_MODULE_STEERING: u8 = 2;
+
+/// The system state changed, the value is the new state
+pub const TELEMETRY_EVENT_SYSTEM_STATE: u8 = 0;
+/// A module was enabled (1) or disabled (0)
+pub const TELEMETRY_EVENT_MODULE_ENABLED: u8 = 1;
+/// An operator override was detected (1) or cleared (0)
+pub const TELEMETRY_EVENT_OPERATOR_OVERRIDE: u8 = 2;
+/// The DTCs of a module changed, the value is the new DTC bitfield
+pub const TELEMETRY_EVENT_DTCS: u8 = 3;
+
+/// Largest payload, a `ModuleTelemetry` record
+pub const TELEMETRY_MAX_PAYLOAD_LEN: usize = 16;
+
+/// Largest frame, the payload and CRC plus the COBS overhead byte
+/// and the two zero delimiters
+pub const TELEMETRY_MAX_FRAME_LEN: usize = TELEMETRY_MAX_PAYLOAD_LEN + 5;
+
+const SYSTEM_PAYLOAD_LEN: usize = 12;
+const MODULE_PAYLOAD_LEN: usize = 16;
+const EVENT_PAYLOAD_LEN: usize = 8;
+const CRC_LEN: usize = 2;
+
+const MODULE_FLAG_ENABLED: u8 = 1 << 0;
+const MODULE_FLAG_OPERATOR_OVERRIDE: u8 = 1 << 1;
+const MODULE_FLAG_SENSOR_PLAUSIBLE: u8 = 1 << 2;
+const MODULE_FLAG_SPOOF_ACTIVE: u8 = 1 << 3;
+
+/// System state snapshot, sent once per telemetry period
+///
+/// | type | timestamp u32 | state | control_allowed | vehicle_profile | dropped_records u32 |
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SystemTelemetry {
+    pub timestamp: u32,
+    /// See `system_state::SystemState`
+    pub state: u8,
+    pub control_allowed: bool,
+    pub vehicle_profile: u8,
+    /// Records which did not fit into the output buffer since boot
+    pub dropped_records: u32,
+}
+
+/// Control module snapshot, sent for each module once per telemetry period
+///
+/// | type | timestamp u32 | module | flags | dtcs | sensor_high u16 | sensor_low u16 |
+/// spoof_high u16 | spoof_low u16 |
+///
+/// The flags are enabled (bit 0), operator override (bit 1),
+/// sensor plausible (bit 2) and spoof active (bit 3).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ModuleTelemetry {
+    pub timestamp: u32,
+    /// One of the `TELEMETRY_MODULE_*` IDs
+    pub module: u8,
+    pub enabled: bool,
+    pub operator_override: bool,
+    pub dtcs: u8,
+    /// Sensor readings \[steps\]
+    pub sensor_high: u16,
+    pub sensor_low: u16,
+    pub sensor_plausible: bool,
+    /// Commanded DAC outputs (high, low) \[steps\], `None` while not spoofing
+    pub spoof: Option<(u16, u16)>,
+}
+
+/// A change, sent as soon as it is seen
+///
+/// | type | timestamp u32 | event | source | value |
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EventTelemetry {
+    pub timestamp: u32,
+    /// One of the `TELEMETRY_EVENT_*` codes
+    pub event: u8,
+    /// The `TELEMETRY_MODULE_*` ID of module events, 0 otherwise
+    pub source: u8,
+    pub value: u8,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TelemetryRecord {
+    System(SystemTelemetry),
+    Module(ModuleTelemetry),
+    Event(EventTelemetry),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TelemetryDecodeError {
+    /// Not a valid COBS frame, or too long for any record
+    Framing,
+    Crc,
+    UnknownRecord(u8),
+    /// The payload length does not match the record type
+    Length,
+}
+
+impl TelemetryRecord {
+    pub fn timestamp(&self) -> u32 {
+        match self {
+            TelemetryRecord::System(record) => record.timestamp,
+            TelemetryRecord::Module(record) => record.timestamp,
+            TelemetryRecord::Event(record) => record.timestamp,
+        }
+    }
+
+    /// Encodes the record into a complete frame, including the
+    /// zero delimiters, returns the frame length
+    pub fn encode(&self, frame: &mut [u8; TELEMETRY_MAX_FRAME_LEN]) -> usize {
+        let mut payload = [0; TELEMETRY_MAX_PAYLOAD_LEN + CRC_LEN];
+
+        let payload_len = match self {
+            TelemetryRecord::System(record) => {
+                payload[0] = TELEMETRY_RECORD_SYSTEM;
+                write_u32(&mut payload[1..5], record.timestamp);
+                payload[5] = record.state;
+                payload[6] = record.control_allowed as _;
+                payload[7] = record.vehicle_profile;
+                write_u32(&mut payload[8..12], record.dropped_records);
+                SYSTEM_PAYLOAD_LEN
+            }
+            TelemetryRecord::Module(record) => {
+                let mut flags = 0;
+                if record.enabled {
+                    flags |= MODULE_FLAG_ENABLED;
+                }
+                if record.operator_override {
+                    flags |= MODULE_FLAG_OPERATOR_OVERRIDE;
+                }
+                if record.sensor_plausible {
+                    flags |= MODULE_FLAG_SENSOR_PLAUSIBLE;
+                }
+                if record.spoof.is_some() {
+                    flags |= MODULE_FLAG_SPOOF_ACTIVE;
+                }
+                let (spoof_high, spoof_low) = record.spoof.unwrap_or((0, 0));
+
+                payload[0] = TELEMETRY_RECORD_MODULE;
+                write_u32(&mut payload[1..5], record.timestamp);
+                payload[5] = record.module;
+                payload[6] = flags;
+                payload[7] = record.dtcs;
+                write_u16(&mut payload[8..10], record.sensor_high);
+                write_u16(&mut payload[10..12], record.sensor_low);
+                write_u16(&mut payload[12..14], spoof_high);
+                write_u16(&mut payload[14..16], spoof_low);
+                MODULE_PAYLOAD_LEN
+            }
+            TelemetryRecord::Event(record) => {
+                payload[0] = TELEMETRY_RECORD_EVENT;
+                write_u32(&mut payload[1..5], record.timestamp);
+                payload[5] = record.event;
+                payload[6] = record.source;
+                payload[7] = record.value;
+                EVENT_PAYLOAD_LEN
+            }
+        };
+
+        let crc = crc16(&payload[..payload_len]);
+        write_u16(&mut payload[payload_len..payload_len + CRC_LEN], crc);
+
+        frame[0] = 0;
+        let encoded_len = cobs_encode(&payload[..payload_len + CRC_LEN], &mut frame[1..]);
+        frame[encoded_len + 1] = 0;
+
+        encoded_len + 2
+    }
+
+    /// Decodes a frame, without its zero delimiters
+    pub fn decode(frame: &[u8]) -> Result<TelemetryRecord, TelemetryDecodeError> {
+        let mut payload = [0; TELEMETRY_MAX_PAYLOAD_LEN + CRC_LEN];
+
+        let len = cobs_decode(frame, &mut payload).ok_or(TelemetryDecodeError::Framing)?;
+        if len <= CRC_LEN {
+            return Err(TelemetryDecodeError::Framing);
+        }
+
+        let payload_len = len - CRC_LEN;
+        if crc16(&payload[..payload_len]) != read_u16(&payload[payload_len..len]) {
+            return Err(TelemetryDecodeError::Crc);
+        }
+
+        let expected_len = match payload[0] {
+            TELEMETRY_RECORD_SYSTEM => SYSTEM_PAYLOAD_LEN,
+            TELEMETRY_RECORD_MODULE => MODULE_PAYLOAD_LEN,
+            TELEMETRY_RECORD_EVENT => EVENT_PAYLOAD_LEN,
+            record_type => return Err(TelemetryDecodeError::UnknownRecord(record_type)),
+        };
+        if payload_len != expected_len {
+            return Err(TelemetryDecodeError::Length);
+        }
+
+        let timestamp = read_u32(&payload[1..5]);
+
+        let record = match payload[0] {
+            TELEMETRY_RECORD_SYSTEM => TelemetryRecord::System(SystemTelemetry {
+                timestamp,
+                state: payload[5],
+                control_allowed: payload[6] != 0,
+                vehicle_profile: payload[7],
+                dropped_records: read_u32(&payload[8..12]),
+            }),
+            TELEMETRY_RECORD_MODULE => {
+                let flags = payload[6];
+
+                TelemetryRecord::Module(ModuleTelemetry {
+                    timestamp,
+                    module: payload[5],
+                    enabled: (flags & MODULE_FLAG_ENABLED) != 0,
+                    operator_override: (flags & MODULE_FLAG_OPERATOR_OVERRIDE) != 0,
+                    dtcs: payload[7],
+                    sensor_high: read_u16(&payload[8..10]),
+                    sensor_low: read_u16(&payload[10..12]),
+                    sensor_plausible: (flags & MODULE_FLAG_SENSOR_PLAUSIBLE) != 0,
+                    spoof: if (flags & MODULE_FLAG_SPOOF_ACTIVE) != 0 {
+                        Some((read_u16(&payload[12..14]), read_u16(&payload[14..16])))
+                    } else {
+                        None
+                    },
+                })
+            }
+            _ => TelemetryRecord::Event(EventTelemetry {
+                timestamp,
+                event: payload[5],
+                source: payload[6],
+                value: payload[7],
+            }),
+        };
+
+        Ok(record)
+    }
+}
+
+/// CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xFFFF,
+/// no reflection, no final XOR)
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+
+    for &byte in data {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if (crc & 0x8000) != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+/// COBS encodes `data`, which must be shorter than 254 bytes,
+/// returns the encoded length
+fn cobs_encode(data: &[u8], encoded: &mut [u8]) -> usize {
+    let mut code_index = 0;
+    let mut len = 1;
+    let mut code = 1;
+
+    for &byte in data {
+        if byte == 0 {
+            encoded[code_index] = code;
+            code_index = len;
+            len += 1;
+            code = 1;
+        } else {
+            encoded[len] = byte;
+            len += 1;
+            code += 1;
+        }
+    }
+    encoded[code_index] = code;
+
+    len
+}
+
+/// COBS decodes a frame without its delimiter, `None` if it is
+/// malformed or does not fit
+fn cobs_decode(encoded: &[u8], data: &mut [u8]) -> Option<usize> {
+    let mut index = 0;
+    let mut len = 0;
+
+    while index < encoded.len() {
+        let code = usize::from(encoded[index]);
+        if (code == 0) || (index + code > encoded.len()) {
+            return None;
+        }
+        index += 1;
+
+        for _ in 1..code {
+            *data.get_mut(len)? = encoded[index];
+            len += 1;
+            index += 1;
+        }
+
+        // a maximum length block has no implied zero
+        if (code < 0xFF) && (index < encoded.len()) {
+            *data.get_mut(len)? = 0;
+            len += 1;
+        }
+    }
+
+    Some(len)
+}
+
+fn write_u16(data: &mut [u8], value: u16) {
+    data[0] = (value & 0xFF) as _;
+    data[1] = ((value >> 8) & 0xFF) as _;
+}
+
+fn write_u32(data: &mut [u8], value: u32) {
+    data[0] = (value & 0xFF) as _;
+    data[1] = ((value >> 8) & 0xFF) as _;
+    data[2] = ((value >> 16) & 0xFF) as _;
+    data[3] = ((value >> 24) & 0xFF) as _;
+}
+
+fn read_u16(data: &[u8]) -> u16 {
+    u16::from(data[0]) | (u16::from(data[1]) << 8)
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from(data[0])
+        | (u32::from(data[1]) << 8)
+        | (u32::from(data[2]) << 16)
+        | (u32::from(data[3]) << 24)
+}
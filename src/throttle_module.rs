@@ -1,22 +1,25 @@
 //! Throttle module
 
-use board::AcceleratorPositionSensor;
-use core::fmt::Write;
+use board::{AcceleratorPositionSensor, SPOOF_READBACK_VERIFICATION};
 use dac_mcp4922::DacOutput;
 use dtc::DtcBitfield;
-use dual_signal::DualSignal;
+use dual_signal::{DualSignal, SensorReading};
 use fault_can_protocol::*;
 use fault_condition::FaultCondition;
-use nucleo_f767zi::debug_console::DebugConsole;
+use interlock::InterlockAction;
 use nucleo_f767zi::hal::can::CanFrame;
 use nucleo_f767zi::hal::prelude::*;
 use num;
 use oscc_magic_byte::*;
+use override_can_protocol::*;
+use override_rearm::OverrideRearm;
 use oxcc_error::OxccError;
-use ranges;
+use params::{self, Param};
+use slew_rate_limiter::SlewRateLimiter;
+use system_state;
 use throttle_can_protocol::*;
 use types::*;
-use vehicle::*;
+use vehicle::{self, *};
 
 struct ThrottleControlState<DTCS: DtcBitfield> {
     enabled: bool,
@@ -43,6 +46,12 @@ pub struct ThrottleModule {
     control_state: ThrottleControlState<u8>,
     grounded_fault_state: FaultCondition<ThrottleGroundedFaultTimer>,
     operator_override_state: FaultCondition<ThrottleOverrideFaultTimer>,
+    override_rearm: OverrideRearm,
+    sensor_mismatch_fault_state: FaultCondition<ThrottleMismatchFaultTimer>,
+    readback_fault_state: FaultCondition<ThrottleReadbackFaultTimer>,
+    commanded_spoof: Option<(u16, u16)>,
+    command_slew_rate_limiter: SlewRateLimiter,
+    brake_interlock: Option<InterlockAction>,
     throttle_report: OsccThrottleReport,
     fault_report: OsccFaultReport,
     throttle_dac: ThrottleDac,
@@ -60,13 +69,26 @@ impl UnpreparedThrottleModule {
         throttle_pins: ThrottlePins,
         grounded_fault_timer: ThrottleGroundedFaultTimer,
         override_timer: ThrottleOverrideFaultTimer,
+        mismatch_fault_timer: ThrottleMismatchFaultTimer,
+        readback_fault_timer: ThrottleReadbackFaultTimer,
     ) -> UnpreparedThrottleModule {
+        let profile = vehicle::profile();
+
         UnpreparedThrottleModule {
             throttle_module: ThrottleModule {
                 accelerator_position: DualSignal::new(0, 0, accelerator_position_sensor),
                 control_state: ThrottleControlState::new(u8::default()),
                 grounded_fault_state: FaultCondition::new(grounded_fault_timer),
                 operator_override_state: FaultCondition::new(override_timer),
+                override_rearm: OverrideRearm::new(profile.throttle_override_rearm_policy),
+                sensor_mismatch_fault_state: FaultCondition::new(mismatch_fault_timer),
+                readback_fault_state: FaultCondition::new(readback_fault_timer),
+                commanded_spoof: None,
+                command_slew_rate_limiter: SlewRateLimiter::new(
+                    profile.throttle_command_slew_rate_limit,
+                    MINIMUM_THROTTLE_COMMAND,
+                ),
+                brake_interlock: None,
                 throttle_report: OsccThrottleReport::new(),
                 fault_report: OsccFaultReport {
                     fault_origin_id: FAULT_ORIGIN_THROTTLE,
@@ -86,7 +108,7 @@ impl UnpreparedThrottleModule {
 }
 
 impl ThrottleModule {
-    pub fn disable_control(&mut self, debug_console: &mut DebugConsole) -> Result<(), OxccError> {
+    pub fn disable_control(&mut self) -> Result<(), OxccError> {
         if self.control_state.enabled {
             self.accelerator_position.prevent_signal_discontinuity();
 
@@ -98,7 +120,8 @@ impl ThrottleModule {
             // even if we've encountered an error, we can still disable
             self.throttle_pins.spoof_enable.set_low();
             self.control_state.enabled = false;
-            writeln!(debug_console, "Throttle control disabled");
+            self.commanded_spoof = None;
+            info!("Throttle control disabled");
 
             return if let Err(e) = result {
                 Err(OxccError::from(e))
@@ -110,8 +133,16 @@ impl ThrottleModule {
         Ok(())
     }
 
-    fn enable_control(&mut self, debug_console: &mut DebugConsole) -> Result<(), OxccError> {
-        if !self.control_state.enabled && !self.control_state.operator_override {
+    pub fn enable_control(&mut self) -> Result<(), OxccError> {
+        if !system_state::control_allowed() {
+            warn!("Throttle enable refused, control not allowed in this system state");
+            return Ok(());
+        }
+
+        if !self.control_state.enabled
+            && !self.control_state.operator_override
+            && (self.brake_interlock != Some(InterlockAction::DisableThrottle))
+        {
             self.accelerator_position.prevent_signal_discontinuity();
 
             let result = self.throttle_dac.output_ab(
@@ -124,7 +155,10 @@ impl ThrottleModule {
             } else {
                 self.throttle_pins.spoof_enable.set_high();
                 self.control_state.enabled = true;
-                writeln!(debug_console, "Throttle control enabled");
+                self.commanded_spoof = None;
+                self.command_slew_rate_limiter
+                    .reset(MINIMUM_THROTTLE_COMMAND);
+                info!("Throttle control enabled");
                 Ok(())
             };
         }
@@ -138,21 +172,23 @@ impl ThrottleModule {
         spoof_command_low: u16,
     ) -> Result<(), OxccError> {
         if self.control_state.enabled {
+            let profile = vehicle::profile();
+            let spoof_high = profile.throttle_spoof_high_signal.clamp(spoof_command_high);
+            let spoof_low = profile.throttle_spoof_low_signal.clamp(spoof_command_low);
+            let commanded_spoof = (spoof_high, spoof_low);
+
             // TODO - revisit this, enforce high->A, low->B
-            self.throttle_dac.output_ab(
-                ranges::coerce(ThrottleSpoofHighSignal::clamp(spoof_command_high)),
-                ranges::coerce(ThrottleSpoofHighSignal::clamp(spoof_command_low)),
-            )?;
+            self.throttle_dac
+                .output_ab(DacOutput::clamp(spoof_high), DacOutput::clamp(spoof_low))?;
+
+            self.commanded_spoof = Some(commanded_spoof);
         }
 
         Ok(())
     }
 
     /// Checks for any fresh (previously undetected or unhandled) faults
-    pub fn check_for_faults(
-        &mut self,
-        debug_console: &mut DebugConsole,
-    ) -> Result<Option<&OsccFaultReport>, OxccError> {
+    pub fn check_for_faults(&mut self) -> Result<Option<&OsccFaultReport>, OxccError> {
         if !self.control_state.enabled && !self.control_state.dtcs.are_any_set() {
             // Assumes this module already went through the proper transition into a faulted
             // and disabled state, and we do not want to double-report a possible duplicate
@@ -163,18 +199,31 @@ impl ThrottleModule {
         self.accelerator_position.update();
 
         let accelerator_position_average = self.accelerator_position.average();
+        let override_threshold = params::get(Param::AcceleratorOverrideThreshold) as u32;
 
-        let operator_overridden: bool = self.operator_override_state.condition_exceeded_duration(
-            accelerator_position_average >= ACCELERATOR_OVERRIDE_THRESHOLD,
-        );
+        let operator_overridden: bool = self
+            .operator_override_state
+            .condition_exceeded_duration(accelerator_position_average >= override_threshold);
 
         let inputs_grounded: bool = self
             .grounded_fault_state
             .check_voltage_grounded(&self.accelerator_position);
 
+        let inputs_implausible: bool = self.sensor_mismatch_fault_state.check_signal_implausible(
+            &self.accelerator_position,
+            &vehicle::profile().accelerator_position_sensor_plausibility,
+        );
+
+        let outputs_mismatched: bool = match self.commanded_spoof {
+            Some((spoof_high, spoof_low)) if SPOOF_READBACK_VERIFICATION => self
+                .readback_fault_state
+                .check_spoof_readback(&self.accelerator_position, spoof_high, spoof_low),
+            _ => self.readback_fault_state.condition_exceeded_duration(false),
+        };
+
         // sensor pins tied to ground - a value of zero indicates disconnection
         if inputs_grounded {
-            self.disable_control(debug_console)?;
+            self.disable_control()?;
 
             self.control_state
                 .dtcs
@@ -182,14 +231,35 @@ impl ThrottleModule {
 
             self.update_fault_report();
 
-            writeln!(
-                debug_console,
-                "Bad value read from accelerator position sensor"
-            );
+            error!("Bad value read from accelerator position sensor");
+
+            Ok(Some(&self.fault_report))
+        } else if inputs_implausible {
+            self.disable_control()?;
+
+            self.control_state
+                .dtcs
+                .set(OSCC_THROTTLE_DTC_SENSOR_MISMATCH);
+
+            self.update_fault_report();
+
+            error!("Implausible value read from accelerator position sensor");
+
+            Ok(Some(&self.fault_report))
+        } else if outputs_mismatched {
+            self.disable_control()?;
+
+            self.control_state
+                .dtcs
+                .set(OSCC_THROTTLE_DTC_SPOOF_MISMATCH);
+
+            self.update_fault_report();
+
+            error!("Throttle spoof output readback mismatch");
 
             Ok(Some(&self.fault_report))
         } else if operator_overridden && !self.control_state.operator_override {
-            self.disable_control(debug_console)?;
+            self.disable_control()?;
 
             self.control_state
                 .dtcs
@@ -198,14 +268,43 @@ impl ThrottleModule {
             self.update_fault_report();
 
             self.control_state.operator_override = true;
+            self.override_rearm.override_detected();
 
-            writeln!(debug_console, "Throttle operator override");
+            warn!("Throttle operator override");
 
             Ok(Some(&self.fault_report))
-        } else {
+        } else if self
+            .override_rearm
+            .update(accelerator_position_average < override_threshold)
+        {
             self.control_state.dtcs.clear_all();
             self.control_state.operator_override = false;
             Ok(None)
+        } else {
+            // overridden, waiting to be re-armed
+            Ok(None)
+        }
+    }
+
+    /// Applies the brake/throttle interlock action, `None` releases
+    /// the interlock
+    pub fn apply_brake_interlock(
+        &mut self,
+        action: Option<InterlockAction>,
+    ) -> Result<(), OxccError> {
+        let newly_interlocked = self.brake_interlock.is_none() && action.is_some();
+
+        self.brake_interlock = action;
+
+        match action {
+            Some(InterlockAction::DisableThrottle) => self.disable_control(),
+            Some(InterlockAction::ZeroThrottle) if newly_interlocked => {
+                // bypass the slew rate limit, releasing the throttle can't wait
+                self.command_slew_rate_limiter
+                    .reset(MINIMUM_THROTTLE_COMMAND);
+                self.output_throttle_position(MINIMUM_THROTTLE_COMMAND)
+            }
+            _ => Ok(()),
         }
     }
 
@@ -213,31 +312,85 @@ impl ThrottleModule {
         self.fault_report.dtcs = self.control_state.dtcs;
     }
 
+    /// Power-on self test of the sensor idle readings
+    ///
+    /// The accelerator pedal is expected to be released.
+    pub fn self_test_sensor(&mut self) -> bool {
+        self.accelerator_position.update();
+
+        let accelerator_position_average = self.accelerator_position.average();
+        let override_threshold = params::get(Param::AcceleratorOverrideThreshold) as u32;
+
+        vehicle::profile()
+            .accelerator_position_sensor_plausibility
+            .is_plausible(
+                self.accelerator_position.high(),
+                self.accelerator_position.low(),
+            )
+            && !(accelerator_position_average >= override_threshold)
+    }
+
+    /// Commanded DAC outputs (high, low), `None` while not spoofing
+    pub fn commanded_spoof(&self) -> Option<(u16, u16)> {
+        self.commanded_spoof
+    }
+
+    /// Reads the accelerator position sensor
+    pub fn read_sensor(&mut self) -> SensorReading {
+        self.accelerator_position.update();
+        self.accelerator_position
+            .reading(&vehicle::profile().accelerator_position_sensor_plausibility)
+    }
+
+    /// Power-on self test of the DAC, the sensor readings are passed through
+    /// as done when control is disabled
+    pub fn self_test_dac(&mut self) -> bool {
+        self.throttle_dac
+            .output_ab(
+                DacOutput::clamp(self.accelerator_position.low()),
+                DacOutput::clamp(self.accelerator_position.high()),
+            )
+            .is_ok()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.control_state.enabled
+    }
+
     pub fn supply_throttle_report(&mut self) -> &OsccThrottleReport {
         self.throttle_report.enabled = self.control_state.enabled;
         self.throttle_report.operator_override = self.control_state.operator_override;
+        self.throttle_report.override_rearm_state = self.override_rearm.state().into();
         self.throttle_report.dtcs = self.control_state.dtcs;
+        if self.control_state.enabled && self.brake_interlock.is_some() {
+            self.throttle_report
+                .dtcs
+                .set(OSCC_THROTTLE_DTC_BRAKE_INTERLOCK);
+        }
+        self.throttle_report.slew_rate_limited = self.command_slew_rate_limiter.is_active();
         &self.throttle_report
     }
 
-    pub fn process_rx_frame(
-        &mut self,
-        can_frame: &CanFrame,
-        debug_console: &mut DebugConsole,
-    ) -> Result<(), OxccError> {
+    pub fn process_rx_frame(&mut self, can_frame: &CanFrame) -> Result<(), OxccError> {
         if let CanFrame::DataFrame(ref frame) = can_frame {
             let id: u32 = frame.id().into();
             let data = frame.data();
 
             if (data[0] == OSCC_MAGIC_BYTE_0) && (data[1] == OSCC_MAGIC_BYTE_1) {
                 if id == OSCC_THROTTLE_ENABLE_CAN_ID.into() {
-                    self.enable_control(debug_console)?;
+                    self.enable_control()?;
                 } else if id == OSCC_THROTTLE_DISABLE_CAN_ID.into() {
-                    self.disable_control(debug_console)?;
+                    self.disable_control()?;
                 } else if id == OSCC_THROTTLE_COMMAND_CAN_ID.into() {
                     self.process_throttle_command(&OsccThrottleCommand::from(frame))?;
+                } else if id == OXCC_OVERRIDE_ACKNOWLEDGE_CAN_ID.into() {
+                    if (data.len() == OXCC_OVERRIDE_ACKNOWLEDGE_CAN_DLC as usize)
+                        && OxccOverrideAcknowledge::from(frame).acknowledges(FAULT_ORIGIN_THROTTLE)
+                    {
+                        self.override_rearm.acknowledge();
+                    }
                 } else if id == OSCC_FAULT_REPORT_CAN_ID.into() {
-                    self.process_fault_report(&OsccFaultReport::from(frame), debug_console)?;
+                    self.process_fault_report(&OsccFaultReport::from(frame))?;
                 }
             }
         }
@@ -245,41 +398,64 @@ impl ThrottleModule {
         Ok(())
     }
 
-    fn process_fault_report(
-        &mut self,
-        fault_report: &OsccFaultReport,
-        debug_console: &mut DebugConsole,
-    ) -> Result<(), OxccError> {
-        writeln!(
-            debug_console,
+    fn process_fault_report(&mut self, fault_report: &OsccFaultReport) -> Result<(), OxccError> {
+        warn!(
             "Fault report received from: {} DTCs: {}",
             fault_report.fault_origin_id, fault_report.dtcs
         );
 
-        self.disable_control(debug_console)
+        self.disable_control()
     }
 
     fn process_throttle_command(&mut self, command: &OsccThrottleCommand) -> Result<(), OxccError> {
+        self.command_position(command.torque_request)
+    }
+
+    /// Applies a throttle position command, as if received over CAN
+    pub fn command_position(&mut self, position: f32) -> Result<(), OxccError> {
+        // the throttle stays released while interlocked
+        let requested_position = if self.brake_interlock.is_some() {
+            MINIMUM_THROTTLE_COMMAND
+        } else {
+            position
+        };
+
         let clamped_position = num::clamp(
-            command.torque_request,
+            requested_position,
             MINIMUM_THROTTLE_COMMAND,
             MAXIMUM_THROTTLE_COMMAND,
         );
 
-        let spoof_voltage_low: f32 = num::clamp(
-            throttle_position_to_volts_low(clamped_position),
-            THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN,
-            THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX,
-        );
+        let clamped_position = self.command_slew_rate_limiter.limit(clamped_position);
 
-        let spoof_voltage_high: f32 = num::clamp(
-            throttle_position_to_volts_high(clamped_position),
-            THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN,
-            THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX,
-        );
+        self.output_throttle_position(clamped_position)
+    }
+
+    /// Steps the slew rate limited position towards the last command,
+    /// called every main loop iteration
+    pub fn update_command(&mut self) -> Result<(), OxccError> {
+        if !self.control_state.enabled {
+            return Ok(());
+        }
+
+        let was_limited = self.command_slew_rate_limiter.is_active();
+        let position = self.command_slew_rate_limiter.update();
+
+        if was_limited {
+            self.output_throttle_position(position)
+        } else {
+            Ok(())
+        }
+    }
 
-        let spoof_value_low = (STEPS_PER_VOLT * spoof_voltage_low) as u16;
-        let spoof_value_high = (STEPS_PER_VOLT * spoof_voltage_high) as u16;
+    fn output_throttle_position(&mut self, clamped_position: f32) -> Result<(), OxccError> {
+        let profile = vehicle::profile();
+        let spoof_value_low = profile
+            .throttle_spoof_low_signal
+            .position_to_steps(clamped_position);
+        let spoof_value_high = profile
+            .throttle_spoof_high_signal
+            .position_to_steps(clamped_position);
 
         self.update_throttle(spoof_value_high, spoof_value_low)
     }
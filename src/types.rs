@@ -2,21 +2,38 @@
 
 use dac_mcp4922::Mcp4922;
 use nucleo_f767zi::hal::can::Can;
-use nucleo_f767zi::hal::gpio::gpioa::{PA15, PA4, PA5, PA6, PA7};
+#[cfg(feature = "kia-soul-petrol")]
+use nucleo_f767zi::hal::gpio::gpioa::PA0;
+use nucleo_f767zi::hal::gpio::gpioa::{PA15, PA4, PA5};
+#[cfg(not(feature = "kia-soul-petrol"))]
+use nucleo_f767zi::hal::gpio::gpioa::{PA6, PA7};
 use nucleo_f767zi::hal::gpio::gpiob::{PB10, PB12, PB13, PB15, PB4};
 use nucleo_f767zi::hal::gpio::gpioc::{PC10, PC11, PC12, PC2};
+#[cfg(feature = "kia-soul-petrol")]
+use nucleo_f767zi::hal::gpio::gpiod::PD14;
 use nucleo_f767zi::hal::gpio::gpiod::{PD0, PD1, PD11, PD12, PD13};
 use nucleo_f767zi::hal::gpio::gpioe::PE2;
+#[cfg(feature = "kia-soul-petrol")]
+use nucleo_f767zi::hal::gpio::gpioe::{PE11, PE13, PE14, PE9};
+#[cfg(feature = "kia-soul-petrol")]
+use nucleo_f767zi::hal::gpio::gpiof::{PF12, PF13};
+#[cfg(feature = "kia-soul-petrol")]
+use nucleo_f767zi::hal::gpio::{Analog, Input, AF1};
 use nucleo_f767zi::hal::gpio::{Output, PushPull, AF5, AF9};
 use nucleo_f767zi::hal::spi::Spi;
+#[cfg(not(feature = "kia-soul-petrol"))]
+use nucleo_f767zi::hal::stm32f7x7::SPI1;
 use nucleo_f767zi::hal::stm32f7x7::{
-    CAN1, CAN2, SPI1, SPI2, SPI3, TIM2, TIM3, TIM4, TIM5, TIM6, TIM7,
+    CAN1, CAN2, SPI2, SPI3, TIM10, TIM11, TIM12, TIM13, TIM14, TIM2, TIM3, TIM4, TIM5, TIM6, TIM7,
+    TIM9,
 };
 use nucleo_f767zi::hal::timer::Timer;
 use nucleo_f767zi::{
     AnalogInput0Pin, AnalogInput1Pin, AnalogInput2Pin, AnalogInput4Pin, AnalogInput5Pin,
     AnalogInput6Pin,
 };
+#[cfg(feature = "kia-soul-petrol")]
+use solenoid_pwm::SolenoidPwm;
 
 pub type CanPublishTimer = Timer<TIM2>;
 pub type BrakeGroundedFaultTimer = Timer<TIM3>;
@@ -24,26 +41,69 @@ pub type BrakeOverrideFaultTimer = Timer<TIM4>;
 pub type ThrottleGroundedFaultTimer = Timer<TIM5>;
 pub type ThrottleOverrideFaultTimer = Timer<TIM6>;
 pub type SteeringGroundedFaultTimer = Timer<TIM7>;
+pub type BrakeMismatchFaultTimer = Timer<TIM12>;
+pub type ThrottleMismatchFaultTimer = Timer<TIM13>;
+pub type SteeringMismatchFaultTimer = Timer<TIM14>;
+pub type BrakeReadbackFaultTimer = Timer<TIM9>;
+pub type ThrottleReadbackFaultTimer = Timer<TIM10>;
+pub type SteeringReadbackFaultTimer = Timer<TIM11>;
 
 pub type ControlCan = Can<CAN1, (PD1<AF9>, PD0<AF9>)>;
 pub type ObdCan = Can<CAN2, (PB13<AF9>, PB12<AF9>)>;
 
+#[cfg(not(feature = "kia-soul-petrol"))]
 pub type BrakeSpi = Spi<SPI1, (PA5<AF5>, PA6<AF5>, PA7<AF5>)>;
 pub type ThrottleSpi = Spi<SPI2, (PB10<AF5>, PC2<AF5>, PB15<AF5>)>;
 pub type SteeringSpi = Spi<SPI3, (PC10<AF5>, PC11<AF5>, PC12<AF5>)>;
 
-pub type BrakeSpoofEnablePin = PD12<Output<PushPull>>;
 pub type BrakeLightEnablePin = PD13<Output<PushPull>>;
+#[cfg(not(feature = "kia-soul-petrol"))]
+pub type BrakeSpoofEnablePin = PD12<Output<PushPull>>;
 // AIN pins chosen to allow brake module to own ADC1
+#[cfg(not(feature = "kia-soul-petrol"))]
 pub type BrakePedalPositionSensorHighPin = AnalogInput0Pin;
+#[cfg(not(feature = "kia-soul-petrol"))]
 pub type BrakePedalPositionSensorLowPin = AnalogInput1Pin;
+#[cfg(not(feature = "kia-soul-petrol"))]
 pub type BrakeSpiSckPin = PA5<AF5>;
+#[cfg(not(feature = "kia-soul-petrol"))]
 pub type BrakeSpiMisoPin = PA6<AF5>;
+#[cfg(not(feature = "kia-soul-petrol"))]
 pub type BrakeSpiMosiPin = PA7<AF5>;
+#[cfg(not(feature = "kia-soul-petrol"))]
 pub type BrakeSpiNssPin = PA4<Output<PushPull>>;
 
+#[cfg(not(feature = "kia-soul-petrol"))]
 pub type BrakeDac = Mcp4922<BrakeSpi, BrakeSpiNssPin>;
 
+// Kia Soul Petrol brake hardware, the master cylinder solenoid takes
+// over the spoof enable pin so the panic handler still releases it
+#[cfg(feature = "kia-soul-petrol")]
+pub type BrakeMasterCylinderSolenoidPin = PD12<Output<PushPull>>;
+// AIN pins chosen to allow brake module to own ADC1
+#[cfg(feature = "kia-soul-petrol")]
+pub type BrakeAccumulatorPumpPin = PD14<Output<PushPull>>;
+#[cfg(feature = "kia-soul-petrol")]
+pub type BrakePressureCheck1Pin = PF13<Output<PushPull>>;
+#[cfg(feature = "kia-soul-petrol")]
+pub type BrakePressureCheck2Pin = PF12<Output<PushPull>>;
+#[cfg(feature = "kia-soul-petrol")]
+pub type BrakeMasterCylinderPressureSensor1Pin = AnalogInput0Pin;
+#[cfg(feature = "kia-soul-petrol")]
+pub type BrakeMasterCylinderPressureSensor2Pin = AnalogInput1Pin;
+#[cfg(feature = "kia-soul-petrol")]
+pub type BrakeAccumulatorPressureSensorPin = PA0<Input<Analog>>;
+#[cfg(feature = "kia-soul-petrol")]
+pub type BrakeWheelPressureSensorFrontLeftPin = PA4<Input<Analog>>;
+#[cfg(feature = "kia-soul-petrol")]
+pub type BrakeWheelPressureSensorFrontRightPin = PA5<Input<Analog>>;
+// TIM1 CH1/CH2 accumulator solenoids, CH3/CH4 release solenoids
+#[cfg(feature = "kia-soul-petrol")]
+pub type BrakeSolenoidPins = (PE9<AF1>, PE11<AF1>, PE13<AF1>, PE14<AF1>);
+
+#[cfg(feature = "kia-soul-petrol")]
+pub type BrakeSolenoidPwm = SolenoidPwm;
+
 pub type ThrottleSpoofEnablePin = PE2<Output<PushPull>>;
 // AIN pins chosen to allow throttle module to own ADC2
 pub type AcceleratorPositionSensorHighPin = AnalogInput2Pin;
@@ -66,6 +126,7 @@ pub type SteeringSpiNssPin = PA15<Output<PushPull>>;
 
 pub type SteeringDac = Mcp4922<SteeringSpi, SteeringSpiNssPin>;
 
+#[cfg(not(feature = "kia-soul-petrol"))]
 pub struct BrakePins {
     pub spoof_enable: BrakeSpoofEnablePin,
     pub brake_light_enable: BrakeLightEnablePin,
@@ -73,6 +134,22 @@ pub struct BrakePins {
     pub pedal_pos_sensor_low: BrakePedalPositionSensorLowPin,
 }
 
+#[cfg(feature = "kia-soul-petrol")]
+pub struct BrakePins {
+    pub master_cylinder_solenoid: BrakeMasterCylinderSolenoidPin,
+    pub brake_light_enable: BrakeLightEnablePin,
+    pub accumulator_pump: BrakeAccumulatorPumpPin,
+    /// PCK1, front left wheel pressure sensor check
+    pub pressure_check_1: BrakePressureCheck1Pin,
+    /// PCK2, front right wheel pressure sensor check
+    pub pressure_check_2: BrakePressureCheck2Pin,
+    pub master_cylinder_pressure_sensor_1: BrakeMasterCylinderPressureSensor1Pin,
+    pub master_cylinder_pressure_sensor_2: BrakeMasterCylinderPressureSensor2Pin,
+    pub accumulator_pressure_sensor: BrakeAccumulatorPressureSensorPin,
+    pub wheel_pressure_sensor_front_left: BrakeWheelPressureSensorFrontLeftPin,
+    pub wheel_pressure_sensor_front_right: BrakeWheelPressureSensorFrontRightPin,
+}
+
 pub struct ThrottlePins {
     pub spoof_enable: ThrottleSpoofEnablePin,
     pub accel_pos_sensor_high: AcceleratorPositionSensorHighPin,
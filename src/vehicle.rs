@@ -1,9 +1,245 @@
 //! Vehicle abstraction
 //!
-//! Pick your vehicle feature set in the `Cargo.toml`
-#[cfg(feature = "kia-niro")]
-pub use kial_niro::*;
-#[cfg(feature = "kia-soul-ev")]
-pub use kial_soul_ev::*;
+//! The Kia Soul EV, Kia Niro and bench profiles share their hardware, a
+//! single image holds all of them and the active one is selected at
+//! runtime, see `params::load`. The Kia Soul Petrol has its own brake
+//! hardware and is built on its own with the `kia-soul-petrol` feature.
+//!
+//! Values of the board, the OBD frames and the pedal command ranges, shared
+//! by all profiles of the image, are re-exported here. The values of each
+//! vehicle, including its spoof signals, command limits, tuning and
+//! parameter limits, are only reachable through `profile()`.
+//!
+//! The vehicle specific values of each profile are kept in
+//! `vehicles/<name>.toml` and turned into constants by `build.rs`.
+//!
+//! Vehicle profile IDs, reported in `OxccSystemStateReport`:
+//! - 0: Kia Soul EV
+//! - 1: Kia Niro
+//! - 2: Kia Soul Petrol
+//! - 3: Bench, test benches and HIL rigs without a vehicle
+
+#[cfg(not(feature = "kia-soul-petrol"))]
+use bench::BENCH_PROFILE;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use dual_signal::SignalPlausibility;
+use interlock::InterlockAction;
+#[cfg(not(feature = "kia-soul-petrol"))]
+use kial_niro::KIA_NIRO_PROFILE;
+#[cfg(not(feature = "kia-soul-petrol"))]
+use kial_soul_ev::KIA_SOUL_EV_PROFILE;
+#[cfg(not(feature = "kia-soul-petrol"))]
+pub use kial_soul_ev::{
+    MAXIMUM_BRAKE_COMMAND, MAXIMUM_THROTTLE_COMMAND, MINIMUM_BRAKE_COMMAND,
+    MINIMUM_THROTTLE_COMMAND, STEPS_PER_VOLT,
+};
 #[cfg(feature = "kia-soul-petrol")]
 pub use kial_soul_petrol::*;
+use num;
+use override_rearm::RearmPolicy;
+use params::ParamLimits;
+use pedal_selection::PedalThresholds;
+use pid::PidGains;
+
+/// Vehicle profiles of the image, the first one is the default
+#[cfg(not(feature = "kia-soul-petrol"))]
+static PROFILES: [VehicleProfile; 3] = [KIA_SOUL_EV_PROFILE, KIA_NIRO_PROFILE, BENCH_PROFILE];
+
+/// Vehicle profiles of the image, the first one is the default
+#[cfg(feature = "kia-soul-petrol")]
+static PROFILES: [VehicleProfile; 1] = [KIA_SOUL_PETROL_PROFILE];
+
+/// Index of the active profile in `PROFILES`
+static ACTIVE_PROFILE: AtomicUsize = AtomicUsize::new(0);
+
+/// Limits and conversion of one channel of a spoofed sensor signal
+#[derive(Copy, Clone)]
+pub struct SpoofSignal {
+    /// Minimum allowed voltage. \[volts\]
+    pub voltage_min: f32,
+    /// Maximum allowed voltage. \[volts\]
+    pub voltage_max: f32,
+    /// Minimum allowed value, `voltage_min * STEPS_PER_VOLT` rounded
+    /// up. \[steps\]
+    pub range_min: u16,
+    /// Maximum allowed value, `voltage_max * STEPS_PER_VOLT` rounded
+    /// down. \[steps\]
+    pub range_max: u16,
+    /// Calculation to convert a position to a spoof voltage.
+    pub position_to_volts: fn(f32) -> f32,
+}
+
+impl SpoofSignal {
+    /// Spoof value of a position, held within the signal limits \[steps\]
+    pub fn position_to_steps(&self, position: f32) -> u16 {
+        let volts = num::clamp(
+            (self.position_to_volts)(position),
+            self.voltage_min,
+            self.voltage_max,
+        );
+
+        self.clamp((STEPS_PER_VOLT * volts) as u16)
+    }
+
+    /// Holds a spoof value within `[range_min, range_max]` \[steps\]
+    pub fn clamp(&self, value: u16) -> u16 {
+        num::clamp(value, self.range_min, self.range_max)
+    }
+}
+
+/// The vehicle specific values of a profile
+pub struct VehicleProfile {
+    pub id: u8,
+    pub name: &'static str,
+
+    /// ID of the OBD wheel speed CAN frame.
+    pub obd_wheel_speed_can_id: u16,
+    /// Factor to scale OBD wheel speed to kilometers per hour.
+    pub obd_wheel_speed_scalar: f32,
+    /// Length of time after which the OBD wheel speed data is
+    /// considered stale. \[ms\]
+    pub obd_wheel_speed_timeout: u32,
+    /// ID of the OBD throttle pressure CAN frame, if the vehicle has one.
+    pub obd_throttle_pressure_can_id: Option<u16>,
+    /// ID of the OBD steering wheel angle CAN frame.
+    pub obd_steering_wheel_angle_can_id: u16,
+    /// Factor to scale OBD steering wheel angle to degrees.
+    pub obd_steering_wheel_angle_scalar: f32,
+    /// Length of time after which the OBD steering wheel angle data
+    /// is considered stale. \[ms\]
+    pub obd_steering_wheel_angle_timeout: u32,
+    /// ID of the OBD brake pressure CAN frame.
+    pub obd_brake_pressure_can_id: u16,
+
+    /// Default of `Param::FaultHysteresis`. \[ms\]
+    pub fault_hysteresis: u32,
+    pub fault_hysteresis_limits: ParamLimits,
+
+    #[cfg(not(feature = "kia-soul-petrol"))]
+    pub brake_spoof_low_signal: SpoofSignal,
+    #[cfg(not(feature = "kia-soul-petrol"))]
+    pub brake_spoof_high_signal: SpoofSignal,
+    /// Default of `Param::BrakeOverrideThreshold`, \[steps\] or \[decibars\]
+    pub brake_override_threshold: f32,
+    pub brake_override_threshold_limits: ParamLimits,
+    pub brake_override_rearm_policy: RearmPolicy,
+    /// Maximum rate of change of the brake command. \[position/second\]
+    pub brake_command_slew_rate_limit: f32,
+    /// Minimum value of the low spoof signal that activates the brake
+    /// lights. \[steps\]
+    #[cfg(not(feature = "kia-soul-petrol"))]
+    pub brake_light_spoof_low_threshold: u16,
+    /// Minimum value of the high spoof signal that activates the brake
+    /// lights. \[steps\]
+    #[cfg(not(feature = "kia-soul-petrol"))]
+    pub brake_light_spoof_high_threshold: u16,
+    /// Plausibility model of the brake pedal position sensor channels.
+    #[cfg(not(feature = "kia-soul-petrol"))]
+    pub brake_pedal_position_sensor_plausibility: SignalPlausibility,
+
+    pub throttle_spoof_low_signal: SpoofSignal,
+    pub throttle_spoof_high_signal: SpoofSignal,
+    /// Default of `Param::AcceleratorOverrideThreshold`. \[steps\]
+    pub accelerator_override_threshold: f32,
+    pub accelerator_override_threshold_limits: ParamLimits,
+    /// Plausibility model of the accelerator position sensor channels.
+    pub accelerator_position_sensor_plausibility: SignalPlausibility,
+    pub throttle_override_rearm_policy: RearmPolicy,
+    /// Maximum rate of change of the throttle command. \[position/second\]
+    pub throttle_command_slew_rate_limit: f32,
+    /// Default of `Param::ThrottleInterlockBrakeCommandThreshold`.
+    pub throttle_interlock_brake_command_threshold: f32,
+    pub throttle_interlock_brake_command_threshold_limits: ParamLimits,
+    /// Default of `Param::ThrottleInterlockBrakePedalThreshold`. \[steps\]
+    #[cfg(not(feature = "kia-soul-petrol"))]
+    pub throttle_interlock_brake_pedal_threshold: f32,
+    #[cfg(not(feature = "kia-soul-petrol"))]
+    pub throttle_interlock_brake_pedal_threshold_limits: ParamLimits,
+    /// Default of `Param::ThrottleInterlockBrakePressureThreshold`.
+    /// \[decibars\]
+    #[cfg(feature = "kia-soul-petrol")]
+    pub throttle_interlock_brake_pressure_threshold: f32,
+    #[cfg(feature = "kia-soul-petrol")]
+    pub throttle_interlock_brake_pressure_threshold_limits: ParamLimits,
+    /// Action taken on the throttle while interlocked.
+    pub throttle_interlock_action: InterlockAction,
+
+    /// The position of the steering spoof signals is the torque command.
+    pub steering_spoof_low_signal: SpoofSignal,
+    pub steering_spoof_high_signal: SpoofSignal,
+    /// Minimum allowable torque value.
+    pub minimum_torque_command: f32,
+    /// Maximum allowable torque value.
+    pub maximum_torque_command: f32,
+    /// Maximum rate of change of the torque command. \[torque/second\]
+    pub steering_command_slew_rate_limit: f32,
+    /// Speed scheduled limit on the magnitude of the torque command.
+    ///
+    /// Each entry is a vehicle speed \[km/h\] and the torque limit at that
+    /// speed, sorted by speed. The limit is interpolated between entries and
+    /// held beyond the first and last. The smallest limit applies when the
+    /// vehicle speed is unknown.
+    pub steering_torque_speed_schedule: &'static [(f32, f32)],
+    /// Plausibility model of the torque sensor channels.
+    pub torque_sensor_plausibility: SignalPlausibility,
+    /// Default of `Param::TorqueDifferenceOverrideThreshold`. \[steps\]
+    pub torque_difference_override_threshold: f32,
+    pub torque_difference_override_threshold_limits: ParamLimits,
+    pub steering_filter_alpha_limits: ParamLimits,
+    pub steering_override_rearm_policy: RearmPolicy,
+    /// Maximum allowable steering wheel angle command magnitude. \[degrees\]
+    pub maximum_steering_angle_command: f32,
+    /// Steering angle PID controller gains, \[torque/degree\]
+    pub steering_angle_pid_gains: PidGains,
+    pub steering_angle_pid_windup_guard: f32,
+
+    /// Maximum allowable target vehicle speed. \[km/h\]
+    pub maximum_speed_control_target_speed: f32,
+    /// Maximum allowable acceleration limit, applies to both
+    /// acceleration and deceleration. \[m/s^2\]
+    pub maximum_speed_control_acceleration_limit: f32,
+    /// Speed control PID controller gains, \[pedal/(km/h)\]
+    pub speed_control_pid_gains: PidGains,
+    pub speed_control_pid_windup_guard: f32,
+    /// Speed control output thresholds selecting the pedal
+    pub speed_control_pedal_thresholds: PedalThresholds,
+}
+
+/// The active vehicle profile
+pub fn profile() -> &'static VehicleProfile {
+    &PROFILES[ACTIVE_PROFILE.load(Ordering::Relaxed)]
+}
+
+/// Whether the bench profile is active, the debug shell only enables
+/// control modules on a bench
+#[cfg(not(feature = "kia-soul-petrol"))]
+pub fn is_bench() -> bool {
+    profile().id == BENCH_PROFILE.id
+}
+
+/// Whether the bench profile is active, the debug shell only enables
+/// control modules on a bench
+#[cfg(feature = "kia-soul-petrol")]
+pub fn is_bench() -> bool {
+    false
+}
+
+/// Looks up a vehicle profile of the image by its ID
+pub fn find(id: u8) -> Option<&'static VehicleProfile> {
+    PROFILES.iter().find(|profile| profile.id == id)
+}
+
+/// Makes the profile with the given ID the active one, returns false
+/// if the image doesn't hold it
+///
+/// Only used before the board is configured, the OBD CAN filters are
+/// derived from the active profile.
+pub fn select(id: u8) -> bool {
+    match PROFILES.iter().position(|profile| profile.id == id) {
+        Some(index) => {
+            ACTIVE_PROFILE.store(index, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
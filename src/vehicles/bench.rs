@@ -0,0 +1,239 @@
+//! Bench vehicle configuration data
+//!
+//! Test bench and HIL rig profile, see `bench.toml`. The steering spoof
+//! signals and torque sensor model are as wide as the pedals', the
+//! parameter limits leave room to tune against any rig.
+//!
+//! **WARNING**
+//!
+//! These ranges are safe for the bench hardware only, they were never
+//! tested on a vehicle. Never select this profile on a car.
+
+#![allow(dead_code)]
+
+use calibration::{self, CalibrationTable};
+use vehicle::{SpoofSignal, VehicleProfile};
+
+use self::generated::*;
+
+/// The values of `bench.toml`, see `build.rs`
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/bench.rs"));
+}
+
+// ****************************************************************************
+// BRAKE MODULE
+// ****************************************************************************
+
+/// Optional calibration table of the brake spoof low signal, replaces
+/// the straight line conversion when set. See `calibration_table!`.
+const BRAKE_SPOOF_LOW_SIGNAL_CALIBRATION_TABLE: Option<CalibrationTable> = None;
+
+/// Calculation to convert a brake position to a low spoof voltage.
+fn brake_position_to_volts_low(position: f32) -> f32 {
+    match BRAKE_SPOOF_LOW_SIGNAL_CALIBRATION_TABLE {
+        Some(table) => calibration::interpolate(table, position),
+        None => {
+            position * (BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX - BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN)
+                + BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN
+        }
+    }
+}
+
+/// Optional calibration table of the brake spoof high signal, replaces
+/// the straight line conversion when set. See `calibration_table!`.
+const BRAKE_SPOOF_HIGH_SIGNAL_CALIBRATION_TABLE: Option<CalibrationTable> = None;
+
+/// Calculation to convert a brake position to a high spoof voltage.
+fn brake_position_to_volts_high(position: f32) -> f32 {
+    match BRAKE_SPOOF_HIGH_SIGNAL_CALIBRATION_TABLE {
+        Some(table) => calibration::interpolate(table, position),
+        None => {
+            position * (BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX - BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN)
+                + BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN
+        }
+    }
+}
+
+// ****************************************************************************
+// STEERING MODULE
+// ****************************************************************************
+
+/// Optional calibration table of the steering spoof low signal, replaces
+/// the straight line conversion when set. See `calibration_table!`.
+const STEERING_SPOOF_LOW_SIGNAL_CALIBRATION_TABLE: Option<CalibrationTable> = None;
+
+/// Calculation to convert a steering torque to a low spoof voltage.
+fn steering_torque_to_volts_low(torque: f32) -> f32 {
+    match STEERING_SPOOF_LOW_SIGNAL_CALIBRATION_TABLE {
+        Some(table) => calibration::interpolate(table, torque),
+        None => {
+            (TORQUE_SPOOF_LOW_SIGNAL_CALIBRATION_CURVE_SCALE * torque)
+                + TORQUE_SPOOF_LOW_SIGNAL_CALIBRATION_CURVE_OFFSET
+        }
+    }
+}
+
+/// Optional calibration table of the steering spoof high signal, replaces
+/// the straight line conversion when set. See `calibration_table!`.
+const STEERING_SPOOF_HIGH_SIGNAL_CALIBRATION_TABLE: Option<CalibrationTable> = None;
+
+/// Calculation to convert a steering torque to a high spoof voltage.
+fn steering_torque_to_volts_high(torque: f32) -> f32 {
+    match STEERING_SPOOF_HIGH_SIGNAL_CALIBRATION_TABLE {
+        Some(table) => calibration::interpolate(table, torque),
+        None => {
+            (TORQUE_SPOOF_HIGH_SIGNAL_CALIBRATION_CURVE_SCALE * torque)
+                + TORQUE_SPOOF_HIGH_SIGNAL_CALIBRATION_CURVE_OFFSET
+        }
+    }
+}
+
+// ****************************************************************************
+// THROTTLE MODULE
+// ****************************************************************************
+
+/// Optional calibration table of the throttle spoof low signal, replaces
+/// the straight line conversion when set. See `calibration_table!`.
+const THROTTLE_SPOOF_LOW_SIGNAL_CALIBRATION_TABLE: Option<CalibrationTable> = None;
+
+/// Calculation to convert a throttle position to a low spoof voltage.
+fn throttle_position_to_volts_low(position: f32) -> f32 {
+    match THROTTLE_SPOOF_LOW_SIGNAL_CALIBRATION_TABLE {
+        Some(table) => calibration::interpolate(table, position),
+        None => {
+            position
+                * (THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX - THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN)
+                + THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN
+        }
+    }
+}
+
+/// Optional calibration table of the throttle spoof high signal, replaces
+/// the straight line conversion when set. See `calibration_table!`.
+const THROTTLE_SPOOF_HIGH_SIGNAL_CALIBRATION_TABLE: Option<CalibrationTable> = None;
+
+/// Calculation to convert a throttle position to a high spoof voltage.
+fn throttle_position_to_volts_high(position: f32) -> f32 {
+    match THROTTLE_SPOOF_HIGH_SIGNAL_CALIBRATION_TABLE {
+        Some(table) => calibration::interpolate(table, position),
+        None => {
+            position
+                * (THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX - THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN)
+                + THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN
+        }
+    }
+}
+
+// ****************************************************************************
+// SPEED CONTROL
+// ****************************************************************************
+
+/// Speed control output above which the throttle is pressed.
+const SPEED_CONTROL_THROTTLE_ENGAGE_THRESHOLD: f32 = 0.05;
+
+/// Speed control output below which a pressed throttle is released.
+const SPEED_CONTROL_THROTTLE_RELEASE_THRESHOLD: f32 = 0.0;
+
+/// Speed control output below which the brake is pressed.
+const SPEED_CONTROL_BRAKE_ENGAGE_THRESHOLD: f32 = -0.1;
+
+/// Speed control output above which a pressed brake is released.
+const SPEED_CONTROL_BRAKE_RELEASE_THRESHOLD: f32 = -0.02;
+
+// ****************************************************************************
+// VEHICLE PROFILE
+// ****************************************************************************
+
+pub const BENCH_PROFILE: VehicleProfile = VehicleProfile {
+    id: PROFILE_ID,
+    name: PROFILE_NAME,
+    obd_wheel_speed_can_id: OBD_WHEEL_SPEED_CAN_ID,
+    obd_wheel_speed_scalar: OBD_WHEEL_SPEED_SCALAR,
+    obd_wheel_speed_timeout: OBD_WHEEL_SPEED_TIMEOUT,
+    obd_throttle_pressure_can_id: OBD_THROTTLE_PRESSURE_CAN_ID,
+    obd_steering_wheel_angle_can_id: OBD_STEERING_WHEEL_ANGLE_CAN_ID,
+    obd_steering_wheel_angle_scalar: OBD_STEERING_WHEEL_ANGLE_SCALAR,
+    obd_steering_wheel_angle_timeout: OBD_STEERING_WHEEL_ANGLE_TIMEOUT,
+    obd_brake_pressure_can_id: OBD_BRAKE_PRESSURE_CAN_ID,
+    fault_hysteresis: FAULT_HYSTERESIS,
+    fault_hysteresis_limits: FAULT_HYSTERESIS_LIMITS,
+    brake_spoof_low_signal: SpoofSignal {
+        voltage_min: BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN,
+        voltage_max: BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX,
+        range_min: BRAKE_SPOOF_LOW_SIGNAL_RANGE_MIN,
+        range_max: BRAKE_SPOOF_LOW_SIGNAL_RANGE_MAX,
+        position_to_volts: brake_position_to_volts_low,
+    },
+    brake_spoof_high_signal: SpoofSignal {
+        voltage_min: BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN,
+        voltage_max: BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX,
+        range_min: BRAKE_SPOOF_HIGH_SIGNAL_RANGE_MIN,
+        range_max: BRAKE_SPOOF_HIGH_SIGNAL_RANGE_MAX,
+        position_to_volts: brake_position_to_volts_high,
+    },
+    brake_override_threshold: BRAKE_PEDAL_OVERRIDE_THRESHOLD as f32,
+    brake_override_threshold_limits: BRAKE_PEDAL_OVERRIDE_THRESHOLD_LIMITS,
+    brake_override_rearm_policy: BRAKE_OVERRIDE_REARM_POLICY,
+    brake_command_slew_rate_limit: BRAKE_COMMAND_SLEW_RATE_LIMIT,
+    brake_light_spoof_low_threshold: BRAKE_LIGHT_SPOOF_LOW_THRESHOLD,
+    brake_light_spoof_high_threshold: BRAKE_LIGHT_SPOOF_HIGH_THRESHOLD,
+    brake_pedal_position_sensor_plausibility: BRAKE_PEDAL_POSITION_SENSOR_PLAUSIBILITY,
+    throttle_spoof_low_signal: SpoofSignal {
+        voltage_min: THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN,
+        voltage_max: THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX,
+        range_min: THROTTLE_SPOOF_LOW_SIGNAL_RANGE_MIN,
+        range_max: THROTTLE_SPOOF_LOW_SIGNAL_RANGE_MAX,
+        position_to_volts: throttle_position_to_volts_low,
+    },
+    throttle_spoof_high_signal: SpoofSignal {
+        voltage_min: THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN,
+        voltage_max: THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX,
+        range_min: THROTTLE_SPOOF_HIGH_SIGNAL_RANGE_MIN,
+        range_max: THROTTLE_SPOOF_HIGH_SIGNAL_RANGE_MAX,
+        position_to_volts: throttle_position_to_volts_high,
+    },
+    accelerator_override_threshold: ACCELERATOR_OVERRIDE_THRESHOLD as f32,
+    accelerator_override_threshold_limits: ACCELERATOR_OVERRIDE_THRESHOLD_LIMITS,
+    accelerator_position_sensor_plausibility: ACCELERATOR_POSITION_SENSOR_PLAUSIBILITY,
+    throttle_override_rearm_policy: THROTTLE_OVERRIDE_REARM_POLICY,
+    throttle_command_slew_rate_limit: THROTTLE_COMMAND_SLEW_RATE_LIMIT,
+    throttle_interlock_brake_command_threshold: THROTTLE_INTERLOCK_BRAKE_COMMAND_THRESHOLD,
+    throttle_interlock_brake_command_threshold_limits:
+        THROTTLE_INTERLOCK_BRAKE_COMMAND_THRESHOLD_LIMITS,
+    throttle_interlock_brake_pedal_threshold: THROTTLE_INTERLOCK_BRAKE_PEDAL_THRESHOLD as f32,
+    throttle_interlock_brake_pedal_threshold_limits:
+        THROTTLE_INTERLOCK_BRAKE_PEDAL_THRESHOLD_LIMITS,
+    throttle_interlock_action: THROTTLE_INTERLOCK_ACTION,
+    steering_spoof_low_signal: SpoofSignal {
+        voltage_min: STEERING_SPOOF_LOW_SIGNAL_VOLTAGE_MIN,
+        voltage_max: STEERING_SPOOF_LOW_SIGNAL_VOLTAGE_MAX,
+        range_min: STEERING_SPOOF_LOW_SIGNAL_RANGE_MIN,
+        range_max: STEERING_SPOOF_LOW_SIGNAL_RANGE_MAX,
+        position_to_volts: steering_torque_to_volts_low,
+    },
+    steering_spoof_high_signal: SpoofSignal {
+        voltage_min: STEERING_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN,
+        voltage_max: STEERING_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX,
+        range_min: STEERING_SPOOF_HIGH_SIGNAL_RANGE_MIN,
+        range_max: STEERING_SPOOF_HIGH_SIGNAL_RANGE_MAX,
+        position_to_volts: steering_torque_to_volts_high,
+    },
+    minimum_torque_command: MINIMUM_TORQUE_COMMAND,
+    maximum_torque_command: MAXIMUM_TORQUE_COMMAND,
+    steering_command_slew_rate_limit: STEERING_COMMAND_SLEW_RATE_LIMIT,
+    steering_torque_speed_schedule: &STEERING_TORQUE_SPEED_SCHEDULE,
+    torque_sensor_plausibility: TORQUE_SENSOR_PLAUSIBILITY,
+    torque_difference_override_threshold: TORQUE_DIFFERENCE_OVERRIDE_THRESHOLD as f32,
+    torque_difference_override_threshold_limits: TORQUE_DIFFERENCE_OVERRIDE_THRESHOLD_LIMITS,
+    steering_filter_alpha_limits: STEERING_FILTER_ALPHA_LIMITS,
+    steering_override_rearm_policy: STEERING_OVERRIDE_REARM_POLICY,
+    maximum_steering_angle_command: MAXIMUM_STEERING_ANGLE_COMMAND,
+    steering_angle_pid_gains: STEERING_ANGLE_PID_GAINS,
+    steering_angle_pid_windup_guard: STEERING_ANGLE_PID_WINDUP_GUARD,
+    maximum_speed_control_target_speed: MAXIMUM_SPEED_CONTROL_TARGET_SPEED,
+    maximum_speed_control_acceleration_limit: MAXIMUM_SPEED_CONTROL_ACCELERATION_LIMIT,
+    speed_control_pid_gains: SPEED_CONTROL_PID_GAINS,
+    speed_control_pid_windup_guard: SPEED_CONTROL_PID_WINDUP_GUARD,
+    speed_control_pedal_thresholds: SPEED_CONTROL_PEDAL_THRESHOLDS,
+};
@@ -1,5 +1,8 @@
 //! Kia Niro vehicle configuration data
 //!
+//! The profile values are in `kial_niro.toml`, the board values shared by
+//! all profiles are in `kial_soul_ev`.
+//!
 //! **WARNING**
 //!
 //! The values listed here are carefully tested to ensure that the vehicle's
@@ -13,234 +16,236 @@
 
 #![allow(dead_code)]
 
-use ranges;
-use typenum::consts::*;
-type U1723 = op!{U1000 + U723};
-type U3358 = op!{U1000 + U1000 + U1000 + U358};
-type U3440 = op!{U1000 + U1000 + U1000 + U440};
-type U3446 = op!{U1000 + U1000 + U1000 + U446};
-
-// ****************************************************************************
-// OBD MESSAGES
-// ****************************************************************************
-
-/// ID of the Kia Niro's OBD steering wheel angle CAN frame.
-pub const KIA_SOUL_OBD_STEERING_WHEEL_ANGLE_CAN_ID: u16 = 0x2B0;
+use calibration::{self, CalibrationTable};
+use vehicle::{SpoofSignal, VehicleProfile};
 
-/// ID of the Kia Niro's OBD wheel speed CAN frame.
-pub const KIA_SOUL_OBD_WHEEL_SPEED_CAN_ID: u16 = 0x386;
+use self::generated::*;
 
-/// ID of the Kia Niro's OBD brake pressure CAN frame.
-pub const KIA_SOUL_OBD_BRAKE_PRESSURE_CAN_ID: u16 = 0x220;
-
-/// ID of the Kia Niro's OBD speed CAN frame.
-pub const KIA_SOUL_OBD_SPEED_CAN_ID: u16 = 0x371;
-
-/// Factor to scale OBD steering angle to degrees
-pub const KIA_SOUL_OBD_STEERING_ANGLE_SCALAR: f32 = 0.1;
+/// The values of `kial_niro.toml`, see `build.rs`
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/kial_niro.rs"));
+}
 
 // ****************************************************************************
-// VEHICLE AND BOARD PARAMETERS
+// OBD MESSAGES
 // ****************************************************************************
 
-/// Number of steps per volt corresponding to 4096 steps (2^12) across
-/// 5 volts.
-pub const STEPS_PER_VOLT: f32 = 819.2;
-
-/// Length of time in ms for delay of signal reads to ensure fault is
-/// outside the range of noise in the signal.
-pub const FAULT_HYSTERESIS: u32 = 150;
+/// ID of the Kia Niro's OBD speed CAN frame.
+const KIA_SOUL_OBD_SPEED_CAN_ID: u16 = 0x371;
 
 // ****************************************************************************
 // BRAKE MODULE
 // ****************************************************************************
 
-/// Minimum allowable brake value.
-pub const MINIMUM_BRAKE_COMMAND: f32 = 0.0;
-
-/// Maximum allowable brake value.
-pub const MAXIMUM_BRAKE_COMMAND: f32 = 1.0;
-
-/// Minimum allowed voltage for the high spoof signal voltage. \[volts\]
-pub const BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN: f32 = 0.609;
-
-/// Maximum allowed voltage for the high spoof signal voltage. \[volts\]
-pub const BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX: f32 = 2.880;
-
-/// Minimum allowed voltage for the low spoof signal voltage. \[volts\]
-pub const BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN: f32 = 0.279;
-
-/// Maximum allowed voltage for the low spoof signal voltage. \[volts\]
-pub const BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX: f32 = 1.386;
-
-/// Minimum allowed value for the high spoof signal value. \[steps\]
-/// Equal to BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN * STEPS_PER_VOLT.
-pub const BRAKE_SPOOF_LOW_SIGNAL_RANGE_MIN: u16 = 499;
-
-/// Minimum allowed value for the high spoof signal value. \[steps\]
-/// Equal to BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX * STEPS_PER_VOLT.
-pub const BRAKE_SPOOF_LOW_SIGNAL_RANGE_MAX: u16 = 2359;
-
-/// Minimum allowed value for the low spoof signal value. \[steps\]
-/// Equal to BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN * STEPS_PER_VOLT.
-pub const BRAKE_SPOOF_HIGH_SIGNAL_RANGE_MIN: u16 = 229;
-
-/// Minimum allowed value for the low spoof signal value. \[steps\]
-/// Equal to BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX * STEPS_PER_VOLT.
-pub const BRAKE_SPOOF_HIGH_SIGNAL_RANGE_MAX: u16 = 1135;
+/// Optional calibration table of the brake spoof low signal, replaces
+/// the straight line conversion when set. See `calibration_table!`.
+const BRAKE_SPOOF_LOW_SIGNAL_CALIBRATION_TABLE: Option<CalibrationTable> = None;
 
 /// Calculation to convert a brake position to a low spoof voltage.
-pub const fn brake_position_to_volts_low(position: f32) -> f32 {
-    position * (BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX - BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN)
-        + BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN
+fn brake_position_to_volts_low(position: f32) -> f32 {
+    match BRAKE_SPOOF_LOW_SIGNAL_CALIBRATION_TABLE {
+        Some(table) => calibration::interpolate(table, position),
+        None => {
+            position * (BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX - BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN)
+                + BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN
+        }
+    }
 }
 
+/// Optional calibration table of the brake spoof high signal, replaces
+/// the straight line conversion when set. See `calibration_table!`.
+const BRAKE_SPOOF_HIGH_SIGNAL_CALIBRATION_TABLE: Option<CalibrationTable> = None;
+
 /// Calculation to convert a brake position to a high spoof voltage.
-pub const fn brake_position_to_volts_high(position: f32) -> f32 {
-    position * (BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX - BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN)
-        + BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN
+fn brake_position_to_volts_high(position: f32) -> f32 {
+    match BRAKE_SPOOF_HIGH_SIGNAL_CALIBRATION_TABLE {
+        Some(table) => calibration::interpolate(table, position),
+        None => {
+            position * (BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX - BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN)
+                + BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN
+        }
+    }
 }
 
-/// Value of the brake position that indicates operator
-/// override. \[steps\]
-pub const BRAKE_PEDAL_OVERRIDE_THRESHOLD: u16 = 200;
-
-/// Minimum value of the high spoof signal that activates the brake
-/// lights. \[steps\]
-pub const BRAKE_LIGHT_SPOOF_HIGH_THRESHOLD: u16 = 300;
-
-/// Minimum value of the low spoof signal that activates the brake
-/// lights. \[steps\]
-pub const BRAKE_LIGHT_SPOOF_LOW_THRESHOLD: u16 = 600;
-
 // ****************************************************************************
 // STEERING MODULE
 // ****************************************************************************
 
-/// Minimum allowable torque value.
-pub const MINIMUM_TORQUE_COMMAND: f32 = -12.8;
-
-/// Maximum allowable torque value.
-pub const MAXIMUM_TORQUE_COMMAND: f32 = 12.7;
-
-/// Minimum allowable steering DAC output. \[volts\]
-pub const STEERING_SPOOF_LOW_SIGNAL_VOLTAGE_MIN: f32 = 0.80;
-
-/// Maximum allowable steering DAC output. \[volts\]
-pub const STEERING_SPOOF_LOW_SIGNAL_VOLTAGE_MAX: f32 = 4.10;
-
-/// Minimum allowable steering DAC output. \[volts\]
-pub const STEERING_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN: f32 = 0.90;
-
-/// Maximum allowable steering DAC output. \[volts\]
-pub const STEERING_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX: f32 = 4.20;
-
-/// Minimum allowed value for the low spoof signal value. \[steps\]
-/// Equal to STEERING_SPOOF_LOW_SIGNAL_VOLTAGE_MIN * STEPS_PER_VOLT.
-pub const STEERING_SPOOF_LOW_SIGNAL_RANGE_MIN: u16 = 656;
-
-/// Minimum allowed value for the low spoof signal value. \[steps\]
-/// Equal to STEERING_SPOOF_LOW_SIGNAL_VOLTAGE_MAX * STEPS_PER_VOLT.
-pub const STEERING_SPOOF_LOW_SIGNAL_RANGE_MAX: u16 = 3358;
-
-pub type SteeringSpoofLowSignal = ranges::Bounded<u16, U656, U3358>;
-
-/// Minimum allowed value for the low spoof signal value. \[steps\]
-/// Equal to STEERING_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN * STEPS_PER_VOLT.
-pub const STEERING_SPOOF_HIGH_SIGNAL_RANGE_MIN: u16 = 738;
-
-/// Minimum allowed value for the low spoof signal value. \[steps\]
-/// Equal to STEERING_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX * STEPS_PER_VOLT.
-pub const STEERING_SPOOF_HIGH_SIGNAL_RANGE_MAX: u16 = 3440;
-
-pub type SteeringSpoofHighSignal = ranges::Bounded<u16, U738, U3440>;
-
-/// Scalar value for the low spoof signal taken from a calibration
-/// curve.
-pub const TORQUE_SPOOF_LOW_SIGNAL_CALIBRATION_CURVE_SCALE: f32 = 0.135;
-
-/// Offset value for the low spoof signal taken from a calibration
-/// curve.
-pub const TORQUE_SPOOF_LOW_SIGNAL_CALIBRATION_CURVE_OFFSET: f32 = 2.39;
-
-/// Scalar value for the high spoof signal taken from a calibration
-/// curve.
-pub const TORQUE_SPOOF_HIGH_SIGNAL_CALIBRATION_CURVE_SCALE: f32 = -0.145;
-
-/// Offset value for the high spoof signal taken from a calibration
-/// curve.
-pub const TORQUE_SPOOF_HIGH_SIGNAL_CALIBRATION_CURVE_OFFSET: f32 = 2.42;
-
-/// Minimum allowed value for the high spoof signal value.
-pub const fn steering_torque_to_volts_low(torque: f32) -> f32 {
-    (TORQUE_SPOOF_HIGH_SIGNAL_CALIBRATION_CURVE_SCALE * torque)
-        + TORQUE_SPOOF_HIGH_SIGNAL_CALIBRATION_CURVE_OFFSET
+/// Optional calibration table of the steering spoof low signal, replaces
+/// the straight line conversion when set. See `calibration_table!`.
+const STEERING_SPOOF_LOW_SIGNAL_CALIBRATION_TABLE: Option<CalibrationTable> = None;
+
+/// Calculation to convert a steering torque to a low spoof voltage.
+fn steering_torque_to_volts_low(torque: f32) -> f32 {
+    match STEERING_SPOOF_LOW_SIGNAL_CALIBRATION_TABLE {
+        Some(table) => calibration::interpolate(table, torque),
+        None => {
+            (TORQUE_SPOOF_LOW_SIGNAL_CALIBRATION_CURVE_SCALE * torque)
+                + TORQUE_SPOOF_LOW_SIGNAL_CALIBRATION_CURVE_OFFSET
+        }
+    }
 }
 
-/// Calculation to convert a steering torque to a low spoof value.
-pub const fn steering_torque_to_volts_high(torque: f32) -> f32 {
-    (TORQUE_SPOOF_LOW_SIGNAL_CALIBRATION_CURVE_SCALE * torque)
-        + TORQUE_SPOOF_LOW_SIGNAL_CALIBRATION_CURVE_OFFSET
+/// Optional calibration table of the steering spoof high signal, replaces
+/// the straight line conversion when set. See `calibration_table!`.
+const STEERING_SPOOF_HIGH_SIGNAL_CALIBRATION_TABLE: Option<CalibrationTable> = None;
+
+/// Calculation to convert a steering torque to a high spoof voltage.
+fn steering_torque_to_volts_high(torque: f32) -> f32 {
+    match STEERING_SPOOF_HIGH_SIGNAL_CALIBRATION_TABLE {
+        Some(table) => calibration::interpolate(table, torque),
+        None => {
+            (TORQUE_SPOOF_HIGH_SIGNAL_CALIBRATION_CURVE_SCALE * torque)
+                + TORQUE_SPOOF_HIGH_SIGNAL_CALIBRATION_CURVE_OFFSET
+        }
+    }
 }
 
-/// Value of torque sensor difference that indicates likely operator
-///        override.
-pub const TORQUE_DIFFERENCE_OVERRIDE_THRESHOLD: u16 = 1600;
-
 // ****************************************************************************
 // THROTTLE MODULE
 // ****************************************************************************
 
-/// Minimum allowable throttle value.
-pub const MINIMUM_THROTTLE_COMMAND: f32 = 0.0;
-
-/// Maximum allowable throttle value.
-pub const MAXIMUM_THROTTLE_COMMAND: f32 = 1.0;
-
-/// Minimum allowed voltage for the low spoof signal voltage. \[volts\]
-pub const THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN: f32 = 0.380;
+/// Optional calibration table of the throttle spoof low signal, replaces
+/// the straight line conversion when set. See `calibration_table!`.
+const THROTTLE_SPOOF_LOW_SIGNAL_CALIBRATION_TABLE: Option<CalibrationTable> = None;
 
-/// Maximum allowed voltage for the low spoof signal voltage. \[volts\]
-pub const THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX: f32 = 2.104;
-
-/// Minimum allowed voltage for the high spoof signal voltage. \[volts\]
-pub const THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN: f32 = 0.757;
-
-/// Maximum allowed voltage for the high spoof signal voltage. \[volts\]
-pub const THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX: f32 = 4.207;
+/// Calculation to convert a throttle position to a low spoof voltage.
+fn throttle_position_to_volts_low(position: f32) -> f32 {
+    match THROTTLE_SPOOF_LOW_SIGNAL_CALIBRATION_TABLE {
+        Some(table) => calibration::interpolate(table, position),
+        None => {
+            position
+                * (THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX - THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN)
+                + THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN
+        }
+    }
+}
 
-/// Minimum allowed value for the low spoof signal value. \[steps\]
-/// Equal to THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN * STEPS_PER_VOLT.
-pub const THROTTLE_SPOOF_LOW_SIGNAL_RANGE_MIN: u16 = 311;
+/// Optional calibration table of the throttle spoof high signal, replaces
+/// the straight line conversion when set. See `calibration_table!`.
+const THROTTLE_SPOOF_HIGH_SIGNAL_CALIBRATION_TABLE: Option<CalibrationTable> = None;
 
-/// Minimum allowed value for the low spoof signal value. \[steps\]
-/// Equal to THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX * STEPS_PER_VOLT.
-pub const THROTTLE_SPOOF_LOW_SIGNAL_RANGE_MAX: u16 = 1723;
+/// Calculation to convert a throttle position to a high spoof voltage.
+fn throttle_position_to_volts_high(position: f32) -> f32 {
+    match THROTTLE_SPOOF_HIGH_SIGNAL_CALIBRATION_TABLE {
+        Some(table) => calibration::interpolate(table, position),
+        None => {
+            position
+                * (THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX - THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN)
+                + THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN
+        }
+    }
+}
 
-pub type ThrottleSpoofLowSignal = ranges::Bounded<u16, U311, U1723>;
+// ****************************************************************************
+// SPEED CONTROL
+// ****************************************************************************
 
-/// Minimum allowed value for the low spoof signal value. \[steps\]
-/// Equal to THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN * STEPS_PER_VOLT.
-pub const THROTTLE_SPOOF_HIGH_SIGNAL_RANGE_MIN: u16 = 620;
+/// Speed control output above which the throttle is pressed.
+const SPEED_CONTROL_THROTTLE_ENGAGE_THRESHOLD: f32 = 0.05;
 
-/// Minimum allowed value for the low spoof signal value. \[steps\]
-/// Equal to THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX * STEPS_PER_VOLT.
-pub const THROTTLE_SPOOF_HIGH_SIGNAL_RANGE_MAX: u16 = 3446;
+/// Speed control output below which a pressed throttle is released.
+const SPEED_CONTROL_THROTTLE_RELEASE_THRESHOLD: f32 = 0.0;
 
-pub type ThrottleSpoofHighSignal = ranges::Bounded<u16, U620, U3446>;
+/// Speed control output below which the brake is pressed.
+const SPEED_CONTROL_BRAKE_ENGAGE_THRESHOLD: f32 = -0.1;
 
-/// Calculation to convert a throttle position to a low spoof voltage.
-pub const fn throttle_position_to_volts_low(position: f32) -> f32 {
-    position * (THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX - THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN)
-        + THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN
-}
+/// Speed control output above which a pressed brake is released.
+const SPEED_CONTROL_BRAKE_RELEASE_THRESHOLD: f32 = -0.02;
 
-/// Calculation to convert a throttle position to a high spoof voltage.
-pub const fn throttle_position_to_volts_high(position: f32) -> f32 {
-    position * (THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX - THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN)
-        + THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN
-}
+// ****************************************************************************
+// VEHICLE PROFILE
+// ****************************************************************************
 
-/// Value of the accelerator position that indicates operator
-/// override. \[steps\]
-pub const ACCELERATOR_OVERRIDE_THRESHOLD: u32 = 185;
+pub const KIA_NIRO_PROFILE: VehicleProfile = VehicleProfile {
+    id: PROFILE_ID,
+    name: PROFILE_NAME,
+    obd_wheel_speed_can_id: OBD_WHEEL_SPEED_CAN_ID,
+    obd_wheel_speed_scalar: OBD_WHEEL_SPEED_SCALAR,
+    obd_wheel_speed_timeout: OBD_WHEEL_SPEED_TIMEOUT,
+    obd_throttle_pressure_can_id: OBD_THROTTLE_PRESSURE_CAN_ID,
+    obd_steering_wheel_angle_can_id: OBD_STEERING_WHEEL_ANGLE_CAN_ID,
+    obd_steering_wheel_angle_scalar: OBD_STEERING_WHEEL_ANGLE_SCALAR,
+    obd_steering_wheel_angle_timeout: OBD_STEERING_WHEEL_ANGLE_TIMEOUT,
+    obd_brake_pressure_can_id: OBD_BRAKE_PRESSURE_CAN_ID,
+    fault_hysteresis: FAULT_HYSTERESIS,
+    fault_hysteresis_limits: FAULT_HYSTERESIS_LIMITS,
+    brake_spoof_low_signal: SpoofSignal {
+        voltage_min: BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN,
+        voltage_max: BRAKE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX,
+        range_min: BRAKE_SPOOF_LOW_SIGNAL_RANGE_MIN,
+        range_max: BRAKE_SPOOF_LOW_SIGNAL_RANGE_MAX,
+        position_to_volts: brake_position_to_volts_low,
+    },
+    brake_spoof_high_signal: SpoofSignal {
+        voltage_min: BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN,
+        voltage_max: BRAKE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX,
+        range_min: BRAKE_SPOOF_HIGH_SIGNAL_RANGE_MIN,
+        range_max: BRAKE_SPOOF_HIGH_SIGNAL_RANGE_MAX,
+        position_to_volts: brake_position_to_volts_high,
+    },
+    brake_override_threshold: BRAKE_PEDAL_OVERRIDE_THRESHOLD as f32,
+    brake_override_threshold_limits: BRAKE_PEDAL_OVERRIDE_THRESHOLD_LIMITS,
+    brake_override_rearm_policy: BRAKE_OVERRIDE_REARM_POLICY,
+    brake_command_slew_rate_limit: BRAKE_COMMAND_SLEW_RATE_LIMIT,
+    brake_light_spoof_low_threshold: BRAKE_LIGHT_SPOOF_LOW_THRESHOLD,
+    brake_light_spoof_high_threshold: BRAKE_LIGHT_SPOOF_HIGH_THRESHOLD,
+    brake_pedal_position_sensor_plausibility: BRAKE_PEDAL_POSITION_SENSOR_PLAUSIBILITY,
+    throttle_spoof_low_signal: SpoofSignal {
+        voltage_min: THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MIN,
+        voltage_max: THROTTLE_SPOOF_LOW_SIGNAL_VOLTAGE_MAX,
+        range_min: THROTTLE_SPOOF_LOW_SIGNAL_RANGE_MIN,
+        range_max: THROTTLE_SPOOF_LOW_SIGNAL_RANGE_MAX,
+        position_to_volts: throttle_position_to_volts_low,
+    },
+    throttle_spoof_high_signal: SpoofSignal {
+        voltage_min: THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN,
+        voltage_max: THROTTLE_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX,
+        range_min: THROTTLE_SPOOF_HIGH_SIGNAL_RANGE_MIN,
+        range_max: THROTTLE_SPOOF_HIGH_SIGNAL_RANGE_MAX,
+        position_to_volts: throttle_position_to_volts_high,
+    },
+    accelerator_override_threshold: ACCELERATOR_OVERRIDE_THRESHOLD as f32,
+    accelerator_override_threshold_limits: ACCELERATOR_OVERRIDE_THRESHOLD_LIMITS,
+    accelerator_position_sensor_plausibility: ACCELERATOR_POSITION_SENSOR_PLAUSIBILITY,
+    throttle_override_rearm_policy: THROTTLE_OVERRIDE_REARM_POLICY,
+    throttle_command_slew_rate_limit: THROTTLE_COMMAND_SLEW_RATE_LIMIT,
+    throttle_interlock_brake_command_threshold: THROTTLE_INTERLOCK_BRAKE_COMMAND_THRESHOLD,
+    throttle_interlock_brake_command_threshold_limits:
+        THROTTLE_INTERLOCK_BRAKE_COMMAND_THRESHOLD_LIMITS,
+    throttle_interlock_brake_pedal_threshold: THROTTLE_INTERLOCK_BRAKE_PEDAL_THRESHOLD as f32,
+    throttle_interlock_brake_pedal_threshold_limits:
+        THROTTLE_INTERLOCK_BRAKE_PEDAL_THRESHOLD_LIMITS,
+    throttle_interlock_action: THROTTLE_INTERLOCK_ACTION,
+    steering_spoof_low_signal: SpoofSignal {
+        voltage_min: STEERING_SPOOF_LOW_SIGNAL_VOLTAGE_MIN,
+        voltage_max: STEERING_SPOOF_LOW_SIGNAL_VOLTAGE_MAX,
+        range_min: STEERING_SPOOF_LOW_SIGNAL_RANGE_MIN,
+        range_max: STEERING_SPOOF_LOW_SIGNAL_RANGE_MAX,
+        position_to_volts: steering_torque_to_volts_low,
+    },
+    steering_spoof_high_signal: SpoofSignal {
+        voltage_min: STEERING_SPOOF_HIGH_SIGNAL_VOLTAGE_MIN,
+        voltage_max: STEERING_SPOOF_HIGH_SIGNAL_VOLTAGE_MAX,
+        range_min: STEERING_SPOOF_HIGH_SIGNAL_RANGE_MIN,
+        range_max: STEERING_SPOOF_HIGH_SIGNAL_RANGE_MAX,
+        position_to_volts: steering_torque_to_volts_high,
+    },
+    minimum_torque_command: MINIMUM_TORQUE_COMMAND,
+    maximum_torque_command: MAXIMUM_TORQUE_COMMAND,
+    steering_command_slew_rate_limit: STEERING_COMMAND_SLEW_RATE_LIMIT,
+    steering_torque_speed_schedule: &STEERING_TORQUE_SPEED_SCHEDULE,
+    torque_sensor_plausibility: TORQUE_SENSOR_PLAUSIBILITY,
+    torque_difference_override_threshold: TORQUE_DIFFERENCE_OVERRIDE_THRESHOLD as f32,
+    torque_difference_override_threshold_limits: TORQUE_DIFFERENCE_OVERRIDE_THRESHOLD_LIMITS,
+    steering_filter_alpha_limits: STEERING_FILTER_ALPHA_LIMITS,
+    steering_override_rearm_policy: STEERING_OVERRIDE_REARM_POLICY,
+    maximum_steering_angle_command: MAXIMUM_STEERING_ANGLE_COMMAND,
+    steering_angle_pid_gains: STEERING_ANGLE_PID_GAINS,
+    steering_angle_pid_windup_guard: STEERING_ANGLE_PID_WINDUP_GUARD,
+    maximum_speed_control_target_speed: MAXIMUM_SPEED_CONTROL_TARGET_SPEED,
+    maximum_speed_control_acceleration_limit: MAXIMUM_SPEED_CONTROL_ACCELERATION_LIMIT,
+    speed_control_pid_gains: SPEED_CONTROL_PID_GAINS,
+    speed_control_pid_windup_guard: SPEED_CONTROL_PID_WINDUP_GUARD,
+    speed_control_pedal_thresholds: SPEED_CONTROL_PEDAL_THRESHOLDS,
+};
@@ -0,0 +1,51 @@
+//! Window watchdog
+//!
+//! The WWDG counter is clocked from PCLK1 (54 MHz) / 4096 / 4, one
+//! counter step is ~0.303 ms.
+//! The counter is reloaded with `WWDG_COUNTER_RELOAD` and a reset is
+//! generated when it goes below 0x40 (~19.4 ms), or when it is refreshed
+//! while still above `WWDG_WINDOW` (earlier than ~0.6 ms).
+//!
+//! Once started, the WWDG can only be disabled by a reset.
+
+use nucleo_f767zi::hal::stm32f7x7::{RCC, WWDG};
+
+/// Counter value loaded on start and refresh
+const WWDG_COUNTER_RELOAD: u32 = 0x7F;
+
+/// Refreshing while the counter is above this value generates a reset
+const WWDG_WINDOW: u32 = 0x7D;
+
+/// Counter clock prescaler, PCLK1 / 4096 / 2^WWDG_PRESCALER
+const WWDG_PRESCALER: u32 = 2;
+
+const WWDG_CR_WDGA: u32 = 1 << 7;
+const WWDG_CFR_WDGTB_SHIFT: u32 = 7;
+
+pub struct Wwdg {
+    wwdg: WWDG,
+}
+
+impl Wwdg {
+    /// Configures the window, the watchdog is not started
+    pub fn new(wwdg: WWDG) -> Self {
+        let rcc = unsafe { &*RCC::ptr() };
+        rcc.apb1enr.modify(|_, w| w.wwdgen().set_bit());
+
+        wwdg.cfr
+            .write(|w| unsafe { w.bits((WWDG_PRESCALER << WWDG_CFR_WDGTB_SHIFT) | WWDG_WINDOW) });
+
+        Wwdg { wwdg }
+    }
+
+    pub fn start(&mut self) {
+        self.refresh();
+    }
+
+    /// Reloads the counter, must only be called inside the window
+    pub fn refresh(&mut self) {
+        self.wwdg
+            .cr
+            .write(|w| unsafe { w.bits(WWDG_CR_WDGA | WWDG_COUNTER_RELOAD) });
+    }
+}